@@ -1,4 +1,7 @@
-use k8s_openapi::api::core::v1::Secret;
+use crate::resource::GeneratedResource;
+use crate::schedule;
+use crate::settings::{self, Defaults, Profile, Settings};
+use chrono::Utc;
 use kube::ResourceExt;
 use sha2::{Digest, Sha256};
 use std::sync::Arc;
@@ -6,76 +9,224 @@ use tracing::{debug, error, info};
 
 pub enum V1Annotation {
     Charset,
+    ClonableBy,
     CloneFrom,
     Generate,
     GeneratedAt,
     GeneratedWithChecksum,
+    GracePeriod,
+    History,
+    HistoryGeneratedAt,
     Length,
+    MaxAge,
     Pause,
     Pattern,
+    PendingSince,
+    Profile,
+    Promote,
     Renewal,
     RenewalCron,
+    RenewalTimezone,
+    RenewalJitter,
+    RolloutRestart,
+    ReplicateTo,
+    RotateRequested,
     ConfigChecksum,
     ForceOverwrite,
+    StagedRotation,
+}
+
+/// What an annotation configures or keeps track of
+enum Scope {
+    /// Configuration of a field, which is part of the config checksum
+    Config,
+    /// Configuration of a field, which doesn't change its value
+    Setting,
+    /// State of a field, which is written by runo
+    State,
+    /// Configuration of the whole resource
+    Resource,
 }
 
 impl V1Annotation {
+    pub const ALL: [V1Annotation; 26] = [
+        V1Annotation::Charset,
+        V1Annotation::ClonableBy,
+        V1Annotation::CloneFrom,
+        V1Annotation::Generate,
+        V1Annotation::GeneratedAt,
+        V1Annotation::GeneratedWithChecksum,
+        V1Annotation::GracePeriod,
+        V1Annotation::History,
+        V1Annotation::HistoryGeneratedAt,
+        V1Annotation::Length,
+        V1Annotation::MaxAge,
+        V1Annotation::Pause,
+        V1Annotation::Pattern,
+        V1Annotation::PendingSince,
+        V1Annotation::Profile,
+        V1Annotation::Promote,
+        V1Annotation::Renewal,
+        V1Annotation::RenewalCron,
+        V1Annotation::RenewalTimezone,
+        V1Annotation::RenewalJitter,
+        V1Annotation::RolloutRestart,
+        V1Annotation::ReplicateTo,
+        V1Annotation::RotateRequested,
+        V1Annotation::ConfigChecksum,
+        V1Annotation::ForceOverwrite,
+        V1Annotation::StagedRotation,
+    ];
+
+    /// The match is exhaustive, so every new annotation has to be assigned to a scope
+    fn scope(&self) -> Scope {
+        match *self {
+            V1Annotation::Charset
+            | V1Annotation::CloneFrom
+            | V1Annotation::Generate
+            | V1Annotation::Length
+            | V1Annotation::Pause
+            | V1Annotation::Pattern
+            | V1Annotation::Profile
+            | V1Annotation::RenewalCron
+            | V1Annotation::ForceOverwrite => Scope::Config,
+            V1Annotation::GracePeriod
+            | V1Annotation::History
+            | V1Annotation::MaxAge
+            | V1Annotation::RenewalTimezone
+            | V1Annotation::RenewalJitter
+            | V1Annotation::StagedRotation => Scope::Setting,
+            V1Annotation::ConfigChecksum
+            | V1Annotation::GeneratedAt
+            | V1Annotation::GeneratedWithChecksum
+            | V1Annotation::HistoryGeneratedAt
+            | V1Annotation::PendingSince
+            | V1Annotation::Promote
+            | V1Annotation::Renewal
+            | V1Annotation::RotateRequested => Scope::State,
+            V1Annotation::ClonableBy | V1Annotation::ReplicateTo | V1Annotation::RolloutRestart => {
+                Scope::Resource
+            }
+        }
+    }
+
     pub fn key(&self) -> String {
         match *self {
             V1Annotation::Charset => "v1.secret.runo.rocks/charset".to_string(),
+            V1Annotation::ClonableBy => "v1.secret.runo.rocks/clonable-by".to_string(),
             V1Annotation::CloneFrom => "v1.secret.runo.rocks/clone-from".to_string(),
             V1Annotation::Generate => "v1.secret.runo.rocks/generate".to_string(),
             V1Annotation::GeneratedAt => "v1.secret.runo.rocks/generated-at".to_string(),
             V1Annotation::GeneratedWithChecksum => {
                 "v1.secret.runo.rocks/generated-with-checksum".to_string()
             }
+            V1Annotation::GracePeriod => "v1.secret.runo.rocks/grace-period".to_string(),
+            V1Annotation::History => "v1.secret.runo.rocks/history".to_string(),
+            V1Annotation::HistoryGeneratedAt => {
+                "v1.secret.runo.rocks/history-generated-at".to_string()
+            }
             V1Annotation::Length => "v1.secret.runo.rocks/length".to_string(),
+            V1Annotation::MaxAge => "v1.secret.runo.rocks/max-age".to_string(),
             V1Annotation::Pause => "v1.secret.runo.rocks/pause".to_string(),
             V1Annotation::Pattern => "v1.secret.runo.rocks/pattern".to_string(),
+            V1Annotation::PendingSince => "v1.secret.runo.rocks/pending-since".to_string(),
+            V1Annotation::Profile => "v1.secret.runo.rocks/profile".to_string(),
+            V1Annotation::Promote => "v1.secret.runo.rocks/promote".to_string(),
             V1Annotation::Renewal => "v1.secret.runo.rocks/renewal".to_string(),
             V1Annotation::RenewalCron => "v1.secret.runo.rocks/renewal-cron".to_string(),
+            V1Annotation::RenewalTimezone => "v1.secret.runo.rocks/renewal-timezone".to_string(),
+            V1Annotation::RenewalJitter => "v1.secret.runo.rocks/renewal-jitter".to_string(),
+            V1Annotation::RolloutRestart => "v1.secret.runo.rocks/rollout-restart".to_string(),
+            V1Annotation::ReplicateTo => "v1.secret.runo.rocks/replicate-to".to_string(),
+            V1Annotation::RotateRequested => "v1.secret.runo.rocks/rotate-requested".to_string(),
             V1Annotation::ConfigChecksum => "v1.secret.runo.rocks/config-checksum".to_string(),
             V1Annotation::ForceOverwrite => "v1.secret.runo.rocks/force-overwrite".to_string(),
+            V1Annotation::StagedRotation => "v1.secret.runo.rocks/staged-rotation".to_string(),
         }
     }
     pub fn value(&self, id: &str) -> String {
         match *self {
             V1Annotation::Charset => format!("{}-{}", V1Annotation::Charset.key(), id),
+            V1Annotation::ClonableBy => format!("{}-{}", V1Annotation::ClonableBy.key(), id),
             V1Annotation::CloneFrom => format!("{}-{}", V1Annotation::CloneFrom.key(), id),
             V1Annotation::Generate => format!("{}-{}", V1Annotation::Generate.key(), id),
             V1Annotation::GeneratedAt => format!("{}-{}", V1Annotation::GeneratedAt.key(), id),
             V1Annotation::GeneratedWithChecksum => {
                 format!("{}-{}", V1Annotation::GeneratedWithChecksum.key(), id)
             }
+            V1Annotation::GracePeriod => format!("{}-{}", V1Annotation::GracePeriod.key(), id),
+            V1Annotation::History => format!("{}-{}", V1Annotation::History.key(), id),
+            V1Annotation::HistoryGeneratedAt => {
+                format!("{}-{}", V1Annotation::HistoryGeneratedAt.key(), id)
+            }
             V1Annotation::Length => format!("{}-{}", V1Annotation::Length.key(), id),
+            V1Annotation::MaxAge => format!("{}-{}", V1Annotation::MaxAge.key(), id),
             V1Annotation::Pause => format!("{}-{}", V1Annotation::Pause.key(), id),
             V1Annotation::Pattern => format!("{}-{}", V1Annotation::Pattern.key(), id),
+            V1Annotation::PendingSince => {
+                format!("{}-{}", V1Annotation::PendingSince.key(), id)
+            }
+            V1Annotation::Profile => format!("{}-{}", V1Annotation::Profile.key(), id),
+            V1Annotation::Promote => format!("{}-{}", V1Annotation::Promote.key(), id),
             V1Annotation::Renewal => format!("{}-{}", V1Annotation::Renewal.key(), id),
             V1Annotation::RenewalCron => format!("{}-{}", V1Annotation::RenewalCron.key(), id),
+            V1Annotation::RenewalTimezone => {
+                format!("{}-{}", V1Annotation::RenewalTimezone.key(), id)
+            }
+            V1Annotation::RenewalJitter => {
+                format!("{}-{}", V1Annotation::RenewalJitter.key(), id)
+            }
+            V1Annotation::RolloutRestart => {
+                format!("{}-{}", V1Annotation::RolloutRestart.key(), id)
+            }
+            V1Annotation::ReplicateTo => format!("{}-{}", V1Annotation::ReplicateTo.key(), id),
+            V1Annotation::RotateRequested => {
+                format!("{}-{}", V1Annotation::RotateRequested.key(), id)
+            }
             V1Annotation::ConfigChecksum => {
                 format!("{}-{}", V1Annotation::ConfigChecksum.key(), id)
             }
             V1Annotation::ForceOverwrite => {
                 format!("{}-{}", V1Annotation::ForceOverwrite.key(), id)
             }
+            V1Annotation::StagedRotation => {
+                format!("{}-{}", V1Annotation::StagedRotation.key(), id)
+            }
         }
     }
-    fn default(&self) -> Option<String> {
+    fn default(&self, defaults: &Defaults) -> Option<String> {
         match *self {
-            V1Annotation::Charset => {
-                Some("abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789".to_string())
-            }
+            V1Annotation::Charset => Some(
+                defaults
+                    .charset
+                    .clone()
+                    .unwrap_or(settings::DEFAULT_CHARSET.to_string()),
+            ),
             V1Annotation::Generate => None,
             V1Annotation::GeneratedAt => None,
             V1Annotation::GeneratedWithChecksum => None,
-            V1Annotation::Length => Some("32".to_string()),
+            V1Annotation::GracePeriod => None,
+            V1Annotation::History => Some("0".to_string()),
+            V1Annotation::HistoryGeneratedAt => None,
+            V1Annotation::Length => Some(defaults.length.to_string()),
+            V1Annotation::MaxAge => None,
             V1Annotation::Pause => None,
-            V1Annotation::Pattern => Some("[a-zA-Z0-9\\-\\_\\(\\)\\%\\$\\@]".to_string()),
+            V1Annotation::Pattern => Some(defaults.pattern.clone()),
+            V1Annotation::PendingSince => None,
+            V1Annotation::Profile => None,
+            V1Annotation::Promote => None,
             V1Annotation::Renewal => None,
             V1Annotation::RenewalCron => None,
+            V1Annotation::RenewalTimezone => None,
+            V1Annotation::RenewalJitter => None,
+            V1Annotation::RolloutRestart => Some("false".to_string()),
+            V1Annotation::ReplicateTo => None,
+            V1Annotation::RotateRequested => None,
             V1Annotation::ConfigChecksum => None,
             V1Annotation::ForceOverwrite => Some("false".to_string()),
+            V1Annotation::ClonableBy => None,
             V1Annotation::CloneFrom => None,
+            V1Annotation::StagedRotation => Some("false".to_string()),
         }
     }
 }
@@ -101,19 +252,19 @@ impl<T> AnnotationResult<T> {
     }
 }
 
-fn already_set(obj: &Arc<Secret>, id: &str) -> bool {
+fn already_set(obj: &Arc<impl GeneratedResource>, id: &str) -> bool {
     let generate = generate(obj, id);
-    match obj.data.as_ref() {
+    match obj.values().as_ref() {
         Some(d) => d.get(&generate.get_value()).is_some(),
         None => false,
     }
 }
 
-fn should_force_overwrite(obj: &Arc<Secret>, id: &str) -> bool {
+fn should_force_overwrite(obj: &Arc<impl GeneratedResource>, id: &str) -> bool {
     force_overwrite(obj, id).get_value() == "true"
 }
 
-pub fn needs_clone(obj: &Arc<Secret>, id: &str) -> bool {
+pub fn needs_clone(obj: &Arc<impl GeneratedResource>, id: &str) -> bool {
     if !clone_from(obj, id).exists() {
         return false;
     }
@@ -124,7 +275,7 @@ pub fn needs_clone(obj: &Arc<Secret>, id: &str) -> bool {
     true
 }
 
-pub fn needs_generation(obj: &Arc<Secret>, id: &str) -> bool {
+pub fn needs_generation(obj: &Arc<impl GeneratedResource>, id: &str, settings: &Settings) -> bool {
     if generate(obj, id).exists() {
         if is_paused(obj, id) {
             debug!("Skip generation for paused field with id: {}", id);
@@ -141,6 +292,10 @@ pub fn needs_generation(obj: &Arc<Secret>, id: &str) -> bool {
                 && generated_with_checksum.exists()
                 && (checksum.get_value() != generated_with_checksum.get_value())
             {
+                if generated_with_legacy_checksum(obj, id, settings) {
+                    debug!("Skip generation since field was generated with a legacy checksum");
+                    return false;
+                }
                 info!("(Re)generation of secret because checksum changed");
                 return true;
             }
@@ -154,11 +309,26 @@ pub fn needs_generation(obj: &Arc<Secret>, id: &str) -> bool {
     false
 }
 
-pub fn needs_renewal(obj: &Arc<Secret>, id: &str) -> bool {
+pub fn needs_renewal(obj: &Arc<impl GeneratedResource>, id: &str, settings: &Settings) -> bool {
     if is_paused(obj, id) {
         debug!("Skip renewal for paused field with id: {}", id);
         return false;
     }
+    if is_rotate_requested(obj, id) {
+        info!("Renewal of field with id {} was requested", id);
+        return true;
+    }
+    if schedule::max_age_exceeded(obj, id, &Utc::now(), settings) {
+        info!(
+            "Renewal of field with id {} because max age is exceeded",
+            id
+        );
+        return true;
+    }
+    if schedule::renewal_deferred(obj, id, &Utc::now(), settings) {
+        debug!("Defer renewal of field with id {} because of jitter", id);
+        return false;
+    }
     let renewal_v1 = V1Annotation::Renewal.value(id);
     match obj.annotations().get(&renewal_v1) {
         Some(val) => {
@@ -178,37 +348,148 @@ pub fn needs_renewal(obj: &Arc<Secret>, id: &str) -> bool {
     }
 }
 
-pub fn create_checksum(obj: &Arc<Secret>, id: &str) -> String {
+/// A pending value which was staged by a renewal gets promoted to the field once it was
+/// acknowledged or the grace period is over
+pub fn needs_promotion(obj: &Arc<impl GeneratedResource>, id: &str, settings: &Settings) -> bool {
+    if !has_pending(obj, id) {
+        return false;
+    }
+    if is_paused(obj, id) {
+        debug!("Skip promotion for paused field with id: {}", id);
+        return false;
+    }
+    if is_promotion_acknowledged(obj, id) {
+        info!("Promotion of pending value for id {} was acknowledged", id);
+        return true;
+    }
+    if schedule::grace_period_exceeded(obj, id, &Utc::now(), settings) {
+        info!(
+            "Promotion of pending value for id {} after grace period",
+            id
+        );
+        return true;
+    }
+    false
+}
+
+/// Profiles only change the checksum if they allow regeneration, otherwise changes of a profile
+/// apply to new fields and renewals only
+pub fn create_checksum(obj: &Arc<impl GeneratedResource>, id: &str, settings: &Settings) -> String {
     let mut hasher = Sha256::new();
     for annotation in get_annotation_values_for_id(obj, id) {
         hasher.update(annotation);
     }
+    if let Some(profile) = referenced_profile(obj, id, settings).filter(|p| p.regenerate) {
+        hasher.update(profile.fingerprint());
+    }
+    let hash = hasher.finalize();
+    hash.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Checksum of releases which hashed every annotation of a field except the checksums and
+/// `generated-at`, including `renewal` and `history`
+fn legacy_checksum(obj: &Arc<impl GeneratedResource>, id: &str) -> String {
+    let excluded = [
+        V1Annotation::ConfigChecksum,
+        V1Annotation::GeneratedWithChecksum,
+        V1Annotation::GeneratedAt,
+    ];
+    let mut hasher = Sha256::new();
+    obj.annotations()
+        .iter()
+        .filter(|p| !excluded.iter().any(|a| p.0.starts_with(a.key().as_str())))
+        .filter(|p| p.0.ends_with(format!("-{}", id).as_str()))
+        .for_each(|p| hasher.update(p.1));
     let hash = hasher.finalize();
     hash.iter().map(|b| format!("{:02x}", b)).collect()
 }
 
-fn get_annotation_values_for_id<'a>(obj: &'a Arc<Secret>, id: &'a str) -> Vec<&'a String> {
+/// Fields generated before the upgrade carry a legacy checksum. If it still matches the
+/// annotations, the config didn't change and only the checksum has to be migrated.
+pub fn generated_with_legacy_checksum(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    settings: &Settings,
+) -> bool {
+    let generated_with_checksum = generated_with_checksum(obj, id);
+    generated_with_checksum.exists()
+        && generated_with_checksum.get_value() == legacy_checksum(obj, id)
+        && legacy_checksum(obj, id) != create_checksum(obj, id, settings)
+}
+
+fn get_annotation_values_for_id<'a>(
+    obj: &'a Arc<impl GeneratedResource>,
+    id: &'a str,
+) -> Vec<&'a String> {
     let annotations_for_id: Vec<(&String, &String)> = obj
         .annotations()
         .iter()
-        .filter(|p| !p.0.starts_with(V1Annotation::ConfigChecksum.key().as_str()))
-        .filter(|p| {
-            !p.0.starts_with(V1Annotation::GeneratedWithChecksum.key().as_str())
-        })
-        .filter(|p| !p.0.starts_with(V1Annotation::GeneratedAt.key().as_str()))
+        .filter(|p| is_config_annotation(p.0, id))
         .filter(|p| p.0.ends_with(format!("-{}", id).as_str()))
         .collect();
     annotations_for_id.iter().map(|p| p.1).collect()
 }
 
-pub fn has_cron(obj: &Arc<Secret>, id: &str) -> bool {
-    let renewal_cron = renewal_cron(obj, id);
+/// Annotations which hold state, trigger actions or don't influence the generated value are
+/// not part of the config checksum
+fn is_config_annotation(key: &str, id: &str) -> bool {
+    !V1Annotation::ALL
+        .iter()
+        .filter(|a| matches!(a.scope(), Scope::Setting | Scope::State))
+        .any(|a| a.value(id) == key)
+}
+
+/// Annotations which belong to a single field, in contrast to `rollout-restart` and
+/// `replicate-to` which apply to the whole Secret
+pub fn is_field_annotation(key: &str, id: &str) -> bool {
+    V1Annotation::ALL
+        .iter()
+        .filter(|a| !matches!(a.scope(), Scope::Resource))
+        .any(|a| a.value(id) == key)
+}
+
+/// Annotations which are written by runo itself to keep track of the state of a field
+pub fn is_state_annotation(key: &str, id: &str) -> bool {
+    V1Annotation::ALL
+        .iter()
+        .filter(|a| matches!(a.scope(), Scope::State))
+        .any(|a| a.value(id) == key)
+}
+
+pub fn has_cron(obj: &Arc<impl GeneratedResource>, id: &str, settings: &Settings) -> bool {
+    let renewal_cron = renewal_cron(obj, id, settings);
     !renewal_cron.is_default()
 }
 
-pub fn length(obj: &Arc<Secret>, id: &str) -> AnnotationResult<usize> {
-    let length_v1 = V1Annotation::Length.value(id);
-    match obj.annotations().get(&length_v1) {
+/// Profile referenced by a field with `profile-<id>`, if it's defined in the config file
+pub fn referenced_profile(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    settings: &Settings,
+) -> Option<Profile> {
+    let name = obj.annotations().get(&V1Annotation::Profile.value(id))?;
+    settings.profiles.get(name).cloned()
+}
+
+/// Value of the annotation of a field or, if it isn't set, of the profile of the field
+fn configured_value(
+    obj: &Arc<impl GeneratedResource>,
+    annotation: &V1Annotation,
+    id: &str,
+    settings: &Settings,
+) -> Option<String> {
+    obj.annotations()
+        .get(&annotation.value(id))
+        .cloned()
+        .or_else(|| referenced_profile(obj, id, settings).and_then(|p| p.value(annotation)))
+}
+
+pub fn length(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    settings: &Settings,
+) -> AnnotationResult<usize> {
+    match configured_value(obj, &V1Annotation::Length, id, settings) {
         Some(value) => {
             let length = value.parse::<i32>().unwrap() as usize;
             match length > 0 && length <= 100 {
@@ -219,7 +500,7 @@ pub fn length(obj: &Arc<Secret>, id: &str) -> AnnotationResult<usize> {
                 },
                 false => {
                     error!("Invalid length! Please set a length > 0 and <= 100. Proceeding with default length.");
-                    match V1Annotation::Length.default() {
+                    match V1Annotation::Length.default(&settings.defaults) {
                         Some(default) => AnnotationResult {
                             value: default.parse::<i32>().unwrap() as usize,
                             default: true,
@@ -230,7 +511,7 @@ pub fn length(obj: &Arc<Secret>, id: &str) -> AnnotationResult<usize> {
                 }
             }
         }
-        None => match V1Annotation::Length.default() {
+        None => match V1Annotation::Length.default(&settings.defaults) {
             Some(default) => AnnotationResult {
                 value: default.parse::<i32>().unwrap() as usize,
                 default: true,
@@ -241,58 +522,171 @@ pub fn length(obj: &Arc<Secret>, id: &str) -> AnnotationResult<usize> {
     }
 }
 
+pub fn history(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    settings: &Settings,
+) -> AnnotationResult<usize> {
+    let default = || AnnotationResult {
+        value: V1Annotation::History
+            .default(&settings.defaults)
+            .and_then(|d| d.parse::<usize>().ok())
+            .unwrap_or_default(),
+        default: true,
+        exists: false,
+    };
+    match configured_value(obj, &V1Annotation::History, id, settings) {
+        Some(value) => match value.parse::<usize>() {
+            Ok(history) if history <= 10 => AnnotationResult {
+                value: history,
+                default: false,
+                exists: true,
+            },
+            _ => {
+                error!("Invalid history! Please set a history >= 0 and <= 10. Proceeding without history.");
+                default()
+            }
+        },
+        None => default(),
+    }
+}
+
+pub fn history_generated_at(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+) -> AnnotationResult<String> {
+    _annotation_result(obj, V1Annotation::HistoryGeneratedAt, id)
+}
+
 fn _annotation_result(
-    obj: &Arc<Secret>,
+    obj: &Arc<impl GeneratedResource>,
+    annotation: V1Annotation,
+    id: &str,
+) -> AnnotationResult<String> {
+    let value = obj.annotations().get(&annotation.value(id)).cloned();
+    _result(value, annotation.default(&Defaults::default()))
+}
+
+/// Like `_annotation_result`, but falls back to the profile of the field and the defaults of
+/// the config file
+fn _configured_result(
+    obj: &Arc<impl GeneratedResource>,
     annotation: V1Annotation,
     id: &str,
+    settings: &Settings,
 ) -> AnnotationResult<String> {
-    match obj.annotations().get(annotation.value(id).as_str()) {
+    let value = configured_value(obj, &annotation, id, settings);
+    _result(value, annotation.default(&settings.defaults))
+}
+
+fn _result(value: Option<String>, default: Option<String>) -> AnnotationResult<String> {
+    match value {
         Some(value) => AnnotationResult {
-            value: value.to_string(),
+            value,
             default: false,
             exists: true,
         },
         None => AnnotationResult {
-            value: annotation.default().unwrap_or("".to_string()),
+            value: default.unwrap_or("".to_string()),
             default: true,
             exists: false,
         },
     }
 }
 
-pub fn charset(obj: &Arc<Secret>, id: &str) -> AnnotationResult<String> {
-    _annotation_result(obj, V1Annotation::Charset, id)
+pub fn charset(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    settings: &Settings,
+) -> AnnotationResult<String> {
+    _configured_result(obj, V1Annotation::Charset, id, settings)
 }
 
-pub fn pattern(obj: &Arc<Secret>, id: &str) -> AnnotationResult<String> {
-    _annotation_result(obj, V1Annotation::Pattern, id)
+/// Fields are generated from a charset if they have a `charset` annotation or if neither
+/// `charset` nor `pattern` is set and their profile or the config file has a charset
+pub fn uses_charset(obj: &Arc<impl GeneratedResource>, id: &str, settings: &Settings) -> bool {
+    let annotations = obj.annotations();
+    if annotations.contains_key(&V1Annotation::Charset.value(id)) {
+        return true;
+    }
+    if annotations.contains_key(&V1Annotation::Pattern.value(id)) {
+        return false;
+    }
+    match referenced_profile(obj, id, settings) {
+        Some(profile) if profile.charset.is_some() => true,
+        Some(profile) if profile.pattern.is_some() => false,
+        _ => settings.defaults.charset.is_some(),
+    }
+}
+
+pub fn profile(obj: &Arc<impl GeneratedResource>, id: &str) -> AnnotationResult<String> {
+    _annotation_result(obj, V1Annotation::Profile, id)
 }
 
-pub fn generated_at(obj: &Arc<Secret>, id: &str) -> AnnotationResult<String> {
+pub fn pattern(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    settings: &Settings,
+) -> AnnotationResult<String> {
+    _configured_result(obj, V1Annotation::Pattern, id, settings)
+}
+
+pub fn generated_at(obj: &Arc<impl GeneratedResource>, id: &str) -> AnnotationResult<String> {
     _annotation_result(obj, V1Annotation::GeneratedAt, id)
 }
 
-pub fn generated_with_checksum(obj: &Arc<Secret>, id: &str) -> AnnotationResult<String> {
+pub fn generated_with_checksum(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+) -> AnnotationResult<String> {
     _annotation_result(obj, V1Annotation::GeneratedWithChecksum, id)
 }
 
-pub fn renewal_cron(obj: &Arc<Secret>, id: &str) -> AnnotationResult<String> {
-    _annotation_result(obj, V1Annotation::RenewalCron, id)
+pub fn renewal_cron(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    settings: &Settings,
+) -> AnnotationResult<String> {
+    _configured_result(obj, V1Annotation::RenewalCron, id, settings)
+}
+
+pub fn renewal_timezone(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    settings: &Settings,
+) -> AnnotationResult<String> {
+    _configured_result(obj, V1Annotation::RenewalTimezone, id, settings)
+}
+
+pub fn renewal_jitter(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    settings: &Settings,
+) -> AnnotationResult<String> {
+    _configured_result(obj, V1Annotation::RenewalJitter, id, settings)
 }
 
-pub fn generate(obj: &Arc<Secret>, id: &str) -> AnnotationResult<String> {
+pub fn max_age(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    settings: &Settings,
+) -> AnnotationResult<String> {
+    _configured_result(obj, V1Annotation::MaxAge, id, settings)
+}
+
+pub fn generate(obj: &Arc<impl GeneratedResource>, id: &str) -> AnnotationResult<String> {
     _annotation_result(obj, V1Annotation::Generate, id)
 }
 
-pub fn force_overwrite(obj: &Arc<Secret>, id: &str) -> AnnotationResult<String> {
+pub fn force_overwrite(obj: &Arc<impl GeneratedResource>, id: &str) -> AnnotationResult<String> {
     _annotation_result(obj, V1Annotation::ForceOverwrite, id)
 }
 
-pub fn clone_from(obj: &Arc<Secret>, id: &str) -> AnnotationResult<String> {
+pub fn clone_from(obj: &Arc<impl GeneratedResource>, id: &str) -> AnnotationResult<String> {
     _annotation_result(obj, V1Annotation::CloneFrom, id)
 }
 
-pub fn id_iter(obj: &Arc<Secret>) -> Vec<String> {
+pub fn id_iter(obj: &Arc<impl GeneratedResource>) -> Vec<String> {
     let prefix = format!("{}-", V1Annotation::Generate.key());
     obj.annotations()
         .keys()
@@ -302,12 +696,85 @@ pub fn id_iter(obj: &Arc<Secret>) -> Vec<String> {
 }
 
 #[allow(dead_code)]
-pub fn checksum(obj: &Arc<Secret>, id: &str) -> AnnotationResult<String> {
+pub fn checksum(obj: &Arc<impl GeneratedResource>, id: &str) -> AnnotationResult<String> {
     _annotation_result(obj, V1Annotation::ConfigChecksum, id)
 }
 
+/// Check if workloads consuming the Secret should be restarted after a value changed. In
+/// contrast to the other annotations, this one applies to the whole Secret.
+pub fn rollout_restart(obj: &Arc<impl GeneratedResource>) -> bool {
+    obj.annotations()
+        .get(&V1Annotation::RolloutRestart.key())
+        .and_then(|val| val.parse::<bool>().ok())
+        .unwrap_or_default()
+}
+
+/// Namespaces or label selector of namespaces the Secret is replicated to. Like
+/// `rollout-restart`, this annotation applies to the whole Secret.
+pub fn replicate_to(obj: &Arc<impl GeneratedResource>) -> Option<String> {
+    obj.annotations()
+        .get(&V1Annotation::ReplicateTo.key())
+        .map(|val| val.trim().to_string())
+        .filter(|val| !val.is_empty())
+}
+
+/// Namespaces whose resources may clone fields of this one. Like `replicate-to`, this
+/// annotation applies to the whole resource.
+pub fn clonable_by(obj: &Arc<impl GeneratedResource>) -> Vec<String> {
+    obj.annotations()
+        .get(&V1Annotation::ClonableBy.key())
+        .map(|val| {
+            val.split(',')
+                .map(|ns| ns.trim().to_string())
+                .filter(|ns| !ns.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Check if a specific field (by id) is rotated in two phases
+pub fn is_staged(obj: &Arc<impl GeneratedResource>, id: &str, settings: &Settings) -> bool {
+    _configured_result(obj, V1Annotation::StagedRotation, id, settings)
+        .get_value()
+        .parse::<bool>()
+        .unwrap_or_default()
+}
+
+/// Check if a specific field (by id) has a staged value waiting for promotion
+pub fn has_pending(obj: &Arc<impl GeneratedResource>, id: &str) -> bool {
+    pending_since(obj, id).exists()
+}
+
+fn is_promotion_acknowledged(obj: &Arc<impl GeneratedResource>, id: &str) -> bool {
+    _annotation_result(obj, V1Annotation::Promote, id)
+        .get_value()
+        .parse::<bool>()
+        .unwrap_or_default()
+}
+
+pub fn pending_since(obj: &Arc<impl GeneratedResource>, id: &str) -> AnnotationResult<String> {
+    _annotation_result(obj, V1Annotation::PendingSince, id)
+}
+
+pub fn grace_period(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    settings: &Settings,
+) -> AnnotationResult<String> {
+    _configured_result(obj, V1Annotation::GracePeriod, id, settings)
+}
+
+/// Check if the renewal of a specific field (by id) was requested manually, e.g. by
+/// `runo rotate`. In contrast to a due renewal cron, the request isn't deferred by the jitter.
+pub fn is_rotate_requested(obj: &Arc<impl GeneratedResource>, id: &str) -> bool {
+    _annotation_result(obj, V1Annotation::RotateRequested, id)
+        .get_value()
+        .parse::<bool>()
+        .unwrap_or_default()
+}
+
 /// Check if a specific field (by id) is paused
-pub fn is_paused(obj: &Arc<Secret>, id: &str) -> bool {
+pub fn is_paused(obj: &Arc<impl GeneratedResource>, id: &str) -> bool {
     let pause_v1 = V1Annotation::Pause.value(id);
     obj.annotations()
         .get(&pause_v1)
@@ -317,50 +784,73 @@ pub fn is_paused(obj: &Arc<Secret>, id: &str) -> bool {
 
 #[cfg(test)]
 mod tests {
+    use crate::settings::Settings;
+    use crate::test_utils::build_secret_with_annotations;
     use chrono::{DateTime, Utc};
-    use k8s_openapi::api::core::v1::Secret;
-    use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
     use k8s_openapi::ByteString;
     use rstest::*;
 
     use std::collections::BTreeMap;
 
-    use crate::annotations::create_checksum;
+    use crate::annotations::{create_checksum, V1Annotation};
     use std::sync::Arc;
     use std::time::SystemTime;
 
-    fn build_secret_with_annotations(annotations: Vec<(String, String)>) -> Secret {
-        let annotation_map = annotations
+    #[rstest]
+    #[case("v1.secret.runo.rocks/renewal-0", false)]
+    #[case("v1.secret.runo.rocks/rotate-requested-0", true)]
+    fn requested_renewal_isnt_deferred_by_jitter(#[case] key: &str, #[case] renewed: bool) {
+        // the cron fired a minute ago, the jitter defers its renewal
+        let generated_at = (Utc::now().timestamp() - 120).to_string();
+        let secret = build_secret_with_annotations(
+            vec![
+                ("v1.secret.runo.rocks/generate-0", "password"),
+                ("v1.secret.runo.rocks/generated-at-0", generated_at.as_str()),
+                ("v1.secret.runo.rocks/renewal-cron-0", "* * * * *"),
+                ("v1.secret.runo.rocks/renewal-jitter-0", "1w"),
+                (key, "true"),
+            ]
             .into_iter()
-            .collect::<BTreeMap<String, String>>();
-        Secret {
-            metadata: ObjectMeta {
-                annotations: Some(annotation_map),
-                ..ObjectMeta::default()
-            },
-            ..Secret::default()
-        }
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        );
+        assert_eq!(
+            crate::annotations::needs_renewal(&Arc::new(secret), "0", &Settings::default()),
+            renewed
+        );
     }
 
     #[rstest]
     #[case("v1.secret.runo.rocks/generated-at-0", "000000000")]
     fn v1_already_generated_is_true(#[case] key: String, #[case] value: String) {
         let secret = build_secret_with_annotations(vec![(key, value)]);
-        assert!(!crate::annotations::needs_renewal(&Arc::new(secret), "0"));
+        assert!(!crate::annotations::needs_renewal(
+            &Arc::new(secret),
+            "0",
+            &Settings::default()
+        ));
     }
 
     #[rstest]
     #[case("v1.secret.runo.rocks/renewal-0", "true")]
     fn v1_needs_renewal_is_true(#[case] key: String, #[case] value: String) {
         let secret = build_secret_with_annotations(vec![(key, value)]);
-        assert!(crate::annotations::needs_renewal(&Arc::new(secret), "0"));
+        assert!(crate::annotations::needs_renewal(
+            &Arc::new(secret),
+            "0",
+            &Settings::default()
+        ));
     }
 
     #[rstest]
     #[case("v1.secret.runo.rocks/not-a-valid-annotation", "true")]
     fn v1_no_valid_annotation(#[case] key: String, #[case] value: String) {
         let secret = build_secret_with_annotations(vec![(key, value)]);
-        assert!(!crate::annotations::needs_renewal(&Arc::new(secret), "0"));
+        assert!(!crate::annotations::needs_renewal(
+            &Arc::new(secret),
+            "0",
+            &Settings::default()
+        ));
     }
 
     #[rstest]
@@ -369,7 +859,11 @@ mod tests {
     #[case("v1.secret.runo.rocks/renewal-0", "")]
     fn v1_needs_renewal_parse_error(#[case] key: String, #[case] value: String) {
         let secret = build_secret_with_annotations(vec![(key, value)]);
-        assert!(!crate::annotations::needs_renewal(&Arc::new(secret), "0"));
+        assert!(!crate::annotations::needs_renewal(
+            &Arc::new(secret),
+            "0",
+            &Settings::default()
+        ));
     }
 
     #[rstest]
@@ -377,7 +871,7 @@ mod tests {
     fn v1_length(#[case] key: String, #[case] value: String) {
         let secret = build_secret_with_annotations(vec![(key, value)]);
         assert_eq!(
-            crate::annotations::length(&Arc::new(secret), "0").get_value(),
+            crate::annotations::length(&Arc::new(secret), "0", &Settings::default()).get_value(),
             10
         );
     }
@@ -386,7 +880,9 @@ mod tests {
     #[case("v1.secret.runo.rocks/length-0", "1")]
     fn v1_length_returns_default(#[case] key: String, #[case] value: String) {
         let secret = build_secret_with_annotations(vec![(key, value)]);
-        assert!(crate::annotations::length(&Arc::new(secret), "1").is_default());
+        assert!(
+            crate::annotations::length(&Arc::new(secret), "1", &Settings::default()).is_default()
+        );
     }
 
     #[rstest]
@@ -395,7 +891,9 @@ mod tests {
     #[case("v1.secret.runo.rocks/length-0", "101")]
     fn v1_length_invalid(#[case] key: String, #[case] value: String) {
         let secret = build_secret_with_annotations(vec![(key, value)]);
-        assert!(crate::annotations::length(&Arc::new(secret), "0").is_default());
+        assert!(
+            crate::annotations::length(&Arc::new(secret), "0", &Settings::default()).is_default()
+        );
     }
 
     #[rstest]
@@ -403,7 +901,7 @@ mod tests {
     fn v1_charset(#[case] key: String, #[case] value: String) {
         let secret = build_secret_with_annotations(vec![(key, value)]);
         assert_eq!(
-            crate::annotations::charset(&Arc::new(secret), "0").get_value(),
+            crate::annotations::charset(&Arc::new(secret), "0", &Settings::default()).get_value(),
             "abc"
         );
     }
@@ -412,7 +910,9 @@ mod tests {
     #[case("v1.secret.runo.rocks/charset-0", "")]
     fn v1_charset_returns_default(#[case] key: String, #[case] value: String) {
         let secret = build_secret_with_annotations(vec![(key, value)]);
-        assert!(crate::annotations::charset(&Arc::new(secret), "1").is_default());
+        assert!(
+            crate::annotations::charset(&Arc::new(secret), "1", &Settings::default()).is_default()
+        );
     }
 
     #[rstest]
@@ -420,7 +920,7 @@ mod tests {
     fn v1_pattern(#[case] key: String, #[case] value: String) {
         let secret = build_secret_with_annotations(vec![(key, value)]);
         assert_eq!(
-            crate::annotations::pattern(&Arc::new(secret), "0").get_value(),
+            crate::annotations::pattern(&Arc::new(secret), "0", &Settings::default()).get_value(),
             "[abc]"
         );
     }
@@ -429,7 +929,9 @@ mod tests {
     #[case("v1.secret.runo.rocks/pattern-0", "")]
     fn v1_pattern_returns_default(#[case] key: String, #[case] value: String) {
         let secret = build_secret_with_annotations(vec![(key, value)]);
-        assert!(crate::annotations::pattern(&Arc::new(secret), "1").is_default());
+        assert!(
+            crate::annotations::pattern(&Arc::new(secret), "1", &Settings::default()).is_default()
+        );
     }
 
     #[rstest]
@@ -453,13 +955,20 @@ mod tests {
     #[case("v1.secret.runo.rocks/renewal-cron-0", "true")]
     fn v1_has_cron_is_true(#[case] key: String, #[case] value: String) {
         let secret = build_secret_with_annotations(vec![(key, value)]);
-        assert!(crate::annotations::has_cron(&Arc::new(secret), "0"));
+        assert!(crate::annotations::has_cron(
+            &Arc::new(secret),
+            "0",
+            &Settings::default()
+        ));
     }
 
     #[rstest]
     fn v1_cron_renewal_cron_returns_default() {
         let secret = build_secret_with_annotations(vec![]);
-        assert!(crate::annotations::renewal_cron(&Arc::new(secret), "0").is_default());
+        assert!(
+            crate::annotations::renewal_cron(&Arc::new(secret), "0", &Settings::default())
+                .is_default()
+        );
     }
 
     #[rstest]
@@ -496,10 +1005,74 @@ mod tests {
     )]
     fn v1_config_checksum_create(#[case] key: String, #[case] value: String, #[case] hash: String) {
         let secret = build_secret_with_annotations(vec![(key, value.to_string())]);
-        let checksum = create_checksum(&Arc::new(secret.clone()), "0");
+        let checksum = create_checksum(&Arc::new(secret.clone()), "0", &Settings::default());
         assert_eq!(checksum, hash);
     }
 
+    #[rstest]
+    fn adding_max_age_does_not_regenerate() {
+        let generated = vec![
+            (
+                "v1.secret.runo.rocks/generate-0".to_string(),
+                "username".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/generated-at-0".to_string(),
+                "2024-01-01T00:00:00+00:00".to_string(),
+            ),
+        ];
+        let checksum = create_checksum(
+            &Arc::new(build_secret_with_annotations(generated.clone())),
+            "0",
+            &Settings::default(),
+        );
+        let mut annotations = generated;
+        annotations.push((
+            "v1.secret.runo.rocks/max-age-0".to_string(),
+            "30d".to_string(),
+        ));
+        let with_max_age = Arc::new(build_secret_with_annotations(annotations.clone()));
+        assert_eq!(
+            create_checksum(&with_max_age, "0", &Settings::default()),
+            checksum
+        );
+        annotations.push((
+            "v1.secret.runo.rocks/config-checksum-0".to_string(),
+            create_checksum(&with_max_age, "0", &Settings::default()),
+        ));
+        annotations.push((
+            "v1.secret.runo.rocks/generated-with-checksum-0".to_string(),
+            checksum,
+        ));
+        assert!(!crate::annotations::needs_generation(
+            &Arc::new(build_secret_with_annotations(annotations)),
+            "0",
+            &Settings::default()
+        ));
+    }
+
+    #[rstest]
+    fn v1_config_checksum_ignores_renewal() {
+        let secret = build_secret_with_annotations(vec![(
+            "v1.secret.runo.rocks/generate-0".to_string(),
+            "username".to_string(),
+        )]);
+        let renewed_secret = build_secret_with_annotations(vec![
+            (
+                "v1.secret.runo.rocks/generate-0".to_string(),
+                "username".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/renewal-0".to_string(),
+                "true".to_string(),
+            ),
+        ]);
+        assert_eq!(
+            create_checksum(&Arc::new(secret), "0", &Settings::default()),
+            create_checksum(&Arc::new(renewed_secret), "0", &Settings::default())
+        );
+    }
+
     #[rstest]
     #[case("v1.secret.runo.rocks/force-overwrite-0", "true")]
     #[case("v1.secret.runo.rocks/force-overwrite-0", "false")]
@@ -535,7 +1108,11 @@ mod tests {
     #[case(vec![("v1.secret.runo.rocks/generate-0".to_string(), "username".to_string())])]
     fn needs_generation(#[case] annotations: Vec<(String, String)>) {
         let secret = build_secret_with_annotations(annotations);
-        assert!(crate::annotations::needs_generation(&Arc::new(secret), "0"));
+        assert!(crate::annotations::needs_generation(
+            &Arc::new(secret),
+            "0",
+            &Settings::default()
+        ));
     }
 
     #[rstest]
@@ -545,7 +1122,8 @@ mod tests {
         let secret = build_secret_with_annotations(annotations);
         assert!(!crate::annotations::needs_generation(
             &Arc::new(secret),
-            "0"
+            "0",
+            &Settings::default()
         ));
     }
 
@@ -561,7 +1139,8 @@ mod tests {
         secret.data = Some(predefined_data);
         assert!(!crate::annotations::needs_generation(
             &Arc::new(secret),
-            "0"
+            "0",
+            &Settings::default()
         ));
     }
 
@@ -578,7 +1157,11 @@ mod tests {
             ByteString("already-set".to_string().into_bytes()),
         );
         secret.data = Some(predefined_data);
-        assert!(crate::annotations::needs_generation(&Arc::new(secret), "0"));
+        assert!(crate::annotations::needs_generation(
+            &Arc::new(secret),
+            "0",
+            &Settings::default()
+        ));
     }
 
     #[rstest]
@@ -588,7 +1171,11 @@ mod tests {
     ("v1.secret.runo.rocks/config-checksum-0".to_string(), "abcde".to_string())])]
     fn needs_generation_checksum_changed(#[case] annotations: Vec<(String, String)>) {
         let secret = build_secret_with_annotations(annotations);
-        assert!(crate::annotations::needs_generation(&Arc::new(secret), "0"));
+        assert!(crate::annotations::needs_generation(
+            &Arc::new(secret),
+            "0",
+            &Settings::default()
+        ));
     }
 
     #[rstest]
@@ -600,7 +1187,8 @@ mod tests {
         let secret = build_secret_with_annotations(annotations);
         assert!(!crate::annotations::needs_generation(
             &Arc::new(secret),
-            "0"
+            "0",
+            &Settings::default()
         ));
     }
 
@@ -614,7 +1202,72 @@ mod tests {
         let secret = build_secret_with_annotations(annotations);
         assert!(!crate::annotations::needs_generation(
             &Arc::new(secret),
-            "0"
+            "0",
+            &Settings::default()
+        ));
+    }
+
+    /// Annotations of a field generated by a release with the legacy checksum, which was the
+    /// hash of "password", "false" and "0 0 * * *"
+    fn legacy_annotations() -> Vec<(String, String)> {
+        vec![
+            ("v1.secret.runo.rocks/generate-0", "password"),
+            ("v1.secret.runo.rocks/generated-at-0", "1767225600"),
+            ("v1.secret.runo.rocks/renewal-0", "false"),
+            ("v1.secret.runo.rocks/renewal-cron-0", "0 0 * * *"),
+            (
+                "v1.secret.runo.rocks/generated-with-checksum-0",
+                "06363924a76b702f3251c9222b63ab123d898f7921a2ee9b0a0c9fa329844523",
+            ),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+    }
+
+    #[rstest]
+    fn needs_no_generation_legacy_checksum() {
+        let secret = Arc::new(build_secret_with_annotations(legacy_annotations()));
+        let mut annotations = legacy_annotations();
+        annotations.push((
+            "v1.secret.runo.rocks/config-checksum-0".to_string(),
+            create_checksum(&secret, "0", &Settings::default()),
+        ));
+        let secret = Arc::new(build_secret_with_annotations(annotations));
+        assert!(crate::annotations::generated_with_legacy_checksum(
+            &secret,
+            "0",
+            &Settings::default()
+        ));
+        assert!(!crate::annotations::needs_generation(
+            &secret,
+            "0",
+            &Settings::default()
+        ));
+    }
+
+    #[rstest]
+    fn needs_generation_legacy_checksum_config_changed() {
+        let mut annotations = legacy_annotations();
+        annotations.push((
+            "v1.secret.runo.rocks/length-0".to_string(),
+            "16".to_string(),
+        ));
+        let secret = Arc::new(build_secret_with_annotations(annotations.clone()));
+        annotations.push((
+            "v1.secret.runo.rocks/config-checksum-0".to_string(),
+            create_checksum(&secret, "0", &Settings::default()),
+        ));
+        let secret = Arc::new(build_secret_with_annotations(annotations));
+        assert!(!crate::annotations::generated_with_legacy_checksum(
+            &secret,
+            "0",
+            &Settings::default()
+        ));
+        assert!(crate::annotations::needs_generation(
+            &secret,
+            "0",
+            &Settings::default()
         ));
     }
 
@@ -626,10 +1279,180 @@ mod tests {
         let secret = build_secret_with_annotations(annotations);
         assert!(!crate::annotations::needs_generation(
             &Arc::new(secret),
-            "1"
+            "1",
+            &Settings::default()
+        ));
+    }
+
+    #[rstest]
+    #[case("v1.secret.runo.rocks/max-age-0", "30d")]
+    fn v1_max_age(#[case] key: String, #[case] value: String) {
+        let secret = build_secret_with_annotations(vec![(key, value.clone())]);
+        assert_eq!(
+            crate::annotations::max_age(&Arc::new(secret), "0", &Settings::default()).get_value(),
+            value
+        );
+    }
+
+    #[rstest]
+    #[case(vec![("v1.secret.runo.rocks/generate-0".to_string(), "username".to_string()),
+    ("v1.secret.runo.rocks/generated-at-0".to_string(), "0".to_string()),
+    ("v1.secret.runo.rocks/max-age-0".to_string(), "720h".to_string())])]
+    fn needs_renewal_max_age_exceeded(#[case] annotations: Vec<(String, String)>) {
+        let secret = build_secret_with_annotations(annotations);
+        assert!(crate::annotations::needs_renewal(
+            &Arc::new(secret),
+            "0",
+            &Settings::default()
+        ));
+    }
+
+    #[rstest]
+    #[case(vec![("v1.secret.runo.rocks/generate-0".to_string(), "username".to_string()),
+    ("v1.secret.runo.rocks/generated-at-0".to_string(), Utc::now().timestamp().to_string()),
+    ("v1.secret.runo.rocks/max-age-0".to_string(), "30d".to_string())])]
+    fn needs_no_renewal_max_age_not_exceeded(#[case] annotations: Vec<(String, String)>) {
+        let secret = build_secret_with_annotations(annotations);
+        assert!(!crate::annotations::needs_renewal(
+            &Arc::new(secret),
+            "0",
+            &Settings::default()
+        ));
+    }
+
+    #[rstest]
+    #[case(vec![("v1.secret.runo.rocks/generate-0".to_string(), "username".to_string()),
+    ("v1.secret.runo.rocks/generated-at-0".to_string(), "0".to_string()),
+    ("v1.secret.runo.rocks/max-age-0".to_string(), "720h".to_string()),
+    ("v1.secret.runo.rocks/pause-0".to_string(), "true".to_string())])]
+    fn needs_no_renewal_max_age_exceeded_when_paused(#[case] annotations: Vec<(String, String)>) {
+        let secret = build_secret_with_annotations(annotations);
+        assert!(!crate::annotations::needs_renewal(
+            &Arc::new(secret),
+            "0",
+            &Settings::default()
         ));
     }
 
+    #[rstest]
+    #[case("v1.secret.runo.rocks/history-0", "3", 3)]
+    #[case("v1.secret.runo.rocks/history-0", "0", 0)]
+    fn v1_history(#[case] key: String, #[case] value: String, #[case] history: usize) {
+        let secret = build_secret_with_annotations(vec![(key, value)]);
+        assert_eq!(
+            crate::annotations::history(&Arc::new(secret), "0", &Settings::default()).get_value(),
+            history
+        );
+    }
+
+    #[rstest]
+    #[case("v1.secret.runo.rocks/history-0", "-1")]
+    #[case("v1.secret.runo.rocks/history-0", "11")]
+    #[case("v1.secret.runo.rocks/history-0", "many")]
+    fn v1_history_invalid(#[case] key: String, #[case] value: String) {
+        let secret = build_secret_with_annotations(vec![(key, value)]);
+        let history = crate::annotations::history(&Arc::new(secret), "0", &Settings::default());
+        assert!(history.is_default());
+        assert_eq!(history.get_value(), 0);
+    }
+
+    #[rstest]
+    fn v1_config_checksum_ignores_history() {
+        let secret = build_secret_with_annotations(vec![(
+            "v1.secret.runo.rocks/generate-0".to_string(),
+            "username".to_string(),
+        )]);
+        let secret_with_history = build_secret_with_annotations(vec![
+            (
+                "v1.secret.runo.rocks/generate-0".to_string(),
+                "username".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/history-0".to_string(),
+                "2".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/history-generated-at-0".to_string(),
+                "0".to_string(),
+            ),
+        ]);
+        assert_eq!(
+            create_checksum(&Arc::new(secret), "0", &Settings::default()),
+            create_checksum(&Arc::new(secret_with_history), "0", &Settings::default())
+        );
+    }
+
+    #[rstest]
+    #[case(vec![("v1.secret.runo.rocks/generate-0".to_string(), "password".to_string()),
+    ("v1.secret.runo.rocks/staged-rotation-0".to_string(), "true".to_string()),
+    ("v1.secret.runo.rocks/pending-since-0".to_string(), Utc::now().timestamp().to_string()),
+    ("v1.secret.runo.rocks/promote-0".to_string(), "true".to_string())])]
+    #[case(vec![("v1.secret.runo.rocks/generate-0".to_string(), "password".to_string()),
+    ("v1.secret.runo.rocks/staged-rotation-0".to_string(), "true".to_string()),
+    ("v1.secret.runo.rocks/pending-since-0".to_string(), "0".to_string()),
+    ("v1.secret.runo.rocks/grace-period-0".to_string(), "1h".to_string())])]
+    fn needs_promotion(#[case] annotations: Vec<(String, String)>) {
+        let secret = build_secret_with_annotations(annotations);
+        assert!(crate::annotations::needs_promotion(
+            &Arc::new(secret),
+            "0",
+            &Settings::default()
+        ));
+    }
+
+    #[rstest]
+    #[case(vec![("v1.secret.runo.rocks/generate-0".to_string(), "password".to_string()),
+    ("v1.secret.runo.rocks/staged-rotation-0".to_string(), "true".to_string()),
+    ("v1.secret.runo.rocks/promote-0".to_string(), "true".to_string())])]
+    #[case(vec![("v1.secret.runo.rocks/generate-0".to_string(), "password".to_string()),
+    ("v1.secret.runo.rocks/staged-rotation-0".to_string(), "true".to_string()),
+    ("v1.secret.runo.rocks/pending-since-0".to_string(), Utc::now().timestamp().to_string()),
+    ("v1.secret.runo.rocks/grace-period-0".to_string(), "1h".to_string())])]
+    #[case(vec![("v1.secret.runo.rocks/generate-0".to_string(), "password".to_string()),
+    ("v1.secret.runo.rocks/staged-rotation-0".to_string(), "true".to_string()),
+    ("v1.secret.runo.rocks/pending-since-0".to_string(), "0".to_string())])]
+    #[case(vec![("v1.secret.runo.rocks/generate-0".to_string(), "password".to_string()),
+    ("v1.secret.runo.rocks/staged-rotation-0".to_string(), "true".to_string()),
+    ("v1.secret.runo.rocks/pending-since-0".to_string(), "0".to_string()),
+    ("v1.secret.runo.rocks/promote-0".to_string(), "true".to_string()),
+    ("v1.secret.runo.rocks/pause-0".to_string(), "true".to_string())])]
+    fn needs_no_promotion(#[case] annotations: Vec<(String, String)>) {
+        let secret = build_secret_with_annotations(annotations);
+        assert!(!crate::annotations::needs_promotion(
+            &Arc::new(secret),
+            "0",
+            &Settings::default()
+        ));
+    }
+
+    #[rstest]
+    #[case("v1.secret.runo.rocks/rollout-restart", "true", true)]
+    #[case("v1.secret.runo.rocks/rollout-restart", "false", false)]
+    #[case("v1.secret.runo.rocks/rollout-restart-0", "true", false)]
+    fn v1_rollout_restart(#[case] key: String, #[case] value: String, #[case] expected: bool) {
+        let secret = build_secret_with_annotations(vec![(key, value)]);
+        assert_eq!(
+            crate::annotations::rollout_restart(&Arc::new(secret)),
+            expected
+        );
+    }
+
+    #[rstest]
+    #[case(
+        "v1.secret.runo.rocks/replicate-to",
+        "monitoring, apps",
+        Some("monitoring, apps")
+    )]
+    #[case("v1.secret.runo.rocks/replicate-to", " ", None)]
+    #[case("v1.secret.runo.rocks/replicate-to-0", "monitoring", None)]
+    fn v1_replicate_to(#[case] key: String, #[case] value: String, #[case] expected: Option<&str>) {
+        let secret = build_secret_with_annotations(vec![(key, value)]);
+        assert_eq!(
+            crate::annotations::replicate_to(&Arc::new(secret)),
+            expected.map(|e| e.to_string())
+        );
+    }
+
     // Pause functionality tests
     #[rstest]
     #[case("v1.secret.runo.rocks/pause-0", "true")]
@@ -660,7 +1483,11 @@ mod tests {
     fn needs_no_generation_when_paused(#[case] annotations: Vec<(String, String)>) {
         let secret = build_secret_with_annotations(annotations);
         let secret_arc = Arc::new(secret);
-        assert!(!crate::annotations::needs_generation(&secret_arc, "0"));
+        assert!(!crate::annotations::needs_generation(
+            &secret_arc,
+            "0",
+            &Settings::default()
+        ));
     }
 
     #[rstest]
@@ -670,8 +1497,16 @@ mod tests {
     fn needs_generation_for_non_paused_field(#[case] annotations: Vec<(String, String)>) {
         let secret = build_secret_with_annotations(annotations);
         let secret_arc = Arc::new(secret);
-        assert!(!crate::annotations::needs_generation(&secret_arc, "0")); // id 0 is paused
-        assert!(crate::annotations::needs_generation(&secret_arc, "1")); // id 1 is not paused
+        assert!(!crate::annotations::needs_generation(
+            &secret_arc,
+            "0",
+            &Settings::default()
+        )); // id 0 is paused
+        assert!(crate::annotations::needs_generation(
+            &secret_arc,
+            "1",
+            &Settings::default()
+        )); // id 1 is not paused
     }
 
     #[rstest]
@@ -681,7 +1516,11 @@ mod tests {
     fn needs_no_renewal_when_paused(#[case] annotations: Vec<(String, String)>) {
         let secret = build_secret_with_annotations(annotations);
         let secret_arc = Arc::new(secret);
-        assert!(!crate::annotations::needs_renewal(&secret_arc, "0"));
+        assert!(!crate::annotations::needs_renewal(
+            &secret_arc,
+            "0",
+            &Settings::default()
+        ));
     }
 
     #[rstest]
@@ -694,4 +1533,109 @@ mod tests {
         let secret_arc = Arc::new(secret);
         assert!(!crate::annotations::needs_clone(&secret_arc, "1")); // id 1 (target) is paused
     }
+
+    #[rstest]
+    fn profile_values_as_defaults() {
+        let settings = crate::settings::test_settings();
+        let secret = Arc::new(build_secret_with_annotations(vec![
+            (
+                "v1.secret.runo.rocks/profile-0".to_string(),
+                "pin".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/profile-1".to_string(),
+                "pin".to_string(),
+            ),
+            ("v1.secret.runo.rocks/length-1".to_string(), "8".to_string()),
+            (
+                "v1.secret.runo.rocks/profile-2".to_string(),
+                "unknown".to_string(),
+            ),
+        ]));
+        assert_eq!(
+            crate::annotations::length(&secret, "0", &settings).get_value(),
+            6
+        );
+        assert_eq!(
+            crate::annotations::charset(&secret, "0", &settings).get_value(),
+            "0123456789"
+        );
+        assert_eq!(
+            crate::annotations::history(&secret, "0", &settings).get_value(),
+            2
+        );
+        assert!(!crate::annotations::has_cron(&secret, "0", &settings));
+        assert_eq!(
+            crate::annotations::length(&secret, "1", &settings).get_value(),
+            8
+        );
+        assert_eq!(
+            crate::annotations::length(&secret, "2", &settings).get_value(),
+            32
+        );
+        assert!(crate::annotations::length(&secret, "2", &settings).is_default());
+    }
+
+    #[rstest]
+    #[case("pin", false)]
+    #[case("api-token", true)]
+    fn profile_changes_checksum_if_regeneration_is_allowed(
+        #[case] profile: &str,
+        #[case] regenerate: bool,
+    ) {
+        use sha2::{Digest, Sha256};
+        let settings = crate::settings::test_settings();
+        let secret = Arc::new(build_secret_with_annotations(vec![
+            (
+                "v1.secret.runo.rocks/generate-0".to_string(),
+                "secret".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/profile-0".to_string(),
+                profile.to_string(),
+            ),
+        ]));
+        let mut hasher = Sha256::new();
+        hasher.update("secret");
+        hasher.update(profile);
+        let without_profile: String = hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        assert_eq!(
+            create_checksum(&secret, "0", &settings) != without_profile,
+            regenerate
+        );
+    }
+
+    #[rstest]
+    fn defaults_of_settings() {
+        let settings = crate::settings::parse("defaults:\n  length: 16\n  charset: abc\n").unwrap();
+        let secret = Arc::new(build_secret_with_annotations(vec![(
+            "v1.secret.runo.rocks/generate-0".to_string(),
+            "password".to_string(),
+        )]));
+        assert_eq!(
+            crate::annotations::length(&secret, "0", &settings).get_value(),
+            16
+        );
+        assert_eq!(
+            crate::annotations::charset(&secret, "0", &settings).get_value(),
+            "abc"
+        );
+        assert!(crate::annotations::uses_charset(&secret, "0", &settings));
+        assert!(!crate::annotations::uses_charset(
+            &secret,
+            "0",
+            &Settings::default()
+        ));
+    }
+
+    #[test]
+    fn all_annotations_have_distinct_keys() {
+        let keys: std::collections::HashSet<String> =
+            V1Annotation::ALL.iter().map(|a| a.key()).collect();
+        assert_eq!(keys.len(), V1Annotation::ALL.len());
+    }
 }
@@ -0,0 +1,15 @@
+use anyhow::anyhow;
+use clap::Parser;
+use runo::cli::PluginArgs;
+use runo::plugin;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let lines = plugin::run(PluginArgs::parse())
+        .await
+        .map_err(|e| anyhow!("{}", e))?;
+    for line in lines {
+        println!("{}", line);
+    }
+    Ok(())
+}
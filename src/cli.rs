@@ -0,0 +1,537 @@
+use crate::config::{
+    CronJobTemplate, NamespaceScope, RenewalMode, RunoConfig, DEFAULT_REQUEUE_DURATION,
+};
+use crate::errors::InvalidSettings;
+use crate::k8s::K8s;
+use crate::limiter::RenewalLimiter;
+use crate::schedule::parse_duration;
+use crate::settings::{self, Settings};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[clap(
+    author,
+    version,
+    about,
+    long_about = None,
+    propagate_version = true,
+    args_conflicts_with_subcommands = true
+)]
+pub struct MainArgs {
+    #[clap(subcommand)]
+    command: Option<Command>,
+    #[clap(flatten, next_help_heading = "Options of `run` without a command")]
+    legacy: LegacyArgs,
+}
+
+impl MainArgs {
+    /// Without a command, the controller runs with the flags of versions before the commands
+    pub fn command(self) -> Command {
+        match self.command {
+            Some(command) => command,
+            None => self.legacy.command(),
+        }
+    }
+
+    /// `--mode` was replaced by the `run` and `once` commands
+    pub fn uses_mode(&self) -> bool {
+        self.legacy.mode.is_some()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+enum LegacyMode {
+    Reconciliation,
+    OneShot,
+}
+
+/// Flags of versions before the commands, running `runo` without a command stays compatible
+#[derive(Args)]
+struct LegacyArgs {
+    /// Use `runo run` or `runo once` instead
+    #[clap(long, hide = true, value_enum)]
+    mode: Option<LegacyMode>,
+    /// Use `runo once --concurrency` instead
+    #[clap(
+        long,
+        hide = true,
+        default_value_t = 10,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    one_shot_concurrency: u32,
+    #[clap(flatten)]
+    run: RunArgs,
+}
+
+impl LegacyArgs {
+    fn command(self) -> Command {
+        match self.mode {
+            Some(LegacyMode::OneShot) => Command::Once(OnceArgs {
+                concurrency: self.one_shot_concurrency,
+                controller: self.run.controller,
+            }),
+            Some(LegacyMode::Reconciliation) | None => Command::Run(self.run),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run the controller and reconcile managed resources continuously
+    Run(RunArgs),
+    /// Reconcile all managed resources once and exit
+    Once(OnceArgs),
+    /// Check manifests of managed Secrets and ConfigMaps without a cluster
+    Lint {
+        /// Files or directories with YAML or JSON manifests, `-` for stdin
+        #[clap(required = true)]
+        paths: Vec<String>,
+        #[clap(flatten)]
+        settings: SettingsArgs,
+    },
+    /// Generate the values of managed Secrets and ConfigMaps of a manifest without a cluster
+    Generate {
+        /// File with YAML or JSON manifests, `-` for stdin
+        #[clap(short = 'f', long = "filename", env = "RUNO_FILENAME")]
+        file: String,
+        #[clap(flatten)]
+        settings: SettingsArgs,
+    },
+    /// Renew fields of a Secret now and wait until the controller has renewed them
+    Rotate {
+        /// Secret in the form <namespace>/<secret>
+        target: String,
+        /// Id of the field to renew
+        #[clap(long, env = "RUNO_ROTATE_ID", required_unless_present = "all")]
+        id: Option<String>,
+        /// Renew all fields which aren't paused or cloned
+        #[clap(long, env = "RUNO_ROTATE_ALL", conflicts_with = "id")]
+        all: bool,
+        /// Maximum time to wait for the controller, e.g. 30s or 5m
+        #[clap(
+            long,
+            env = "RUNO_ROTATE_TIMEOUT",
+            default_value = "2m",
+            value_parser = |v: &str| parse_duration(v).map_err(|e| e.to_string())
+        )]
+        timeout: Duration,
+    },
+    /// List managed Secrets with the state of their fields, values are never printed
+    Status {
+        /// Namespace of the Secrets, all namespaces if it's not set
+        #[clap(short = 'n', long, env = "RUNO_STATUS_NAMESPACE")]
+        namespace: Option<String>,
+        #[clap(flatten)]
+        settings: SettingsArgs,
+    },
+    /// Print the version
+    Version,
+}
+
+/// Config file of the controller for the commands without a controller, so fields get the
+/// defaults and profiles they get during the reconciliation
+#[derive(Args)]
+pub struct SettingsArgs {
+    /// YAML or JSON file with the cluster-wide defaults and policies of the controller
+    #[clap(long, env = "RUNO_CONFIG_FILE")]
+    pub config_file: Option<PathBuf>,
+}
+
+impl SettingsArgs {
+    pub fn load(&self) -> Result<Settings, InvalidSettings> {
+        match &self.config_file {
+            Some(file) => settings::load(file),
+            None => Ok(Settings::default()),
+        }
+    }
+}
+
+/// Settings of the reconciliation shared by `run` and `once`
+#[derive(Args)]
+pub struct ControllerArgs {
+    #[clap(long, env = "RUNO_DRY_RUN", default_value_t = false)]
+    pub dry_run: bool,
+    #[clap(long, env = "RUNO_RENEWAL_MODE", value_enum, default_value_t = RenewalMode::Native)]
+    pub renewal_mode: RenewalMode,
+    /// Maximum number of renewals running at the same time in the cluster
+    #[clap(
+        long,
+        env = "RUNO_MAX_CONCURRENT_RENEWALS",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub max_concurrent_renewals: Option<u32>,
+    /// Maximum number of renewals running at the same time per namespace
+    #[clap(
+        long,
+        env = "RUNO_MAX_CONCURRENT_RENEWALS_PER_NAMESPACE",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub max_concurrent_renewals_per_namespace: Option<u32>,
+    /// Namespaces other Secrets may clone fields from, comma separated
+    #[clap(long, env = "RUNO_ALLOWED_CLONE_NAMESPACES", value_delimiter = ',')]
+    pub allowed_clone_namespaces: Vec<String>,
+    /// Namespaces Secrets may be replicated to with replicate-to, comma separated
+    #[clap(
+        long,
+        env = "RUNO_ALLOWED_REPLICATION_NAMESPACES",
+        value_delimiter = ','
+    )]
+    pub allowed_replication_namespaces: Vec<String>,
+    /// Generate values of managed ConfigMaps as well
+    #[clap(long, env = "RUNO_CONFIG_MAPS", default_value_t = false)]
+    pub config_maps: bool,
+    /// YAML or JSON file with cluster-wide defaults and policies, reloaded on changes
+    #[clap(long, env = "RUNO_CONFIG_FILE")]
+    pub config_file: Option<PathBuf>,
+    #[clap(flatten)]
+    pub cronjob_template: CronJobTemplate,
+    #[clap(flatten)]
+    pub namespace_scope: NamespaceScope,
+}
+
+impl ControllerArgs {
+    pub fn build_config(self, requeue_duration: u64) -> Result<RunoConfig, InvalidSettings> {
+        let settings = match &self.config_file {
+            Some(file) => settings::load(file)?,
+            None => Default::default(),
+        };
+        let k8s = K8s::build(self.dry_run);
+        Ok(RunoConfig::build(k8s, requeue_duration)
+            .with_renewal_mode(self.renewal_mode)
+            .with_cronjob_template(self.cronjob_template)
+            .with_renewal_limiter(RenewalLimiter::build(
+                self.max_concurrent_renewals,
+                self.max_concurrent_renewals_per_namespace,
+            ))
+            .with_allowed_clone_namespaces(self.allowed_clone_namespaces)
+            .with_allowed_replication_namespaces(self.allowed_replication_namespaces)
+            .with_config_maps(self.config_maps)
+            .with_namespace_scope(self.namespace_scope)
+            .with_settings(settings, self.config_file))
+    }
+}
+
+#[derive(Args)]
+pub struct RunArgs {
+    #[clap(long, env = "RUNO_HTTP_PORT", default_value_t = 8080)]
+    pub http_port: u16,
+    #[clap(long, env = "RUNO_REQUEUE_DURATION", default_value_t = DEFAULT_REQUEUE_DURATION)]
+    pub requeue_duration: u64,
+    #[clap(flatten)]
+    pub controller: ControllerArgs,
+}
+
+#[derive(Args)]
+pub struct OnceArgs {
+    /// Number of resources reconciled at the same time
+    #[clap(
+        long,
+        env = "RUNO_CONCURRENCY",
+        default_value_t = 10,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub concurrency: u32,
+    #[clap(flatten)]
+    pub controller: ControllerArgs,
+}
+
+/// Arguments of the `kubectl-runo` plugin, invoked as `kubectl runo`
+#[derive(Parser)]
+#[clap(
+    name = "kubectl-runo",
+    bin_name = "kubectl runo",
+    version,
+    about = "Manage the runo annotations of Secrets",
+    long_about = None,
+    propagate_version = true
+)]
+pub struct PluginArgs {
+    /// Namespace of the Secret, the namespace of the current context if it's not set
+    #[clap(short = 'n', long, global = true)]
+    pub namespace: Option<String>,
+    #[clap(subcommand)]
+    pub command: PluginCommand,
+}
+
+#[derive(Subcommand)]
+pub enum PluginCommand {
+    /// Add a generated field with the next free id and mark the Secret as managed
+    AddField {
+        /// Name of the Secret
+        secret: String,
+        /// Key of the field in the Secret
+        field: String,
+        /// Length of the value between 1 and 100
+        #[clap(long, value_parser = clap::value_parser!(u32).range(1..=100))]
+        length: Option<u32>,
+        /// Characters the value is generated from
+        #[clap(long, conflicts_with = "pattern")]
+        charset: Option<String>,
+        /// Regex pattern every character of the value has to match
+        #[clap(long)]
+        pattern: Option<String>,
+        /// Clone the value from `<id>`, `<secret>/<id-or-key>` or `<namespace>/<secret>/<id-or-key>`
+        #[clap(long, conflicts_with_all = ["length", "charset", "pattern"])]
+        clone_from: Option<String>,
+        /// Renew the value on a cron schedule, e.g. "0 3 * * 0"
+        #[clap(long)]
+        renewal_cron: Option<String>,
+        /// Profile of the controller config file with the settings of the field
+        #[clap(long, conflicts_with = "clone_from")]
+        profile: Option<String>,
+    },
+    /// Remove all annotations of a field. The value is kept in the Secret.
+    RemoveField {
+        /// Name of the Secret
+        secret: String,
+        /// Key of the field in the Secret
+        field: String,
+    },
+    /// Stop generating and renewing a field
+    Pause {
+        /// Name of the Secret
+        secret: String,
+        /// Key of the field in the Secret
+        field: String,
+    },
+    /// Generate and renew a paused field again
+    Resume {
+        /// Name of the Secret
+        secret: String,
+        /// Key of the field in the Secret
+        field: String,
+    },
+    /// Renew fields now and wait until the controller has renewed them
+    Rotate {
+        /// Name of the Secret
+        secret: String,
+        /// Key of the field in the Secret
+        #[clap(required_unless_present = "all")]
+        field: Option<String>,
+        /// Renew all fields which aren't paused or cloned
+        #[clap(long, conflicts_with = "field")]
+        all: bool,
+        /// Maximum time to wait for the controller, e.g. 30s or 5m
+        #[clap(
+            long,
+            default_value = "2m",
+            value_parser = |v: &str| parse_duration(v).map_err(|e| e.to_string())
+        )]
+        timeout: Duration,
+    },
+    /// Show the fields of the Secret and their state without their values
+    Describe {
+        /// Name of the Secret
+        secret: String,
+        #[clap(flatten)]
+        settings: SettingsArgs,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Command, MainArgs, PluginArgs, PluginCommand};
+    use crate::config::RenewalMode;
+    use clap::{CommandFactory, Parser};
+    use rstest::rstest;
+    use std::time::Duration;
+
+    /// Variables of all flags of the controller. Tests set some of them, so the others parse
+    /// without them to be independent of the environment and of tests running in parallel.
+    fn env_vars() -> Vec<String> {
+        let command = <MainArgs as CommandFactory>::command();
+        std::iter::once(&command)
+            .chain(command.get_subcommands())
+            .flat_map(|c| c.get_arguments())
+            .filter_map(|a| a.get_env())
+            .map(|env| env.to_string_lossy().to_string())
+            .collect()
+    }
+
+    fn parse(args: Vec<&str>) -> Result<MainArgs, clap::Error> {
+        temp_env::with_vars_unset(env_vars(), || MainArgs::try_parse_from(args))
+    }
+
+    #[rstest]
+    fn parse_run() {
+        let args = parse(vec![
+            "runo",
+            "run",
+            "--http-port",
+            "9090",
+            "--renewal-mode",
+            "cronjob",
+        ])
+        .unwrap();
+        match args.command() {
+            Command::Run(run) => {
+                assert_eq!(run.http_port, 9090);
+                assert_eq!(run.requeue_duration, 300);
+                assert_eq!(run.controller.renewal_mode, RenewalMode::CronJob);
+            }
+            _ => panic!("Expected run"),
+        }
+    }
+
+    #[rstest]
+    fn parse_once_from_env() {
+        let set = [
+            ("RUNO_CONCURRENCY", "3"),
+            ("RUNO_DRY_RUN", "true"),
+            ("RUNO_DENY_NAMESPACES", "kube-system,kube-public"),
+        ];
+        let vars: Vec<(String, Option<&str>)> = env_vars()
+            .into_iter()
+            .map(|var| {
+                let value = set.iter().find(|(name, _)| *name == var).map(|(_, v)| *v);
+                (var, value)
+            })
+            .collect();
+        temp_env::with_vars(vars, || {
+            let args = MainArgs::try_parse_from(["runo", "once"]).unwrap();
+            match args.command() {
+                Command::Once(once) => {
+                    assert_eq!(once.concurrency, 3);
+                    assert!(once.controller.dry_run);
+                    assert_eq!(
+                        once.controller.namespace_scope.denied,
+                        vec!["kube-system", "kube-public"]
+                    );
+                }
+                _ => panic!("Expected once"),
+            }
+        });
+    }
+
+    #[rstest]
+    fn parse_rotate() {
+        let args = parse(vec![
+            "runo",
+            "rotate",
+            "apps/db",
+            "--all",
+            "--timeout",
+            "30s",
+        ])
+        .unwrap();
+        match args.command() {
+            Command::Rotate {
+                target,
+                id,
+                all,
+                timeout,
+            } => {
+                assert_eq!(target, "apps/db");
+                assert_eq!(id, None);
+                assert!(all);
+                assert_eq!(timeout, Duration::from_secs(30));
+            }
+            _ => panic!("Expected rotate"),
+        }
+    }
+
+    #[rstest]
+    #[case(vec!["runo", "status"], None)]
+    #[case(vec!["runo", "status", "-n", "apps"], Some("apps"))]
+    #[case(vec!["runo", "status", "--namespace", "apps"], Some("apps"))]
+    fn parse_status(#[case] args: Vec<&str>, #[case] expected: Option<&str>) {
+        match parse(args).unwrap().command() {
+            Command::Status { namespace, .. } => assert_eq!(namespace.as_deref(), expected),
+            _ => panic!("Expected status"),
+        }
+    }
+
+    #[rstest]
+    fn parse_plugin_add_field() {
+        let args = PluginArgs::try_parse_from([
+            "kubectl-runo",
+            "add-field",
+            "db",
+            "password",
+            "--length",
+            "16",
+            "-n",
+            "apps",
+        ])
+        .unwrap();
+        assert_eq!(args.namespace.as_deref(), Some("apps"));
+        match args.command {
+            PluginCommand::AddField {
+                secret,
+                field,
+                length,
+                ..
+            } => {
+                assert_eq!(secret, "db");
+                assert_eq!(field, "password");
+                assert_eq!(length, Some(16));
+            }
+            _ => panic!("Expected add-field"),
+        }
+    }
+
+    #[rstest]
+    #[case(vec!["kubectl-runo", "add-field", "db", "password", "--length", "101"])]
+    #[case(vec!["kubectl-runo", "add-field", "db", "password", "--charset", "a", "--pattern", "b"])]
+    #[case(vec!["kubectl-runo", "add-field", "db", "token", "--clone-from", "0", "--length", "8"])]
+    #[case(vec!["kubectl-runo", "rotate", "db"])]
+    #[case(vec!["kubectl-runo", "rotate", "db", "password", "--all"])]
+    #[case(vec!["kubectl-runo", "pause", "db"])]
+    fn parse_plugin_invalid(#[case] args: Vec<&str>) {
+        assert!(PluginArgs::try_parse_from(args).is_err());
+    }
+
+    #[rstest]
+    #[case(vec!["runo"], 8080, false)]
+    #[case(vec!["runo", "--http-port", "9090", "--dry-run"], 9090, true)]
+    #[case(vec!["runo", "--mode", "reconciliation", "--http-port", "9090"], 9090, false)]
+    fn parse_without_command(
+        #[case] args: Vec<&str>,
+        #[case] http_port: u16,
+        #[case] dry_run: bool,
+    ) {
+        match parse(args).unwrap().command() {
+            Command::Run(run) => {
+                assert_eq!(run.http_port, http_port);
+                assert_eq!(run.controller.dry_run, dry_run);
+            }
+            _ => panic!("Expected run"),
+        }
+    }
+
+    #[rstest]
+    fn parse_deprecated_one_shot_mode() {
+        let args = parse(vec![
+            "runo",
+            "--mode",
+            "one-shot",
+            "--one-shot-concurrency",
+            "3",
+            "--renewal-mode",
+            "cronjob",
+        ])
+        .unwrap();
+        assert!(args.uses_mode());
+        match args.command() {
+            Command::Once(once) => {
+                assert_eq!(once.concurrency, 3);
+                assert_eq!(once.controller.renewal_mode, RenewalMode::CronJob);
+            }
+            _ => panic!("Expected once"),
+        }
+    }
+
+    #[rstest]
+    #[case(vec!["runo", "rotate", "apps/db"])]
+    #[case(vec!["runo", "rotate", "apps/db", "--id", "0", "--all"])]
+    #[case(vec!["runo", "--mode", "invalid"])]
+    #[case(vec!["runo", "--dry-run", "run"])]
+    #[case(vec!["runo", "run", "--mode", "one-shot"])]
+    #[case(vec!["runo", "once", "--http-port", "8080"])]
+    #[case(vec!["runo", "run", "--concurrency", "1"])]
+    fn parse_invalid(#[case] args: Vec<&str>) {
+        assert!(parse(args).is_err());
+    }
+}
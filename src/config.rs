@@ -1,9 +1,249 @@
+use crate::cron::CleanedUp;
 use crate::k8s::K8s;
+use crate::limiter::RenewalLimiter;
+use crate::settings::{self, Settings, SharedSettings};
+use clap::{Args, ValueEnum};
+use k8s_openapi::api::core::v1::{Namespace, ResourceRequirements, Toleration};
+use kube::runtime::reflector::Store;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
 
-#[derive(Copy, Clone)]
+pub const DEFAULT_REQUEUE_DURATION: u64 = 300;
+pub const DEFAULT_CRONJOB_IMAGE: &str = "cgr.dev/chainguard/kubectl";
+pub const DEFAULT_CRONJOB_IMAGE_PULL_POLICY: &str = "Always";
+pub const DEFAULT_CRONJOB_SERVICE_ACCOUNT: &str = "runo-cronjob";
+
+/// How renewals based on `renewal-cron` annotations are triggered
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum RenewalMode {
+    /// The controller computes the next fire time itself and requeues the Secret accordingly
+    Native,
+    /// A Kubernetes CronJob per field sets the renewal annotation
+    #[value(name = "cronjob")]
+    CronJob,
+}
+
+/// How the CronJob controller treats concurrent executions of a renewal job
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+pub enum ConcurrencyPolicy {
+    Allow,
+    Forbid,
+    Replace,
+}
+
+impl ConcurrencyPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ConcurrencyPolicy::Allow => "Allow",
+            ConcurrencyPolicy::Forbid => "Forbid",
+            ConcurrencyPolicy::Replace => "Replace",
+        }
+    }
+}
+
+/// Parse a value given as JSON, e.g. resources or tolerations of the renewal CronJobs
+pub fn parse_json<T: DeserializeOwned>(value: &str) -> Result<T, String> {
+    serde_json::from_str(value).map_err(|e| format!("{} is not valid: {}", value, e))
+}
+
+/// Parse a `key=value` pair, e.g. a node selector of the renewal CronJobs
+pub fn parse_key_value(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((k, v)) if !k.is_empty() => Ok((k.to_string(), v.to_string())),
+        _ => Err(format!("{} is not a key=value pair", value)),
+    }
+}
+
+/// Node selectors are maps in the config file
+fn deserialize_node_selector<'de, D>(deserializer: D) -> Result<Vec<(String, String)>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(BTreeMap::<String, String>::deserialize(deserializer)?
+        .into_iter()
+        .collect())
+}
+
+/// Pod template and job settings of the renewal CronJobs. Each setting can be overridden per
+/// Secret with the corresponding `v1.secret.runo.rocks/cronjob-*` annotation. Images, service
+/// accounts, image pull secrets and priority classes can only be overridden with values of the
+/// allowlists. Resources, node selectors and tolerations can only be overridden if the operator
+/// allows scheduling overrides.
+#[derive(Args, Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct CronJobTemplate {
+    /// Image of the container which sets the renewal annotation
+    #[clap(
+        long = "cronjob-image",
+        env = "RUNO_CRONJOB_IMAGE",
+        default_value = DEFAULT_CRONJOB_IMAGE
+    )]
+    pub(crate) image: String,
+    #[clap(
+        long = "cronjob-image-pull-policy",
+        env = "RUNO_CRONJOB_IMAGE_PULL_POLICY",
+        default_value = DEFAULT_CRONJOB_IMAGE_PULL_POLICY,
+        value_parser = ["Always", "IfNotPresent", "Never"]
+    )]
+    pub(crate) image_pull_policy: String,
+    /// Names of image pull secrets, comma separated
+    #[clap(
+        long = "cronjob-image-pull-secrets",
+        env = "RUNO_CRONJOB_IMAGE_PULL_SECRETS",
+        value_delimiter = ','
+    )]
+    pub(crate) image_pull_secrets: Vec<String>,
+    #[clap(
+        long = "cronjob-service-account",
+        env = "RUNO_CRONJOB_SERVICE_ACCOUNT",
+        default_value = DEFAULT_CRONJOB_SERVICE_ACCOUNT
+    )]
+    pub(crate) service_account: String,
+    /// Resource requests and limits of the container as JSON
+    #[clap(
+        long = "cronjob-resources",
+        env = "RUNO_CRONJOB_RESOURCES",
+        value_parser = parse_json::<ResourceRequirements>
+    )]
+    pub(crate) resources: Option<ResourceRequirements>,
+    /// Node selector as key=value, can be repeated
+    #[clap(
+        long = "cronjob-node-selector",
+        env = "RUNO_CRONJOB_NODE_SELECTOR",
+        value_parser = parse_key_value
+    )]
+    #[serde(deserialize_with = "deserialize_node_selector")]
+    pub(crate) node_selector: Vec<(String, String)>,
+    /// Toleration as JSON, can be repeated
+    #[clap(
+        long = "cronjob-toleration",
+        env = "RUNO_CRONJOB_TOLERATION",
+        value_parser = parse_json::<Toleration>
+    )]
+    pub(crate) tolerations: Vec<Toleration>,
+    #[clap(long = "cronjob-priority-class", env = "RUNO_CRONJOB_PRIORITY_CLASS")]
+    pub(crate) priority_class: Option<String>,
+    #[clap(
+        long = "cronjob-successful-jobs-history-limit",
+        env = "RUNO_CRONJOB_SUCCESSFUL_JOBS_HISTORY_LIMIT"
+    )]
+    pub(crate) successful_jobs_history_limit: Option<i32>,
+    #[clap(
+        long = "cronjob-failed-jobs-history-limit",
+        env = "RUNO_CRONJOB_FAILED_JOBS_HISTORY_LIMIT"
+    )]
+    pub(crate) failed_jobs_history_limit: Option<i32>,
+    #[clap(
+        long = "cronjob-concurrency-policy",
+        env = "RUNO_CRONJOB_CONCURRENCY_POLICY",
+        value_enum
+    )]
+    pub(crate) concurrency_policy: Option<ConcurrencyPolicy>,
+    /// Images Secrets may select with the `cronjob-image` annotation, comma separated
+    #[clap(
+        long = "cronjob-allowed-images",
+        env = "RUNO_CRONJOB_ALLOWED_IMAGES",
+        value_delimiter = ','
+    )]
+    pub(crate) allowed_images: Vec<String>,
+    /// Service accounts Secrets may select with the `cronjob-service-account` annotation,
+    /// comma separated
+    #[clap(
+        long = "cronjob-allowed-service-accounts",
+        env = "RUNO_CRONJOB_ALLOWED_SERVICE_ACCOUNTS",
+        value_delimiter = ','
+    )]
+    pub(crate) allowed_service_accounts: Vec<String>,
+    /// Image pull secrets Secrets may select with the `cronjob-image-pull-secrets` annotation,
+    /// comma separated
+    #[clap(
+        long = "cronjob-allowed-image-pull-secrets",
+        env = "RUNO_CRONJOB_ALLOWED_IMAGE_PULL_SECRETS",
+        value_delimiter = ','
+    )]
+    pub(crate) allowed_image_pull_secrets: Vec<String>,
+    /// Priority classes Secrets may select with the `cronjob-priority-class` annotation, comma
+    /// separated
+    #[clap(
+        long = "cronjob-allowed-priority-classes",
+        env = "RUNO_CRONJOB_ALLOWED_PRIORITY_CLASSES",
+        value_delimiter = ','
+    )]
+    pub(crate) allowed_priority_classes: Vec<String>,
+    /// Allow Secrets to override resources, node selector and tolerations with the
+    /// `cronjob-resources`, `cronjob-node-selector` and `cronjob-tolerations` annotations
+    #[clap(
+        long = "cronjob-allow-scheduling-overrides",
+        env = "RUNO_CRONJOB_ALLOW_SCHEDULING_OVERRIDES",
+        default_value_t = false
+    )]
+    pub(crate) allow_scheduling_overrides: bool,
+}
+
+impl Default for CronJobTemplate {
+    fn default() -> Self {
+        CronJobTemplate {
+            image: DEFAULT_CRONJOB_IMAGE.to_string(),
+            image_pull_policy: DEFAULT_CRONJOB_IMAGE_PULL_POLICY.to_string(),
+            image_pull_secrets: vec![],
+            service_account: DEFAULT_CRONJOB_SERVICE_ACCOUNT.to_string(),
+            resources: None,
+            node_selector: vec![],
+            tolerations: vec![],
+            priority_class: None,
+            successful_jobs_history_limit: None,
+            failed_jobs_history_limit: None,
+            concurrency_policy: None,
+            allowed_images: vec![],
+            allowed_service_accounts: vec![],
+            allowed_image_pull_secrets: vec![],
+            allowed_priority_classes: vec![],
+            allow_scheduling_overrides: false,
+        }
+    }
+}
+
+/// Namespaces the controller is responsible for
+#[derive(Args, Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NamespaceScope {
+    /// Namespaces to watch, comma separated. All namespaces are watched if none is given.
+    #[clap(long = "namespace", env = "RUNO_NAMESPACE", value_delimiter = ',')]
+    pub(crate) namespaces: Vec<String>,
+    /// Label selector of namespaces to watch
+    #[clap(
+        long = "namespace-selector",
+        env = "RUNO_NAMESPACE_SELECTOR",
+        conflicts_with = "namespaces"
+    )]
+    pub(crate) selector: Option<String>,
+    /// Namespaces to ignore, comma separated
+    #[clap(
+        long = "deny-namespaces",
+        env = "RUNO_DENY_NAMESPACES",
+        value_delimiter = ','
+    )]
+    pub(crate) denied: Vec<String>,
+}
+
+#[derive(Clone)]
 pub struct RunoConfig {
     pub(crate) k8s: K8s,
     pub(crate) requeue_duration: u64,
+    pub(crate) renewal_mode: RenewalMode,
+    pub(crate) cronjob_template: CronJobTemplate,
+    pub(crate) renewal_limiter: Arc<RenewalLimiter>,
+    pub(crate) allowed_clone_namespaces: Vec<String>,
+    pub(crate) allowed_replication_namespaces: Vec<String>,
+    pub(crate) config_maps: bool,
+    pub(crate) namespace_scope: NamespaceScope,
+    pub(crate) selected_namespaces: Option<Store<Namespace>>,
+    pub(crate) settings: SharedSettings,
+    pub(crate) settings_file: Option<PathBuf>,
+    pub(crate) cleaned_up_cronjobs: Arc<CleanedUp>,
 }
 
 impl RunoConfig {
@@ -11,8 +251,86 @@ impl RunoConfig {
         RunoConfig {
             k8s,
             requeue_duration,
+            renewal_mode: RenewalMode::Native,
+            cronjob_template: CronJobTemplate::default(),
+            renewal_limiter: Arc::new(RenewalLimiter::default()),
+            allowed_clone_namespaces: vec![],
+            allowed_replication_namespaces: vec![],
+            config_maps: false,
+            namespace_scope: NamespaceScope::default(),
+            selected_namespaces: None,
+            settings: Arc::new(RwLock::new(Arc::new(Settings::default()))),
+            settings_file: None,
+            cleaned_up_cronjobs: Arc::new(CleanedUp::default()),
         }
     }
+
+    pub fn with_renewal_mode(mut self, renewal_mode: RenewalMode) -> RunoConfig {
+        self.renewal_mode = renewal_mode;
+        self
+    }
+
+    pub fn with_cronjob_template(mut self, cronjob_template: CronJobTemplate) -> RunoConfig {
+        self.cronjob_template = cronjob_template;
+        self
+    }
+
+    pub fn with_renewal_limiter(mut self, renewal_limiter: RenewalLimiter) -> RunoConfig {
+        self.renewal_limiter = Arc::new(renewal_limiter);
+        self
+    }
+
+    pub fn with_allowed_clone_namespaces(mut self, namespaces: Vec<String>) -> RunoConfig {
+        self.allowed_clone_namespaces = namespaces;
+        self
+    }
+
+    pub fn with_allowed_replication_namespaces(mut self, namespaces: Vec<String>) -> RunoConfig {
+        self.allowed_replication_namespaces = namespaces;
+        self
+    }
+
+    pub fn with_config_maps(mut self, config_maps: bool) -> RunoConfig {
+        self.config_maps = config_maps;
+        self
+    }
+
+    pub fn with_namespace_scope(mut self, namespace_scope: NamespaceScope) -> RunoConfig {
+        self.namespace_scope = namespace_scope;
+        self
+    }
+
+    pub fn with_selected_namespaces(mut self, selected: Store<Namespace>) -> RunoConfig {
+        self.selected_namespaces = Some(selected);
+        self
+    }
+
+    /// Settings of the config file, which is reloaded while the controller runs
+    pub fn with_settings(mut self, settings: Settings, file: Option<PathBuf>) -> RunoConfig {
+        settings::apply(&self.settings, settings);
+        self.settings_file = file;
+        self
+    }
+
+    pub fn settings(&self) -> Arc<Settings> {
+        self.settings.read().unwrap().clone()
+    }
+
+    /// CronJob settings of the config file or of the command line
+    pub fn cronjob_template(&self) -> CronJobTemplate {
+        self.settings()
+            .cronjob
+            .clone()
+            .unwrap_or(self.cronjob_template.clone())
+    }
+
+    /// Namespace scope of the config file or of the command line
+    pub fn namespace_scope(&self) -> NamespaceScope {
+        self.settings()
+            .namespace_scope
+            .clone()
+            .unwrap_or(self.namespace_scope.clone())
+    }
 }
 
 #[cfg(test)]
@@ -21,7 +339,11 @@ mod tests {
 
     use crate::k8s::K8s;
 
-    use super::RunoConfig;
+    use super::{
+        parse_json, parse_key_value, CronJobTemplate, NamespaceScope, RenewalMode, RunoConfig,
+    };
+    use crate::settings::Settings;
+    use k8s_openapi::api::core::v1::{ResourceRequirements, Toleration};
 
     #[fixture]
     fn valid_k8s() -> K8s {
@@ -34,4 +356,143 @@ mod tests {
         let config = RunoConfig::build(valid_k8s, requeue_duration);
         assert_eq!(config.requeue_duration, requeue_duration)
     }
+
+    #[rstest]
+    fn settings_replace_command_line(valid_k8s: K8s) {
+        let scope = NamespaceScope {
+            denied: vec!["kube-system".to_string()],
+            ..NamespaceScope::default()
+        };
+        let config = RunoConfig::build(valid_k8s, 10).with_namespace_scope(scope.clone());
+        assert_eq!(config.namespace_scope(), scope);
+        assert_eq!(config.cronjob_template(), CronJobTemplate::default());
+        let settings = Settings {
+            cronjob: Some(CronJobTemplate {
+                image: "registry.example.com/kubectl".to_string(),
+                ..CronJobTemplate::default()
+            }),
+            namespace_scope: Some(NamespaceScope::default()),
+            ..Settings::default()
+        };
+        let config = config.with_settings(settings, None);
+        assert_eq!(config.namespace_scope(), NamespaceScope::default());
+        assert_eq!(
+            config.cronjob_template().image,
+            "registry.example.com/kubectl"
+        );
+    }
+
+    #[rstest]
+    fn build_defaults_to_native_renewal(valid_k8s: K8s) {
+        let config = RunoConfig::build(valid_k8s, 10);
+        assert_eq!(config.renewal_mode, RenewalMode::Native)
+    }
+
+    #[rstest]
+    fn build_with_cronjob_renewal(valid_k8s: K8s) {
+        let config = RunoConfig::build(valid_k8s, 10).with_renewal_mode(RenewalMode::CronJob);
+        assert_eq!(config.renewal_mode, RenewalMode::CronJob)
+    }
+
+    #[rstest]
+    fn build_defaults_to_cronjob_template(valid_k8s: K8s) {
+        let config = RunoConfig::build(valid_k8s, 10);
+        assert_eq!(config.cronjob_template, CronJobTemplate::default());
+        assert_eq!(config.cronjob_template.service_account, "runo-cronjob");
+    }
+
+    #[rstest]
+    fn build_with_cronjob_template(valid_k8s: K8s) {
+        let template = CronJobTemplate {
+            image: "registry.local/kubectl".to_string(),
+            ..CronJobTemplate::default()
+        };
+        let config = RunoConfig::build(valid_k8s, 10).with_cronjob_template(template.clone());
+        assert_eq!(config.cronjob_template, template)
+    }
+
+    #[rstest]
+    fn build_without_allowed_clone_namespaces(valid_k8s: K8s) {
+        let config = RunoConfig::build(valid_k8s, 10);
+        assert!(config.allowed_clone_namespaces.is_empty())
+    }
+
+    #[rstest]
+    fn build_with_allowed_clone_namespaces(valid_k8s: K8s) {
+        let config = RunoConfig::build(valid_k8s, 10)
+            .with_allowed_clone_namespaces(vec!["databases".to_string()]);
+        assert_eq!(config.allowed_clone_namespaces, vec!["databases"])
+    }
+
+    #[rstest]
+    fn build_with_allowed_replication_namespaces(valid_k8s: K8s) {
+        let config = RunoConfig::build(valid_k8s, 10)
+            .with_allowed_replication_namespaces(vec!["monitoring".to_string()]);
+        assert_eq!(config.allowed_replication_namespaces, vec!["monitoring"])
+    }
+
+    #[rstest]
+    fn build_defaults_to_all_namespaces(valid_k8s: K8s) {
+        let config = RunoConfig::build(valid_k8s, 10);
+        assert_eq!(config.namespace_scope, NamespaceScope::default());
+        assert!(config.selected_namespaces.is_none())
+    }
+
+    #[rstest]
+    fn build_with_namespace_scope(valid_k8s: K8s) {
+        let scope = NamespaceScope {
+            denied: vec!["kube-system".to_string()],
+            ..NamespaceScope::default()
+        };
+        let config = RunoConfig::build(valid_k8s, 10).with_namespace_scope(scope.clone());
+        assert_eq!(config.namespace_scope, scope)
+    }
+
+    #[rstest]
+    fn build_without_config_maps(valid_k8s: K8s) {
+        let config = RunoConfig::build(valid_k8s, 10);
+        assert!(!config.config_maps)
+    }
+
+    #[rstest]
+    fn build_with_config_maps(valid_k8s: K8s) {
+        let config = RunoConfig::build(valid_k8s, 10).with_config_maps(true);
+        assert!(config.config_maps)
+    }
+
+    #[rstest]
+    #[case("disktype=ssd", ("disktype", "ssd"))]
+    #[case("empty=", ("empty", ""))]
+    #[case("a=b=c", ("a", "b=c"))]
+    fn parse_valid_key_value(#[case] value: &str, #[case] expected: (&str, &str)) {
+        assert_eq!(
+            parse_key_value(value).unwrap(),
+            (expected.0.to_string(), expected.1.to_string())
+        )
+    }
+
+    #[rstest]
+    #[case("disktype")]
+    #[case("=ssd")]
+    fn parse_invalid_key_value(#[case] value: &str) {
+        assert!(parse_key_value(value).is_err())
+    }
+
+    #[rstest]
+    fn parse_valid_json() {
+        let resources =
+            parse_json::<ResourceRequirements>(r#"{"limits":{"cpu":"100m","memory":"64Mi"}}"#)
+                .unwrap();
+        assert!(resources.limits.unwrap().contains_key("memory"));
+        let toleration =
+            parse_json::<Toleration>(r#"{"key":"dedicated","operator":"Exists"}"#).unwrap();
+        assert_eq!(toleration.key, Some("dedicated".to_string()));
+    }
+
+    #[rstest]
+    #[case("{")]
+    #[case("[]")]
+    fn parse_invalid_json(#[case] value: &str) {
+        assert!(parse_json::<ResourceRequirements>(value).is_err())
+    }
 }
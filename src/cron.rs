@@ -1,18 +1,33 @@
 use crate::annotations;
-use crate::annotations::{id_iter, renewal_cron, AnnotationResult};
-use crate::errors::NoNamespaceForSecret;
+use crate::annotations::{id_iter, is_paused, renewal_cron, renewal_timezone};
+use crate::config::{parse_json, parse_key_value, ConcurrencyPolicy, CronJobTemplate};
+use crate::errors::{CronJobCollision, FollowUpFailed, NoNamespaceForSecret};
 use crate::k8s::K8s;
+use crate::labels::{get_field_id_label, get_secret_name_label, to_label_value};
+use crate::schedule;
+use crate::settings::Settings;
+use clap::ValueEnum;
 use k8s_openapi::api::batch::v1::{CronJob, CronJobSpec, JobSpec, JobTemplateSpec};
 use k8s_openapi::api::core::v1::{
-    Capabilities, Container, PodSpec, PodTemplateSpec, Secret, SecurityContext,
+    Capabilities, Container, LocalObjectReference, PodSpec, PodTemplateSpec, ResourceRequirements,
+    Secret, SecurityContext, Toleration,
 };
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
-use kube::{Api, ResourceExt};
-use std::sync::Arc;
+use kube::api::ListParams;
+use kube::{Api, Resource, ResourceExt};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
 use tracing::{debug, error};
 
-fn build_cronjob(obj: &Arc<Secret>, secret_name: &str, id: &str) -> CronJob {
-    let cron_spec = renewal_cron(obj, id);
+fn build_cronjob(
+    obj: &Arc<Secret>,
+    secret_name: &str,
+    id: &str,
+    template: &CronJobTemplate,
+    settings: &Settings,
+) -> CronJob {
+    let cron_spec = renewal_cron(obj, id, settings);
     debug!(
         "Will create cron job with pattern {:?} for {:?} and id {}",
         cron_spec.clone().get_value(),
@@ -21,30 +36,50 @@ fn build_cronjob(obj: &Arc<Secret>, secret_name: &str, id: &str) -> CronJob {
     );
 
     CronJob {
-        metadata: build_cronjob_object_meta(&build_cron_name(obj, id)),
-        spec: build_cronjob_spec(cron_spec, secret_name, id),
+        metadata: build_cronjob_object_meta(obj, &build_cron_name(obj, id), id),
+        spec: build_cronjob_spec(obj, secret_name, id, template, settings),
         ..CronJob::default()
     }
 }
 
-fn build_cronjob_object_meta(cron_name: &str) -> ObjectMeta {
+fn build_cronjob_object_meta(obj: &Arc<Secret>, cron_name: &str, id: &str) -> ObjectMeta {
     ObjectMeta {
         name: Some(cron_name.into()),
+        labels: Some(build_cronjob_labels(obj, id)),
+        owner_references: obj.owner_ref(&()).map(|o| vec![o]),
         ..ObjectMeta::default()
     }
 }
 
+fn build_cronjob_labels(obj: &Arc<Secret>, id: &str) -> BTreeMap<String, String> {
+    let mut labels = BTreeMap::new();
+    labels.insert(get_secret_name_label(), to_label_value(&obj.name_any()));
+    labels.insert(get_field_id_label(), to_label_value(id));
+    labels
+}
+
 fn build_cronjob_spec(
-    cron_spec: AnnotationResult<String>,
+    obj: &Arc<Secret>,
     secret_name: &str,
     id: &str,
+    template: &CronJobTemplate,
+    settings: &Settings,
 ) -> CronJobSpec {
+    let timezone = renewal_timezone(obj, id, settings);
     CronJobSpec {
-        schedule: cron_spec.get_value(),
+        schedule: renewal_cron(obj, id, settings).get_value(),
+        time_zone: match timezone.exists() {
+            true => Some(timezone.get_value()),
+            false => None,
+        },
+        suspend: Some(is_paused(obj, id)),
+        concurrency_policy: template.concurrency_policy.map(|p| p.as_str().to_string()),
+        successful_jobs_history_limit: template.successful_jobs_history_limit,
+        failed_jobs_history_limit: template.failed_jobs_history_limit,
         job_template: JobTemplateSpec {
             spec: Some(JobSpec {
                 template: PodTemplateSpec {
-                    spec: build_pod_spec(secret_name, id),
+                    spec: build_pod_spec(secret_name, id, template),
                     ..PodTemplateSpec::default()
                 },
                 ..JobSpec::default()
@@ -55,34 +90,190 @@ fn build_cronjob_spec(
     }
 }
 
-fn build_pod_spec(secret_name: &str, id: &str) -> Option<PodSpec> {
+fn build_pod_spec(secret_name: &str, id: &str, template: &CronJobTemplate) -> Option<PodSpec> {
     Some(PodSpec {
-        containers: build_containers(secret_name, id),
+        containers: build_containers(secret_name, id, template),
         restart_policy: Some("Never".to_string()),
-        service_account_name: Some("runo-cronjob".to_string()),
+        service_account_name: Some(template.service_account.clone()),
+        image_pull_secrets: match template.image_pull_secrets.is_empty() {
+            true => None,
+            false => Some(
+                template
+                    .image_pull_secrets
+                    .iter()
+                    .map(|name| LocalObjectReference { name: name.clone() })
+                    .collect(),
+            ),
+        },
+        node_selector: match template.node_selector.is_empty() {
+            true => None,
+            false => Some(template.node_selector.iter().cloned().collect()),
+        },
+        tolerations: match template.tolerations.is_empty() {
+            true => None,
+            false => Some(template.tolerations.clone()),
+        },
+        priority_class_name: template.priority_class.clone(),
         ..PodSpec::default()
     })
 }
 
-fn build_containers(secret_name: &str, id: &str) -> Vec<Container> {
+fn build_args(secret_name: &str, id: &str) -> Vec<String> {
+    vec![
+        "annotate".to_string(),
+        "--overwrite".to_string(),
+        "secrets".to_string(),
+        secret_name.to_string(),
+        format!("v1.secret.runo.rocks/renewal-{}=true", id),
+    ]
+}
+
+fn build_containers(secret_name: &str, id: &str, template: &CronJobTemplate) -> Vec<Container> {
     vec![Container {
-        args: Some(vec![
-            "annotate".to_string(),
-            "--overwrite".to_string(),
-            "secrets".to_string(),
-            secret_name.to_string(),
-            format!("v1.secret.runo.rocks/renewal-{}=true", id),
-        ]),
+        args: Some(build_args(secret_name, id)),
         command: None,
-        image: Some("cgr.dev/chainguard/kubectl".to_string()),
-        image_pull_policy: Some("Always".to_string()),
+        image: Some(template.image.clone()),
+        image_pull_policy: Some(template.image_pull_policy.clone()),
         name: "runo-cron-container".to_string(),
-        resources: None,
+        resources: template.resources.clone(),
         security_context: build_security_context(),
         ..Container::default()
     }]
 }
 
+/// Annotation to override a setting of the renewal CronJobs for a Secret, e.g.
+/// `v1.secret.runo.rocks/cronjob-image`
+pub fn get_cronjob_annotation(setting: &str) -> String {
+    format!("v1.secret.runo.rocks/cronjob-{}", setting)
+}
+
+/// Split a comma separated annotation value and drop empty entries
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+fn parse_history_limit(value: &str) -> Result<Option<i32>, String> {
+    match value.parse::<i32>() {
+        Ok(limit) if limit >= 0 => Ok(Some(limit)),
+        _ => Err(format!("{} is not a valid history limit", value)),
+    }
+}
+
+/// Values which grant the renewal jobs other rights than the controller chose have to be
+/// allowed by the controller, otherwise anyone who can annotate a Secret could run any image
+/// with any service account through runo
+fn check_allowed<'a>(
+    kind: &str,
+    value: &'a str,
+    default: &[String],
+    allowed: &[String],
+) -> Result<&'a str, String> {
+    match default.iter().chain(allowed).any(|v| v == value) {
+        true => Ok(value),
+        false => Err(format!("{} is not an allowed {}", value, kind)),
+    }
+}
+
+fn apply_override(
+    template: &mut CronJobTemplate,
+    setting: &str,
+    value: &str,
+) -> Result<(), String> {
+    match setting {
+        "image" => {
+            template.image = check_allowed(
+                "image",
+                value,
+                std::slice::from_ref(&template.image),
+                &template.allowed_images,
+            )?
+            .to_string()
+        }
+        "image-pull-policy" => match value {
+            "Always" | "IfNotPresent" | "Never" => template.image_pull_policy = value.to_string(),
+            _ => return Err(format!("{} is not a valid image pull policy", value)),
+        },
+        "image-pull-secrets" => {
+            template.image_pull_secrets = split_list(value)
+                .iter()
+                .map(|name| {
+                    check_allowed(
+                        "image pull secret",
+                        name,
+                        &template.image_pull_secrets,
+                        &template.allowed_image_pull_secrets,
+                    )
+                    .map(String::from)
+                })
+                .collect::<Result<_, _>>()?
+        }
+        "service-account" => {
+            template.service_account = check_allowed(
+                "service account",
+                value,
+                std::slice::from_ref(&template.service_account),
+                &template.allowed_service_accounts,
+            )?
+            .to_string()
+        }
+        "resources" | "node-selector" | "tolerations" if !template.allow_scheduling_overrides => {
+            return Err(format!(
+                "{} can only be overridden with --cronjob-allow-scheduling-overrides",
+                setting
+            ))
+        }
+        "resources" => template.resources = Some(parse_json::<ResourceRequirements>(value)?),
+        "node-selector" => {
+            template.node_selector = split_list(value)
+                .iter()
+                .map(|pair| parse_key_value(pair))
+                .collect::<Result<_, _>>()?
+        }
+        "tolerations" => template.tolerations = parse_json::<Vec<Toleration>>(value)?,
+        "priority-class" => {
+            template.priority_class = Some(
+                check_allowed(
+                    "priority class",
+                    value,
+                    template.priority_class.as_slice(),
+                    &template.allowed_priority_classes,
+                )?
+                .to_string(),
+            )
+        }
+        "successful-jobs-history-limit" => {
+            template.successful_jobs_history_limit = parse_history_limit(value)?
+        }
+        "failed-jobs-history-limit" => {
+            template.failed_jobs_history_limit = parse_history_limit(value)?
+        }
+        "concurrency-policy" => {
+            template.concurrency_policy = Some(ConcurrencyPolicy::from_str(value, true)?)
+        }
+        _ => return Err(format!("{} is not a known CronJob setting", setting)),
+    }
+    Ok(())
+}
+
+/// Apply the `v1.secret.runo.rocks/cronjob-*` annotations of the Secret on top of the
+/// controller-level template. Invalid values are reported and the controller-level value is kept.
+fn resolve_template(obj: &Arc<Secret>, defaults: &CronJobTemplate) -> CronJobTemplate {
+    let prefix = get_cronjob_annotation("");
+    let mut template = defaults.clone();
+    for (key, value) in obj.annotations() {
+        if let Some(setting) = key.strip_prefix(prefix.as_str()) {
+            if let Err(e) = apply_override(&mut template, setting, value) {
+                error!("Invalid {:?} on {:?}: {}", key, obj.name_any(), e)
+            }
+        }
+    }
+    template
+}
+
 fn build_security_context() -> Option<SecurityContext> {
     Some(SecurityContext {
         allow_privilege_escalation: Some(false),
@@ -99,54 +290,310 @@ fn build_security_context() -> Option<SecurityContext> {
     })
 }
 
-async fn create_or_replace(cj: CronJob, namespace: &str, k8s: &K8s) {
+/// A CronJob belongs to a field of a Secret if it's owned by it and carries the labels runo sets
+fn belongs_to(cj: &CronJob, obj: &Arc<Secret>, id: &str) -> bool {
+    owned_by(cj, obj)
+        && cj.labels().get(&get_secret_name_label()) == Some(&to_label_value(&obj.name_any()))
+        && cj.labels().get(&get_field_id_label()) == Some(&to_label_value(id))
+}
+
+async fn create_or_replace(
+    cj: CronJob,
+    obj: &Arc<Secret>,
+    id: &str,
+    namespace: &str,
+    k8s: &K8s,
+) -> bool {
     let cronjobs: Api<CronJob> = Api::namespaced(K8s::get_client().await, namespace);
     let c = cronjobs.create(&k8s.get_post_params(), &cj).await;
     match c {
         Err(_e) => {
+            match cronjobs.get_opt(&cj.name_any()).await {
+                Ok(Some(existing)) if !belongs_to(&existing, obj, id) => {
+                    error!(
+                        "{}",
+                        CronJobCollision {
+                            name: cj.name_any()
+                        }
+                    );
+                    return false;
+                }
+                Err(e) => {
+                    error!("{:?}", e);
+                    return false;
+                }
+                _ => (),
+            }
             let r = cronjobs
                 .replace(&cj.name_any(), &k8s.get_post_params(), &cj)
                 .await;
             match r {
-                Err(e) => error!("{:?}", e),
-                Ok(cj) => debug!("{:?} replaced successfully", cj.metadata.name.unwrap()),
+                Err(e) => {
+                    error!("{:?}", e);
+                    false
+                }
+                Ok(cj) => {
+                    debug!("{:?} replaced successfully", cj.metadata.name.unwrap());
+                    true
+                }
             }
         }
-        Ok(cj) => debug!("{:?} created successfully", cj.metadata.name.unwrap()),
+        Ok(cj) => {
+            debug!("{:?} created successfully", cj.metadata.name.unwrap());
+            true
+        }
     }
 }
 
+/// Lowercase the value and replace everything that isn't allowed in a DNS label with a dash
+fn to_dns_label(value: &str) -> String {
+    value
+        .to_lowercase()
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c,
+            false => '-',
+        })
+        .collect()
+}
+
+/// The name consists of a readable part and a hash of namespace, name and id of the field. It
+/// respects the 52 characters limit of CronJob names.
 pub fn build_cron_name(obj: &Arc<Secret>, id: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(format!(
+        "{}/{}/{}",
+        obj.namespace().unwrap_or_default(),
+        obj.name_any(),
+        id
+    ));
+    let hash: String = hasher
+        .finalize()
+        .iter()
+        .take(5)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let mut readable: String = to_dns_label(&format!("{}-{}", obj.name_any(), id))
+        .chars()
+        .take(52 - "runo-renewal-".len() - hash.len() - 1)
+        .collect();
+    while readable.ends_with('-') {
+        readable.pop();
+    }
+    format!("runo-renewal-{}-{}", readable, hash)
+}
+
+/// Name of renewal CronJobs created by earlier versions of runo
+fn build_legacy_cron_name(obj: &Arc<Secret>, id: &str) -> String {
     let mut trunc_obj_name = obj.name_any();
     trunc_obj_name.truncate(20);
     format!("runo-renewal-{}-{}", trunc_obj_name, id)
 }
 
-pub async fn update(obj: &Arc<Secret>, k8s: &K8s) {
+/// Check if a CronJob created by earlier versions of runo renews the given field
+fn renews_field(cj: &CronJob, obj: &Arc<Secret>, id: &str) -> bool {
+    let expected_args = Some(build_args(obj.name_any().as_str(), id));
+    cj.labels().get(&get_field_id_label()).is_none()
+        && cj
+            .spec
+            .job_template
+            .spec
+            .as_ref()
+            .and_then(|s| s.template.spec.as_ref())
+            .and_then(|s| s.containers.first())
+            .is_some_and(|c| c.args == expected_args)
+}
+
+/// Delete CronJobs with the naming scheme of earlier versions, which could collide for
+/// Secrets with a common prefix
+async fn delete_legacy(obj: &Arc<Secret>, namespace: &str, k8s: &K8s) -> bool {
+    let cronjobs: Api<CronJob> = Api::namespaced(K8s::get_client().await, namespace);
+    let mut deleted = true;
+    for id in id_iter(obj) {
+        let legacy_name = build_legacy_cron_name(obj, &id);
+        if let Ok(Some(cj)) = cronjobs.get_opt(&legacy_name).await {
+            if renews_field(&cj, obj, &id) {
+                debug!("Legacy CronJob {:?} will be deleted", legacy_name);
+                if let Err(e) = cronjobs
+                    .delete(&legacy_name, &k8s.get_delete_params())
+                    .await
+                {
+                    error!("{:?}", e);
+                    deleted = false
+                }
+            }
+        }
+    }
+    deleted
+}
+
+fn owned_by(cj: &CronJob, obj: &Arc<Secret>) -> bool {
+    match obj.uid() {
+        Some(uid) => cj.owner_references().iter().any(|o| o.uid == uid),
+        None => true,
+    }
+}
+
+/// Delete all CronJobs of the Secret whose id shouldn't keep its CronJob
+async fn delete_orphans(
+    obj: &Arc<Secret>,
+    namespace: &str,
+    k8s: &K8s,
+    keep: impl Fn(&str) -> bool,
+) -> bool {
+    let cronjobs: Api<CronJob> = Api::namespaced(K8s::get_client().await, namespace);
+    let list_params = ListParams::default().labels(
+        format!(
+            "{}={}",
+            get_secret_name_label(),
+            to_label_value(&obj.name_any())
+        )
+        .as_str(),
+    );
+    let list = match cronjobs.list(&list_params).await {
+        Ok(list) => list,
+        Err(e) => {
+            error!("Can't list CronJobs for {:?}: {:?}", obj.name_any(), e);
+            return false;
+        }
+    };
+    let mut deleted = true;
+    for cj in list.into_iter().filter(|cj| owned_by(cj, obj)) {
+        let keep = cj
+            .labels()
+            .get(&get_field_id_label())
+            .is_some_and(|id| keep(id));
+        if !keep {
+            debug!(
+                "CronJob {:?} isn't needed anymore and will be deleted",
+                cj.name_any()
+            );
+            match cronjobs
+                .delete(&cj.name_any(), &k8s.get_delete_params())
+                .await
+            {
+                Ok(_) => debug!("{:?} deleted successfully", cj.name_any()),
+                Err(e) => {
+                    error!("{:?}", e);
+                    deleted = false
+                }
+            }
+        }
+    }
+    deleted
+}
+
+fn failed() -> FollowUpFailed {
+    FollowUpFailed {
+        step: "cronjobs".to_string(),
+    }
+}
+
+pub async fn update(
+    obj: &Arc<Secret>,
+    k8s: &K8s,
+    defaults: &CronJobTemplate,
+    settings: &Settings,
+) -> Result<(), FollowUpFailed> {
     match obj.namespace() {
         Some(namespace) => {
+            let template = resolve_template(obj, defaults);
+            let mut updated = true;
             for id in id_iter(obj) {
-                if annotations::has_cron(obj, &id) {
+                if annotations::has_cron(obj, &id, settings) {
+                    if let Err(e) = schedule::validate_renewal(obj, &id, settings) {
+                        error!("{} for {:?} and id {}", e, obj.name_any(), id);
+                        updated = false;
+                        continue;
+                    }
                     debug!(
                         "CronJob for {:?} and id {:?} needs to be created",
                         obj.name_any(),
                         id
                     );
-                    let cj = build_cronjob(obj, obj.name_any().as_str(), &id);
-                    create_or_replace(cj, &namespace, k8s).await
+                    let cj = build_cronjob(obj, obj.name_any().as_str(), &id, &template, settings);
+                    updated &= create_or_replace(cj, obj, &id, &namespace, k8s).await
                 }
             }
+            updated &= delete_orphans(obj, &namespace, k8s, |id| {
+                annotations::has_cron(obj, id, settings)
+            })
+            .await;
+            updated &= delete_legacy(obj, &namespace, k8s).await;
+            match updated {
+                true => Ok(()),
+                false => Err(failed()),
+            }
+        }
+        None => {
+            error!("{:?}", NoNamespaceForSecret);
+            Err(failed())
+        }
+    }
+}
+
+/// Secrets and fields whose CronJobs were deleted since the controller started
+pub type CleanedUp = Mutex<BTreeSet<String>>;
+
+/// The Secret and each of its fields, identified by the uid so recreated Secrets are cleaned up
+/// again
+fn cleanup_keys(obj: &Arc<Secret>) -> Vec<String> {
+    let uid = obj.uid().unwrap_or_default();
+    std::iter::once(uid.clone())
+        .chain(id_iter(obj).into_iter().map(|id| format!("{}/{}", uid, id)))
+        .collect()
+}
+
+fn needs_cleanup(obj: &Arc<Secret>, cleaned_up: &CleanedUp) -> bool {
+    let cleaned_up = cleaned_up.lock().unwrap();
+    cleanup_keys(obj)
+        .iter()
+        .any(|key| !cleaned_up.contains(key))
+}
+
+/// Delete all renewal CronJobs of the Secret, including the ones of earlier versions. Fields
+/// are renewed natively, so CronJobs created before would renew them a second time. Native
+/// renewals don't create CronJobs, so each field is only cleaned up once after the renewal mode
+/// was changed and not on every reconciliation.
+pub async fn cleanup(
+    obj: &Arc<Secret>,
+    k8s: &K8s,
+    cleaned_up: &CleanedUp,
+) -> Result<(), FollowUpFailed> {
+    if !needs_cleanup(obj, cleaned_up) {
+        return Ok(());
+    }
+    match obj.namespace() {
+        Some(namespace) => {
+            let orphans_deleted = delete_orphans(obj, &namespace, k8s, |_| false).await;
+            match delete_legacy(obj, &namespace, k8s).await && orphans_deleted {
+                true => {
+                    cleaned_up.lock().unwrap().extend(cleanup_keys(obj));
+                    Ok(())
+                }
+                false => Err(failed()),
+            }
+        }
+        None => {
+            error!("{:?}", NoNamespaceForSecret);
+            Err(failed())
         }
-        None => error!("{:?}", NoNamespaceForSecret),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::cron::{build_cron_name, build_cronjob};
+    use crate::config::{ConcurrencyPolicy, CronJobTemplate};
+    use crate::cron::{
+        belongs_to, build_cron_name, build_cronjob, build_legacy_cron_name, cleanup_keys,
+        needs_cleanup, renews_field, resolve_template, CleanedUp,
+    };
+    use crate::settings::Settings;
     use k8s_openapi::api::core::v1::Secret;
     use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
     use kube::ResourceExt;
+    use rstest::rstest;
+    use std::collections::BTreeMap;
     use std::sync::Arc;
 
     fn build_secret() -> Secret {
@@ -158,10 +605,373 @@ mod tests {
         }
     }
 
-    #[cfg(test)]
+    #[test]
     fn test_build_cronjob() {
         let secret = Arc::from(build_secret());
-        let cronjob = build_cronjob(&secret, "test-secret", "0");
+        let cronjob = build_cronjob(
+            &secret,
+            "test-secret",
+            "0",
+            &CronJobTemplate::default(),
+            &Settings::default(),
+        );
         assert_eq!(build_cron_name(&secret, "0"), cronjob.name_any())
     }
+
+    #[test]
+    fn test_build_cronjob_labels_and_owner() {
+        let mut secret = build_secret();
+        secret.metadata.name = Some("test-secret".to_string());
+        secret.metadata.uid = Some("1234".to_string());
+        let secret = Arc::from(secret);
+        let cronjob = build_cronjob(
+            &secret,
+            "test-secret",
+            "0",
+            &CronJobTemplate::default(),
+            &Settings::default(),
+        );
+        assert_eq!(
+            cronjob.labels().get("v1.secret.runo.rocks/secret-name"),
+            Some(&"test-secret".to_string())
+        );
+        assert_eq!(
+            cronjob.labels().get("v1.secret.runo.rocks/field-id"),
+            Some(&"0".to_string())
+        );
+        let owner = cronjob.owner_references().first().unwrap();
+        assert_eq!(owner.uid, "1234");
+        assert_eq!(owner.kind, "Secret");
+        assert_eq!(owner.name, "test-secret");
+    }
+
+    #[test]
+    fn test_build_cronjob_without_uid_has_no_owner() {
+        let secret = Arc::from(build_secret());
+        let cronjob = build_cronjob(
+            &secret,
+            "test-secret",
+            "0",
+            &CronJobTemplate::default(),
+            &Settings::default(),
+        );
+        assert!(cronjob.owner_references().is_empty());
+    }
+
+    fn build_named_secret(namespace: &str, name: &str) -> Arc<Secret> {
+        let mut secret = build_secret();
+        secret.metadata.name = Some(name.to_string());
+        secret.metadata.namespace = Some(namespace.to_string());
+        Arc::from(secret)
+    }
+
+    #[rstest]
+    #[case("payments-service-db-primary", "payments-service-db-replica")]
+    #[case("a", "b")]
+    fn test_build_cron_name_no_collision(#[case] name_a: &str, #[case] name_b: &str) {
+        let a = build_named_secret("default", name_a);
+        let b = build_named_secret("default", name_b);
+        assert_ne!(build_cron_name(&a, "0"), build_cron_name(&b, "0"));
+    }
+
+    #[test]
+    fn test_build_cron_name_differs_by_namespace() {
+        let a = build_named_secret("default", "secret");
+        let b = build_named_secret("other", "secret");
+        assert_ne!(build_cron_name(&a, "0"), build_cron_name(&b, "0"));
+    }
+
+    #[test]
+    fn test_build_cron_name_is_stable() {
+        let a = build_named_secret("default", "secret");
+        assert_eq!(build_cron_name(&a, "0"), build_cron_name(&a, "0"));
+    }
+
+    #[rstest]
+    #[case("payments-service-db-primary", "0")]
+    #[case(&"a".repeat(253), "0")]
+    #[case("secret", "Some_ID")]
+    fn test_build_cron_name_is_valid(#[case] name: &str, #[case] id: &str) {
+        let secret = build_named_secret("default", name);
+        let cron_name = build_cron_name(&secret, id);
+        assert!(cron_name.len() <= 52);
+        assert!(cron_name.starts_with("runo-renewal-"));
+        assert!(cron_name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-'));
+        assert!(!cron_name.contains("--"));
+    }
+
+    #[test]
+    fn test_belongs_to() {
+        let secret = build_named_secret("default", "secret");
+        let cronjob = build_cronjob(
+            &secret,
+            "secret",
+            "0",
+            &CronJobTemplate::default(),
+            &Settings::default(),
+        );
+        assert!(belongs_to(&cronjob, &secret, "0"));
+        assert!(!belongs_to(&cronjob, &secret, "1"));
+        let other = build_named_secret("default", "other");
+        assert!(!belongs_to(&cronjob, &other, "0"));
+    }
+
+    #[test]
+    fn test_renews_field_of_legacy_cronjob() {
+        let secret = build_named_secret("default", "secret");
+        let mut cronjob = build_cronjob(
+            &secret,
+            "secret",
+            "0",
+            &CronJobTemplate::default(),
+            &Settings::default(),
+        );
+        cronjob.metadata.labels = None;
+        cronjob.metadata.name = Some(build_legacy_cron_name(&secret, "0"));
+        assert!(renews_field(&cronjob, &secret, "0"));
+        assert!(!renews_field(&cronjob, &secret, "1"));
+    }
+
+    fn build_annotated_secret(annotations: Vec<(&str, &str)>) -> Arc<Secret> {
+        let mut secret = build_secret();
+        secret.metadata.name = Some("secret".to_string());
+        secret.metadata.annotations = Some(
+            annotations
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<BTreeMap<String, String>>(),
+        );
+        Arc::from(secret)
+    }
+
+    #[test]
+    fn test_build_cronjob_with_template() {
+        let secret = build_named_secret("default", "secret");
+        let template = CronJobTemplate {
+            image: "registry.local/kubectl:1.36".to_string(),
+            image_pull_policy: "IfNotPresent".to_string(),
+            image_pull_secrets: vec!["registry".to_string()],
+            service_account: "renewer".to_string(),
+            node_selector: vec![("disktype".to_string(), "ssd".to_string())],
+            priority_class: Some("low".to_string()),
+            successful_jobs_history_limit: Some(1),
+            failed_jobs_history_limit: Some(2),
+            concurrency_policy: Some(ConcurrencyPolicy::Forbid),
+            ..CronJobTemplate::default()
+        };
+        let cronjob = build_cronjob(&secret, "secret", "0", &template, &Settings::default());
+        assert_eq!(cronjob.spec.concurrency_policy, Some("Forbid".to_string()));
+        assert_eq!(cronjob.spec.successful_jobs_history_limit, Some(1));
+        assert_eq!(cronjob.spec.failed_jobs_history_limit, Some(2));
+        let pod_spec = cronjob
+            .spec
+            .job_template
+            .spec
+            .unwrap()
+            .template
+            .spec
+            .unwrap();
+        assert_eq!(pod_spec.service_account_name, Some("renewer".to_string()));
+        assert_eq!(pod_spec.priority_class_name, Some("low".to_string()));
+        assert_eq!(pod_spec.image_pull_secrets.unwrap()[0].name, "registry");
+        assert_eq!(
+            pod_spec.node_selector.unwrap().get("disktype"),
+            Some(&"ssd".to_string())
+        );
+        let container = pod_spec.containers.first().unwrap();
+        assert_eq!(
+            container.image,
+            Some("registry.local/kubectl:1.36".to_string())
+        );
+        assert_eq!(
+            container.image_pull_policy,
+            Some("IfNotPresent".to_string())
+        );
+    }
+
+    #[test]
+    fn test_build_cronjob_with_default_template() {
+        let secret = build_named_secret("default", "secret");
+        let cronjob = build_cronjob(
+            &secret,
+            "secret",
+            "0",
+            &CronJobTemplate::default(),
+            &Settings::default(),
+        );
+        assert_eq!(cronjob.spec.concurrency_policy, None);
+        let pod_spec = cronjob
+            .spec
+            .job_template
+            .spec
+            .unwrap()
+            .template
+            .spec
+            .unwrap();
+        assert_eq!(
+            pod_spec.service_account_name,
+            Some("runo-cronjob".to_string())
+        );
+        assert_eq!(pod_spec.image_pull_secrets, None);
+        assert_eq!(pod_spec.node_selector, None);
+        assert_eq!(pod_spec.tolerations, None);
+        assert_eq!(
+            pod_spec.containers.first().unwrap().image,
+            Some("cgr.dev/chainguard/kubectl".to_string())
+        );
+    }
+
+    #[test]
+    fn test_resolve_template_with_overrides() {
+        let secret = build_annotated_secret(vec![
+            (
+                "v1.secret.runo.rocks/cronjob-image",
+                "registry.local/kubectl",
+            ),
+            ("v1.secret.runo.rocks/cronjob-image-pull-secrets", "a, b"),
+            ("v1.secret.runo.rocks/cronjob-service-account", "renewer"),
+            (
+                "v1.secret.runo.rocks/cronjob-resources",
+                r#"{"limits":{"cpu":"100m"}}"#,
+            ),
+            ("v1.secret.runo.rocks/cronjob-node-selector", "a=b,c=d"),
+            (
+                "v1.secret.runo.rocks/cronjob-tolerations",
+                r#"[{"key":"dedicated","operator":"Exists"}]"#,
+            ),
+            ("v1.secret.runo.rocks/cronjob-priority-class", "low"),
+            (
+                "v1.secret.runo.rocks/cronjob-successful-jobs-history-limit",
+                "0",
+            ),
+            (
+                "v1.secret.runo.rocks/cronjob-failed-jobs-history-limit",
+                "3",
+            ),
+            ("v1.secret.runo.rocks/cronjob-concurrency-policy", "Forbid"),
+        ]);
+        let defaults = CronJobTemplate {
+            allowed_images: vec!["registry.local/kubectl".to_string()],
+            allowed_service_accounts: vec!["renewer".to_string()],
+            allowed_image_pull_secrets: vec!["a".to_string(), "b".to_string()],
+            allowed_priority_classes: vec!["low".to_string()],
+            allow_scheduling_overrides: true,
+            ..CronJobTemplate::default()
+        };
+        let template = resolve_template(&secret, &defaults);
+        assert_eq!(template.image, "registry.local/kubectl");
+        assert_eq!(template.image_pull_secrets, vec!["a", "b"]);
+        assert_eq!(template.service_account, "renewer");
+        assert!(template
+            .resources
+            .unwrap()
+            .limits
+            .unwrap()
+            .contains_key("cpu"));
+        assert_eq!(
+            template.node_selector,
+            vec![
+                ("a".to_string(), "b".to_string()),
+                ("c".to_string(), "d".to_string())
+            ]
+        );
+        assert_eq!(template.tolerations.len(), 1);
+        assert_eq!(template.priority_class, Some("low".to_string()));
+        assert_eq!(template.successful_jobs_history_limit, Some(0));
+        assert_eq!(template.failed_jobs_history_limit, Some(3));
+        assert_eq!(template.concurrency_policy, Some(ConcurrencyPolicy::Forbid));
+    }
+
+    #[rstest]
+    #[case("v1.secret.runo.rocks/cronjob-image-pull-policy", "Sometimes")]
+    #[case("v1.secret.runo.rocks/cronjob-resources", "{")]
+    #[case("v1.secret.runo.rocks/cronjob-node-selector", "ssd")]
+    #[case("v1.secret.runo.rocks/cronjob-successful-jobs-history-limit", "-1")]
+    #[case("v1.secret.runo.rocks/cronjob-concurrency-policy", "Sometimes")]
+    #[case("v1.secret.runo.rocks/cronjob-unknown", "value")]
+    #[case("v1.secret.runo.rocks/cronjob-image", "attacker.example.com/shell")]
+    #[case("v1.secret.runo.rocks/cronjob-service-account", "cluster-admin")]
+    #[case(
+        "v1.secret.runo.rocks/cronjob-image-pull-secrets",
+        "registry-credentials"
+    )]
+    #[case(
+        "v1.secret.runo.rocks/cronjob-priority-class",
+        "system-cluster-critical"
+    )]
+    #[case(
+        "v1.secret.runo.rocks/cronjob-resources",
+        r#"{"limits":{"cpu":"100m"}}"#
+    )]
+    #[case(
+        "v1.secret.runo.rocks/cronjob-node-selector",
+        "kubernetes.io/hostname=control-plane"
+    )]
+    #[case(
+        "v1.secret.runo.rocks/cronjob-tolerations",
+        r#"[{"operator":"Exists"}]"#
+    )]
+    fn test_resolve_template_keeps_defaults_on_invalid_override(
+        #[case] key: &str,
+        #[case] value: &str,
+    ) {
+        let secret = build_annotated_secret(vec![(key, value)]);
+        assert_eq!(
+            resolve_template(&secret, &CronJobTemplate::default()),
+            CronJobTemplate::default()
+        );
+    }
+
+    #[test]
+    fn test_build_cronjob_with_timezone_and_pause() {
+        let secret = build_annotated_secret(vec![
+            ("v1.secret.runo.rocks/generate-0", "password"),
+            ("v1.secret.runo.rocks/renewal-cron-0", "0 0 * * *"),
+            ("v1.secret.runo.rocks/renewal-timezone-0", "Europe/Berlin"),
+            ("v1.secret.runo.rocks/pause-0", "true"),
+        ]);
+        let cronjob = build_cronjob(
+            &secret,
+            "secret",
+            "0",
+            &CronJobTemplate::default(),
+            &Settings::default(),
+        );
+        assert_eq!(cronjob.spec.schedule, "0 0 * * *");
+        assert_eq!(cronjob.spec.time_zone, Some("Europe/Berlin".to_string()));
+        assert_eq!(cronjob.spec.suspend, Some(true));
+    }
+
+    #[test]
+    fn test_build_cronjob_without_timezone_and_pause() {
+        let secret = build_annotated_secret(vec![
+            ("v1.secret.runo.rocks/generate-0", "password"),
+            ("v1.secret.runo.rocks/renewal-cron-0", "0 0 * * *"),
+        ]);
+        let cronjob = build_cronjob(
+            &secret,
+            "secret",
+            "0",
+            &CronJobTemplate::default(),
+            &Settings::default(),
+        );
+        assert_eq!(cronjob.spec.time_zone, None);
+        assert_eq!(cronjob.spec.suspend, Some(false));
+    }
+
+    #[test]
+    fn test_needs_cleanup_once_per_field() {
+        let secret = build_annotated_secret(vec![("v1.secret.runo.rocks/generate-0", "password")]);
+        let cleaned_up = CleanedUp::default();
+        assert!(needs_cleanup(&secret, &cleaned_up));
+        cleaned_up.lock().unwrap().extend(cleanup_keys(&secret));
+        assert!(!needs_cleanup(&secret, &cleaned_up));
+        let extended = build_annotated_secret(vec![
+            ("v1.secret.runo.rocks/generate-0", "password"),
+            ("v1.secret.runo.rocks/generate-1", "token"),
+        ]);
+        assert!(needs_cleanup(&extended, &cleaned_up));
+    }
 }
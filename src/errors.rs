@@ -9,15 +9,6 @@ impl fmt::Display for NoNamespaceForSecret {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct AnnotationsDoesntExist;
-
-impl fmt::Display for AnnotationsDoesntExist {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "No annotations in metadata")
-    }
-}
-
 #[derive(Debug, Clone)]
 pub struct CantCreateStringFromRegex;
 
@@ -69,12 +60,22 @@ impl fmt::Display for AnnotationUpdateError {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct SecretUpdateError;
+/// A conflict means that the resource was changed after it was read, so the update can be
+/// retried with the latest version
+#[derive(Debug, Clone, PartialEq)]
+pub enum SecretUpdateError {
+    Failed,
+    Conflict,
+}
 
 impl fmt::Display for SecretUpdateError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Secret update failed!",)
+        match self {
+            SecretUpdateError::Failed => write!(f, "Secret update failed!",),
+            SecretUpdateError::Conflict => {
+                write!(f, "Secret update failed! It was changed in the meantime")
+            }
+        }
     }
 }
 
@@ -88,3 +89,220 @@ impl fmt::Display for DuplicateKeysError {
         write!(f, "Duplicate keys detected: {}", self.duplicates.join(", "))
     }
 }
+
+#[derive(Debug, Clone)]
+pub struct InvalidCronSpec {
+    pub spec: String,
+}
+
+impl fmt::Display for InvalidCronSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid cron spec {:?}!", self.spec)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InvalidDuration {
+    pub value: String,
+}
+
+impl fmt::Display for InvalidDuration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid duration {:?}! Please use a number followed by a unit (s, m, h, d or w), e.g. 720h or 30d",
+            self.value
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InvalidTimezone {
+    pub value: String,
+}
+
+impl fmt::Display for InvalidTimezone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid timezone {:?}! Please use a name of the IANA time zone database, e.g. Europe/Berlin",
+            self.value
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CloneSourceNotAllowed {
+    pub source: String,
+    pub namespace: String,
+}
+
+impl fmt::Display for CloneSourceNotAllowed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Cloning from {} isn't allowed! It has to be managed by runo and list {:?} in v1.secret.runo.rocks/clonable-by",
+            self.source, self.namespace
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CloneNamespaceNotAllowed {
+    pub namespace: String,
+}
+
+impl fmt::Display for CloneNamespaceNotAllowed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Cloning from namespace {:?} isn't allowed! Please add it to --allowed-clone-namespaces",
+            self.namespace
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CronJobCollision {
+    pub name: String,
+}
+
+impl fmt::Display for CronJobCollision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "CronJob {} already exists and isn't owned by runo for this secret!",
+            self.name
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplicaCollision {
+    pub namespace: String,
+    pub name: String,
+}
+
+impl fmt::Display for ReplicaCollision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Secret {} already exists in {} and isn't a replica of this secret!",
+            self.name, self.namespace
+        )
+    }
+}
+
+/// A step after the update of a Secret, like restarting its workloads, failed at least partly
+#[derive(Debug, Clone, PartialEq)]
+pub struct FollowUpFailed {
+    pub step: String,
+}
+
+impl fmt::Display for FollowUpFailed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} failed!", self.step)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NamespaceOutOfScope {
+    pub namespace: String,
+}
+
+impl fmt::Display for NamespaceOutOfScope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Namespace {:?} is out of the scope of the controller!",
+            self.namespace
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplicationNamespaceNotAllowed {
+    pub namespace: String,
+}
+
+impl fmt::Display for ReplicationNamespaceNotAllowed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Replicating to namespace {:?} isn't allowed! Please add it to --allowed-replication-namespaces",
+            self.namespace
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InvalidReplicationTarget {
+    pub value: String,
+    pub reason: String,
+}
+
+impl fmt::Display for InvalidReplicationTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Can't replicate to {:?}! {}", self.value, self.reason)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InvalidManifest {
+    pub reason: String,
+}
+
+impl fmt::Display for InvalidManifest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid manifest! {}", self.reason)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RotationFailed {
+    pub reason: String,
+}
+
+impl fmt::Display for RotationFailed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Rotation failed! {}", self.reason)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PluginFailed {
+    pub reason: String,
+}
+
+impl fmt::Display for PluginFailed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Can't change the Secret! {}", self.reason)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InvalidSettings {
+    pub reason: String,
+}
+
+impl fmt::Display for InvalidSettings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid config file! {}", self.reason)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PolicyViolation {
+    pub id: String,
+    pub reason: String,
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Field with id {} isn't allowed by the config file! {}",
+            self.id, self.reason
+        )
+    }
+}
@@ -1,5 +1,5 @@
-use kube::api::{PatchParams, PostParams};
-use kube::Client;
+use kube::api::{DeleteParams, ListParams, PatchParams, PostParams};
+use kube::{Api, Client};
 use tracing::info;
 
 #[derive(Copy, Clone)]
@@ -23,6 +23,26 @@ impl K8s {
         }
     }
 
+    /// Keys of removed fields are handed to this field manager, so they aren't deleted once runo
+    /// stops applying them
+    pub fn get_release_patch_params(self) -> PatchParams {
+        PatchParams {
+            dry_run: self.dry_run,
+            force: true,
+            field_manager: Some("runo-released".to_string()),
+            field_validation: None,
+        }
+    }
+
+    pub fn get_merge_patch_params(self) -> PatchParams {
+        PatchParams {
+            dry_run: self.dry_run,
+            force: false,
+            field_manager: Some("runo".to_string()),
+            field_validation: None,
+        }
+    }
+
     pub fn get_post_params(self) -> PostParams {
         PostParams {
             dry_run: self.dry_run,
@@ -30,6 +50,13 @@ impl K8s {
         }
     }
 
+    pub fn get_delete_params(self) -> DeleteParams {
+        DeleteParams {
+            dry_run: self.dry_run,
+            ..DeleteParams::default()
+        }
+    }
+
     pub async fn get_client() -> Client {
         match Client::try_default().await {
             Ok(client) => client,
@@ -37,3 +64,20 @@ impl K8s {
         }
     }
 }
+
+/// List all resources matching the params page by page, so large clusters don't have to be
+/// listed at once
+pub async fn list_paged<K>(api: &Api<K>, mut params: ListParams) -> Result<Vec<K>, kube::Error>
+where
+    K: Clone + std::fmt::Debug + serde::de::DeserializeOwned,
+{
+    let mut objs = Vec::new();
+    loop {
+        let page = api.list(&params).await?;
+        objs.extend(page.items);
+        match page.metadata.continue_ {
+            Some(token) if !token.is_empty() => params = params.continue_token(&token),
+            _ => return Ok(objs),
+        }
+    }
+}
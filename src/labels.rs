@@ -1,9 +1,9 @@
 use std::sync::Arc;
 
-use k8s_openapi::api::core::v1::Secret;
+use crate::resource::GeneratedResource;
 
-pub fn managed_by_us(obj: &Arc<Secret>) -> bool {
-    let r = match &obj.metadata.labels {
+pub fn managed_by_us(obj: &Arc<impl GeneratedResource>) -> bool {
+    let r = match &obj.meta().labels {
         Some(l) => match l.get(&get_managed_label()) {
             Some(m) => m == &"true".to_string(),
             None => {
@@ -19,9 +19,34 @@ pub fn get_managed_label() -> String {
     "v1.secret.runo.rocks/managed".to_string()
 }
 
+pub fn get_secret_name_label() -> String {
+    "v1.secret.runo.rocks/secret-name".to_string()
+}
+
+pub fn get_field_id_label() -> String {
+    "v1.secret.runo.rocks/field-id".to_string()
+}
+
+pub fn get_replica_of_label() -> String {
+    "v1.secret.runo.rocks/replica-of".to_string()
+}
+
+pub fn get_replica_of_uid_label() -> String {
+    "v1.secret.runo.rocks/replica-of-uid".to_string()
+}
+
+/// Label values are limited to 63 characters and have to start and end alphanumeric
+pub fn to_label_value(value: &str) -> String {
+    let mut label_value: String = value.chars().take(63).collect();
+    while label_value.ends_with(|c: char| !c.is_ascii_alphanumeric()) {
+        label_value.pop();
+    }
+    label_value
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{get_managed_label, managed_by_us};
+    use super::{get_managed_label, managed_by_us, to_label_value};
     use k8s_openapi::api::core::v1::Secret;
     use rstest::*;
     use std::{collections::BTreeMap, sync::Arc};
@@ -71,4 +96,12 @@ mod tests {
     fn v1_unmanaged_secret_implicitly(unmanaged_secret_explicitly: Arc<Secret>) {
         assert!(!managed_by_us(&unmanaged_secret_explicitly));
     }
+
+    #[rstest]
+    #[case("example-secret", "example-secret")]
+    #[case(&"a".repeat(70), &"a".repeat(63))]
+    #[case(&format!("{}-b", "a".repeat(62)), &"a".repeat(62))]
+    fn v1_label_value(#[case] value: &str, #[case] expected: &str) {
+        assert_eq!(to_label_value(value), expected);
+    }
 }
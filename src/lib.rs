@@ -0,0 +1,26 @@
+pub mod annotations;
+pub mod cli;
+pub mod config;
+pub mod cron;
+pub mod errors;
+pub mod http;
+pub mod k8s;
+pub mod labels;
+pub mod limiter;
+pub mod lint;
+pub mod logging;
+pub mod namespaces;
+pub mod plugin;
+pub mod reconciler;
+pub mod render;
+pub mod replication;
+pub mod report;
+pub mod resource;
+pub mod rotate;
+pub mod schedule;
+pub mod secrets;
+pub mod settings;
+pub mod status;
+#[cfg(test)]
+mod test_utils;
+pub mod workloads;
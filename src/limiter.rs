@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::debug;
+
+/// Limits how many renewals run at the same time in the whole cluster and per namespace, so
+/// Secrets sharing a renewal schedule don't rotate all at once
+#[derive(Debug, Default)]
+pub struct RenewalLimiter {
+    cluster: Option<Arc<Semaphore>>,
+    per_namespace: Option<usize>,
+    namespaces: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+/// Held as long as a renewal is running
+pub struct RenewalPermit {
+    _namespace: Option<OwnedSemaphorePermit>,
+    _cluster: Option<OwnedSemaphorePermit>,
+}
+
+impl RenewalLimiter {
+    pub fn build(cluster: Option<u32>, per_namespace: Option<u32>) -> RenewalLimiter {
+        RenewalLimiter {
+            cluster: cluster.map(|limit| Arc::new(Semaphore::new(limit as usize))),
+            per_namespace: per_namespace.map(|limit| limit as usize),
+            namespaces: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn namespace_semaphore(&self, namespace: &str) -> Option<Arc<Semaphore>> {
+        let limit = self.per_namespace?;
+        let mut namespaces = self.namespaces.lock().unwrap();
+        Some(
+            namespaces
+                .entry(namespace.to_string())
+                .or_insert_with(|| Arc::new(Semaphore::new(limit)))
+                .clone(),
+        )
+    }
+
+    /// Wait until a renewal in the namespace is allowed. The namespace permit is acquired first,
+    /// so a renewal waiting for its namespace doesn't block renewals in other namespaces.
+    pub async fn acquire(&self, namespace: &str) -> RenewalPermit {
+        let namespace_semaphore = self.namespace_semaphore(namespace);
+        let namespace_permit = match namespace_semaphore {
+            Some(semaphore) => {
+                if semaphore.available_permits() == 0 {
+                    debug!("Waiting for a free renewal slot in {:?}", namespace);
+                }
+                semaphore.acquire_owned().await.ok()
+            }
+            None => None,
+        };
+        let cluster_permit = match &self.cluster {
+            Some(semaphore) => {
+                if semaphore.available_permits() == 0 {
+                    debug!("Waiting for a free renewal slot in the cluster");
+                }
+                semaphore.clone().acquire_owned().await.ok()
+            }
+            None => None,
+        };
+        RenewalPermit {
+            _namespace: namespace_permit,
+            _cluster: cluster_permit,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RenewalLimiter;
+    use std::time::Duration;
+    use tokio::time::timeout;
+
+    const WAIT: Duration = Duration::from_millis(50);
+
+    #[tokio::test]
+    async fn unlimited_renewals() {
+        let limiter = RenewalLimiter::default();
+        let _first = limiter.acquire("default").await;
+        assert!(timeout(WAIT, limiter.acquire("default")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn limit_renewals_per_namespace() {
+        let limiter = RenewalLimiter::build(None, Some(1));
+        let first = limiter.acquire("default").await;
+        assert!(timeout(WAIT, limiter.acquire("default")).await.is_err());
+        assert!(timeout(WAIT, limiter.acquire("other")).await.is_ok());
+        drop(first);
+        assert!(timeout(WAIT, limiter.acquire("default")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn limit_renewals_in_cluster() {
+        let limiter = RenewalLimiter::build(Some(2), None);
+        let _first = limiter.acquire("default").await;
+        let second = limiter.acquire("other").await;
+        assert!(timeout(WAIT, limiter.acquire("third")).await.is_err());
+        drop(second);
+        assert!(timeout(WAIT, limiter.acquire("third")).await.is_ok());
+    }
+}
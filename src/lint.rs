@@ -0,0 +1,509 @@
+use crate::annotations::{clone_from, generate, id_iter, referenced_profile, V1Annotation};
+use crate::labels;
+use crate::resource::GeneratedResource;
+use crate::settings::Settings;
+use crate::{schedule, secrets};
+use k8s_openapi::api::core::v1::Secret;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::ResourceExt;
+use serde::Deserialize;
+use serde_saphyr::Spanned;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Problem of a managed resource, optionally caused by a single annotation
+#[derive(Debug, PartialEq)]
+pub struct Finding {
+    pub(crate) annotation: Option<String>,
+    pub(crate) message: String,
+}
+
+impl Finding {
+    fn new(annotation: Option<String>, message: impl Into<String>) -> Finding {
+        Finding {
+            annotation,
+            message: message.into(),
+        }
+    }
+}
+
+/// Finding with the position in the manifest which caused it
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub(crate) file: String,
+    pub(crate) line: u64,
+    pub(crate) message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file, self.line, self.message)
+    }
+}
+
+#[derive(Deserialize)]
+struct Document {
+    kind: Option<Spanned<String>>,
+    #[serde(default)]
+    metadata: DocumentMetadata,
+}
+
+#[derive(Default, Deserialize)]
+struct DocumentMetadata {
+    name: Option<String>,
+    namespace: Option<String>,
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+    #[serde(default)]
+    annotations: BTreeMap<String, Spanned<String>>,
+}
+
+fn check_annotation<T>(
+    obj: &Arc<impl GeneratedResource>,
+    annotation: V1Annotation,
+    id: &str,
+    check: impl Fn(&str) -> Result<T, String>,
+) -> Option<Finding> {
+    let key = annotation.value(id);
+    let value = obj.annotations().get(&key)?;
+    check(value)
+        .err()
+        .map(|e| Finding::new(Some(key.clone()), format!("{}: {}", key, e)))
+}
+
+fn check_length(value: &str) -> Result<(), String> {
+    match value.parse::<i32>() {
+        Ok(length) if length > 0 && length <= 100 => Ok(()),
+        _ => Err(format!("length {:?} must be > 0 and <= 100", value)),
+    }
+}
+
+fn check_history(value: &str) -> Result<(), String> {
+    match value.parse::<usize>() {
+        Ok(history) if history <= 10 => Ok(()),
+        _ => Err(format!("history {:?} must be >= 0 and <= 10", value)),
+    }
+}
+
+fn check_charset(value: &str) -> Result<(), String> {
+    if value.is_empty() {
+        return Err("charset is empty".to_string());
+    }
+    if !value.is_ascii() {
+        return Err(format!("charset {:?} contains non-ASCII characters", value));
+    }
+    Ok(())
+}
+
+fn check_cron(value: &str) -> Result<(), String> {
+    schedule::parse_cron(value)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+fn check_timezone(value: &str) -> Result<(), String> {
+    schedule::parse_timezone(value)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+fn check_duration(value: &str) -> Result<(), String> {
+    schedule::parse_duration(value)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+type Check = fn(&str) -> Result<(), String>;
+
+/// Checks of the renewal settings, which are independent of each other
+fn renewal_checks() -> [(V1Annotation, Check); 5] {
+    [
+        (V1Annotation::RenewalCron, check_cron),
+        (V1Annotation::RenewalTimezone, check_timezone),
+        (V1Annotation::MaxAge, check_duration),
+        (V1Annotation::GracePeriod, check_duration),
+        (V1Annotation::RenewalJitter, check_duration),
+    ]
+}
+
+/// Renewal settings a field takes from its profile are checked like its own annotations and
+/// reported on its `profile` annotation
+fn check_profile(obj: &Arc<impl GeneratedResource>, id: &str, settings: &Settings) -> Vec<Finding> {
+    let profile = match referenced_profile(obj, id, settings) {
+        Some(profile) => profile,
+        None => return vec![],
+    };
+    let key = V1Annotation::Profile.value(id);
+    let name = obj.annotations().get(&key).cloned().unwrap_or_default();
+    renewal_checks()
+        .into_iter()
+        .filter(|(annotation, _)| !obj.annotations().contains_key(&annotation.value(id)))
+        .filter_map(|(annotation, check)| {
+            let value = profile.value(&annotation)?;
+            check(&value).err().map(|e| {
+                Finding::new(
+                    Some(key.clone()),
+                    format!("{}: {} of profile {:?}: {}", key, annotation.key(), name, e),
+                )
+            })
+        })
+        .collect()
+}
+
+fn check_clone_from(obj: &Arc<impl GeneratedResource>, id: &str) -> Result<(), String> {
+    let value = clone_from(obj, id).get_value();
+    if secrets::parse_clone_source(&value).is_some() {
+        return match value.split('/').any(|part| part.trim().is_empty()) {
+            true => Err(format!("clone source {:?} is incomplete", value)),
+            false => Ok(()),
+        };
+    }
+    if !generate(obj, &value).exists() {
+        return Err(format!("there is no field with id {:?} to clone", value));
+    }
+    if clone_from(obj, &value).exists() {
+        return Err(format!(
+            "field with id {:?} is cloned itself and can't be cloned",
+            value
+        ));
+    }
+    Ok(())
+}
+
+/// Run the checks of the reconciliation on the annotations of a managed resource, including the
+/// policies of the config file
+pub fn check(obj: &Arc<impl GeneratedResource>, settings: &Settings) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    if let Err(e) = secrets::validate_no_duplicate_keys(obj) {
+        findings.push(Finding::new(None, e.to_string()));
+    }
+    for id in id_iter(obj) {
+        let length = check_annotation(obj, V1Annotation::Length, &id, check_length);
+        let valid_length = length.is_none();
+        findings.extend(length);
+        findings.extend(check_annotation(
+            obj,
+            V1Annotation::History,
+            &id,
+            check_history,
+        ));
+        findings.extend(check_annotation(
+            obj,
+            V1Annotation::Charset,
+            &id,
+            check_charset,
+        ));
+        findings.extend(check_annotation(obj, V1Annotation::Pattern, &id, |p| {
+            secrets::validate_pattern(p).map_err(|e| e.to_string())?;
+            if !valid_length {
+                return Ok(());
+            }
+            match secrets::generate_random_string(obj, &id, settings) {
+                Ok(_) => Ok(()),
+                Err(_) => Err(format!("no value can be generated from pattern {:?}", p)),
+            }
+        }));
+        findings.extend(check_annotation(obj, V1Annotation::CloneFrom, &id, |_| {
+            check_clone_from(obj, &id)
+        }));
+        for (annotation, check) in renewal_checks() {
+            findings.extend(check_annotation(obj, annotation, &id, check));
+        }
+        findings.extend(check_profile(obj, &id, settings));
+    }
+    for violation in settings.violations(obj) {
+        let key = V1Annotation::Generate.value(&violation.id);
+        findings.push(Finding::new(
+            Some(key.clone()),
+            format!("{}: {}", key, violation.reason),
+        ));
+    }
+    findings
+}
+
+fn is_runo_annotation(key: &str) -> bool {
+    key.starts_with(&format!("{}-", V1Annotation::Generate.key()))
+}
+
+/// Lint all Secrets and ConfigMaps of a YAML or JSON file with one or more documents
+pub fn lint_str(file: &str, content: &str, settings: &Settings) -> Vec<Diagnostic> {
+    let documents = match serde_saphyr::from_multiple::<Document>(content) {
+        Ok(documents) => documents,
+        Err(e) => {
+            return vec![Diagnostic {
+                file: file.to_string(),
+                line: e.location().map(|l| l.line()).unwrap_or(1),
+                message: format!("can't parse manifest: {}", e),
+            }]
+        }
+    };
+    let mut diagnostics = Vec::new();
+    for document in documents {
+        let kind = match &document.kind {
+            Some(kind) if kind.value == "Secret" || kind.value == "ConfigMap" => kind,
+            _ => continue,
+        };
+        let metadata = document.metadata;
+        let obj = Arc::new(Secret {
+            metadata: ObjectMeta {
+                name: metadata.name,
+                namespace: metadata.namespace,
+                labels: Some(metadata.labels),
+                annotations: Some(
+                    metadata
+                        .annotations
+                        .iter()
+                        .map(|(k, v)| (k.clone(), v.value.clone()))
+                        .collect(),
+                ),
+                ..ObjectMeta::default()
+            },
+            ..Secret::default()
+        });
+        let findings = match labels::managed_by_us(&obj) {
+            true => check(&obj, settings),
+            false if obj.annotations().keys().any(|k| is_runo_annotation(k)) => vec![Finding::new(
+                None,
+                format!(
+                    "fields are annotated, but the label {:?} isn't set to \"true\"",
+                    labels::get_managed_label()
+                ),
+            )],
+            false => continue,
+        };
+        for finding in findings {
+            let line = finding
+                .annotation
+                .and_then(|a| metadata.annotations.get(&a))
+                .map(|a| a.referenced.line())
+                .unwrap_or(kind.referenced.line());
+            diagnostics.push(Diagnostic {
+                file: file.to_string(),
+                line,
+                message: format!("{} {}: {}", kind.value, obj.name_any(), finding.message),
+            });
+        }
+    }
+    diagnostics
+}
+
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries = std::fs::read_dir(path)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<PathBuf>>>()?;
+    entries.sort();
+    for entry in entries {
+        let is_manifest = entry
+            .extension()
+            .is_some_and(|e| e == "yaml" || e == "yml" || e == "json");
+        if entry.is_dir() || is_manifest {
+            collect_files(&entry, files)?;
+        }
+    }
+    Ok(())
+}
+
+/// Lint files, directories with manifests or stdin for `-`
+pub fn lint_paths(paths: &[String], settings: &Settings) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for path in paths {
+        if path == "-" {
+            let mut content = String::new();
+            match std::io::stdin().read_to_string(&mut content) {
+                Ok(_) => diagnostics.extend(lint_str("<stdin>", &content, settings)),
+                Err(e) => diagnostics.push(unreadable("<stdin>", e)),
+            }
+            continue;
+        }
+        let mut files = Vec::new();
+        if let Err(e) = collect_files(Path::new(path), &mut files) {
+            diagnostics.push(unreadable(path, e));
+        }
+        for file in files {
+            let name = file.display().to_string();
+            match std::fs::read_to_string(&file) {
+                Ok(content) => diagnostics.extend(lint_str(&name, &content, settings)),
+                Err(e) => diagnostics.push(unreadable(&name, e)),
+            }
+        }
+    }
+    diagnostics
+}
+
+fn unreadable(file: &str, e: std::io::Error) -> Diagnostic {
+    Diagnostic {
+        file: file.to_string(),
+        line: 0,
+        message: format!("can't read: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, lint_str, Finding};
+    use crate::settings::Settings;
+    use crate::test_utils::build_secret;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(vec![("v1.secret.runo.rocks/length-0", "32")])]
+    #[case(vec![("v1.secret.runo.rocks/charset-0", "abc")])]
+    #[case(vec![("v1.secret.runo.rocks/pattern-0", "[a-z]")])]
+    #[case(vec![("v1.secret.runo.rocks/renewal-cron-0", "0 0 * * *")])]
+    #[case(vec![("v1.secret.runo.rocks/max-age-0", "30d")])]
+    #[case(vec![("v1.secret.runo.rocks/generate-1", "copy"),
+                ("v1.secret.runo.rocks/clone-from-1", "0")])]
+    #[case(vec![("v1.secret.runo.rocks/generate-1", "copy"),
+                ("v1.secret.runo.rocks/clone-from-1", "other/password")])]
+    fn check_valid_annotations(#[case] mut annotations: Vec<(&str, &str)>) {
+        annotations.push(("v1.secret.runo.rocks/generate-0", "password"));
+        assert_eq!(
+            check(&build_secret(annotations), &Settings::default()),
+            vec![]
+        );
+    }
+
+    #[rstest]
+    #[case(("v1.secret.runo.rocks/length-0", "0"))]
+    #[case(("v1.secret.runo.rocks/length-0", "101"))]
+    #[case(("v1.secret.runo.rocks/length-0", "abc"))]
+    #[case(("v1.secret.runo.rocks/history-0", "11"))]
+    #[case(("v1.secret.runo.rocks/charset-0", ""))]
+    #[case(("v1.secret.runo.rocks/charset-0", "äöü"))]
+    #[case(("v1.secret.runo.rocks/pattern-0", "[a-z]+"))]
+    #[case(("v1.secret.runo.rocks/pattern-0", "[a-z"))]
+    #[case(("v1.secret.runo.rocks/renewal-cron-0", "every day"))]
+    #[case(("v1.secret.runo.rocks/max-age-0", "one month"))]
+    #[case(("v1.secret.runo.rocks/renewal-timezone-0", "Mars/Olympus"))]
+    #[case(("v1.secret.runo.rocks/renewal-jitter-0", "a while"))]
+    #[case(("v1.secret.runo.rocks/clone-from-0", "7"))]
+    #[case(("v1.secret.runo.rocks/clone-from-0", "other/"))]
+    fn check_invalid_annotation(#[case] annotation: (&str, &str)) {
+        let secret = build_secret(vec![
+            ("v1.secret.runo.rocks/generate-0", "password"),
+            annotation,
+        ]);
+        let findings = check(&secret, &Settings::default());
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].annotation, Some(annotation.0.to_string()));
+    }
+
+    #[rstest]
+    fn check_policies_of_settings() {
+        let settings =
+            crate::settings::parse("maxLength: 32\nallowedGenerators: [pattern]\n").unwrap();
+        let secret = build_secret(vec![
+            ("v1.secret.runo.rocks/generate-0", "password"),
+            ("v1.secret.runo.rocks/length-0", "40"),
+            ("v1.secret.runo.rocks/generate-1", "pin"),
+            ("v1.secret.runo.rocks/charset-1", "0123456789"),
+        ]);
+        let annotations = check(&secret, &settings)
+            .into_iter()
+            .map(|f| f.annotation.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            annotations,
+            vec![
+                "v1.secret.runo.rocks/generate-0",
+                "v1.secret.runo.rocks/generate-1"
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case(vec![], vec!["v1.secret.runo.rocks/profile-0"])]
+    #[case(vec![("v1.secret.runo.rocks/renewal-cron-0", "0 0 * * *")], vec![])]
+    #[case(vec![("v1.secret.runo.rocks/renewal-cron-0", "every day")],
+           vec!["v1.secret.runo.rocks/renewal-cron-0"])]
+    fn check_renewal_of_profile(
+        #[case] mut annotations: Vec<(&str, &str)>,
+        #[case] expected: Vec<&str>,
+    ) {
+        let mut settings = crate::settings::test_settings();
+        let pin = settings.profiles.get_mut("pin").unwrap();
+        pin.renewal_cron = Some("every day".to_string());
+        pin.renewal_timezone = Some("Mars/Olympus".to_string());
+        // The timezone of the profile is overridden by the field
+        annotations.extend([
+            ("v1.secret.runo.rocks/generate-0", "pin"),
+            ("v1.secret.runo.rocks/profile-0", "pin"),
+            ("v1.secret.runo.rocks/renewal-timezone-0", "Europe/Berlin"),
+        ]);
+        let annotations = check(&build_secret(annotations), &settings)
+            .into_iter()
+            .map(|f| f.annotation.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(annotations, expected);
+    }
+
+    #[rstest]
+    fn check_duplicate_field_names() {
+        let secret = build_secret(vec![
+            ("v1.secret.runo.rocks/generate-0", "password"),
+            ("v1.secret.runo.rocks/generate-1", "password"),
+        ]);
+        assert!(matches!(
+            check(&secret, &Settings::default()).as_slice(),
+            [Finding {
+                annotation: None,
+                ..
+            }]
+        ));
+    }
+
+    #[rstest]
+    fn lint_reports_lines_of_annotations() {
+        let manifests = "\
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: unrelated
+---
+apiVersion: v1
+kind: Secret
+metadata:
+  name: example
+  labels:
+    v1.secret.runo.rocks/managed: \"true\"
+  annotations:
+    v1.secret.runo.rocks/generate-0: password
+    v1.secret.runo.rocks/length-0: \"500\"
+";
+        let diagnostics = lint_str("secret.yaml", manifests, &Settings::default());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, 14);
+        assert!(diagnostics[0]
+            .to_string()
+            .starts_with("secret.yaml:14: Secret example: v1.secret.runo.rocks/length-0"));
+    }
+
+    #[rstest]
+    fn lint_reports_unmanaged_secrets_with_annotations() {
+        let manifest = r#"{"apiVersion": "v1", "kind": "Secret", "metadata": {"name": "example",
+            "annotations": {"v1.secret.runo.rocks/generate-0": "password"}}}"#;
+        let diagnostics = lint_str("secret.json", manifest, &Settings::default());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, 1);
+    }
+
+    #[rstest]
+    fn lint_reports_invalid_manifests() {
+        let diagnostics = lint_str(
+            "broken.yaml",
+            "kind: Secret\nmetadata: [\n",
+            &Settings::default(),
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].file, "broken.yaml");
+        assert!(diagnostics[0].message.starts_with("can't parse manifest: "));
+        assert!(diagnostics[0].message.len() > "can't parse manifest: ".len());
+    }
+}
@@ -1,38 +1,12 @@
-mod annotations;
-mod config;
-mod cron;
-mod errors;
-mod http;
-mod k8s;
-mod labels;
-mod logging;
-mod reconciler;
-mod secrets;
-
-use crate::k8s::K8s;
 use anyhow::anyhow;
 use clap::Parser;
-use config::RunoConfig;
-use errors::LogLevelMissing;
+use runo::cli::{Command, MainArgs};
+use runo::errors::LogLevelMissing;
+use runo::{config, http, lint, logging, reconciler, render, rotate, status};
 use tracing::info;
 use tracing_subscriber::util::SubscriberInitExt;
 
-#[derive(Parser)]
-#[clap(author, version, about, long_about = None)]
-struct MainArgs {
-    #[clap(long, default_value_t = 8080)]
-    http_port: u16,
-    #[clap(long, default_value_t = false)]
-    dry_run: bool,
-    #[clap(long, default_value_t = String::from("reconciliation"))]
-    mode: String,
-    #[clap(long, default_value_t = 300)]
-    requeue_duration: u64,
-}
-
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    let args = MainArgs::parse();
+fn init_logging() {
     let subscriber = match logging::get_subscriber(true) {
         Ok(s) => {
             info!("Logging initialized..");
@@ -41,12 +15,23 @@ async fn main() -> anyhow::Result<()> {
         Err(LogLevelMissing) => panic!("RUST_LOG is not set properly!"),
     };
     subscriber.init();
-    let k8s = K8s::build(args.dry_run);
-    let config = RunoConfig::build(k8s, args.requeue_duration);
-    match args.mode.as_str() {
-        "reconciliation" => {
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = MainArgs::parse();
+    if args.uses_mode() {
+        eprintln!("--mode is deprecated, please use `runo run` or `runo once` instead");
+    }
+    match args.command() {
+        Command::Run(run) => {
+            init_logging();
             info!("Running runo in reconciliation mode.");
-            let http_server_result = http::run_http_server(args.http_port);
+            let config = run
+                .controller
+                .build_config(run.requeue_duration)
+                .map_err(|e| anyhow!("{}", e))?;
+            let http_server_result = http::run_http_server(run.http_port);
             let reconciler = reconciler::run_with_reconciliation(config);
             match http_server_result {
                 Ok(http_server) => {
@@ -56,11 +41,71 @@ async fn main() -> anyhow::Result<()> {
                 Err(_) => Err(anyhow!("Can't bind HTTP server to port!")),
             }
         }
-        "one-shot" => {
+        Command::Once(once) => {
+            init_logging();
             info!("Running runo in one-shot mode.");
-            reconciler::run_one_shot(config).await;
+            let config = once
+                .controller
+                .build_config(config::DEFAULT_REQUEUE_DURATION)
+                .map_err(|e| anyhow!("{}", e))?;
+            let summary = reconciler::run_one_shot(config, once.concurrency as usize).await;
+            println!("{}", summary);
+            match summary.failures() {
+                0 => Ok(()),
+                failures => Err(anyhow!("{} failures in one-shot mode", failures)),
+            }
+        }
+        Command::Lint { paths, settings } => {
+            let settings = settings.load().map_err(|e| anyhow!("{}", e))?;
+            let diagnostics = lint::lint_paths(&paths, &settings);
+            for diagnostic in &diagnostics {
+                println!("{}", diagnostic);
+            }
+            match diagnostics.len() {
+                0 => Ok(()),
+                problems => Err(anyhow!("{} problems found", problems)),
+            }
+        }
+        Command::Generate { file, settings } => {
+            let settings = settings.load().map_err(|e| anyhow!("{}", e))?;
+            let content = match file.as_str() {
+                "-" => std::io::read_to_string(std::io::stdin())?,
+                file => std::fs::read_to_string(file)?,
+            };
+            print!(
+                "{}",
+                render::render(&content, &settings).map_err(|e| anyhow!("{}", e))?
+            );
+            Ok(())
+        }
+        Command::Rotate {
+            target,
+            id,
+            all,
+            timeout,
+        } => {
+            let rotated = rotate::rotate(&target, id.as_deref(), all, timeout)
+                .await
+                .map_err(|e| anyhow!("{}", e))?;
+            for field in rotated {
+                println!("{}", field);
+            }
+            Ok(())
+        }
+        Command::Status {
+            namespace,
+            settings,
+        } => {
+            let settings = settings.load().map_err(|e| anyhow!("{}", e))?;
+            let statuses = status::status(namespace.as_deref(), &settings)
+                .await
+                .map_err(|e| anyhow!("Can't list managed Secrets: {}", e))?;
+            println!("{}", status::format_table(&statuses));
+            Ok(())
+        }
+        Command::Version => {
+            println!("runo {}", env!("CARGO_PKG_VERSION"));
             Ok(())
         }
-        _ => Err(anyhow!("Mode is not supported!: {:?}", args.mode)),
     }
 }
@@ -0,0 +1,122 @@
+use crate::config::{NamespaceScope, RunoConfig};
+use futures::StreamExt;
+use k8s_openapi::api::core::v1::Namespace;
+use k8s_openapi::NamespaceResourceScope;
+use kube::runtime::reflector::{self, ObjectRef, Store};
+use kube::runtime::{watcher, WatchStreamExt};
+use kube::{Api, Client, Resource};
+use tracing::{debug, error, info};
+
+/// APIs to watch or list resources in. Listed namespaces get an API each, so the controller
+/// works with namespaced Roles. Otherwise, resources of all namespaces are watched.
+pub fn apis<K>(client: &Client, scope: &NamespaceScope) -> Vec<Api<K>>
+where
+    K: Resource<Scope = NamespaceResourceScope, DynamicType = ()>,
+{
+    if scope.namespaces.is_empty() {
+        return vec![Api::all(client.clone())];
+    }
+    scope
+        .namespaces
+        .iter()
+        .filter(|n| !scope.denied.contains(n))
+        .map(|n| Api::namespaced(client.clone(), n))
+        .collect()
+}
+
+/// Keep track of the namespaces matching the label selector. The store is ready as soon as the
+/// namespaces were listed once.
+pub async fn watch_selected(client: &Client, selector: &str) -> Store<Namespace> {
+    info!("Watching namespaces matching {:?}", selector);
+    let (reader, writer) = reflector::store();
+    let namespaces: Api<Namespace> = Api::all(client.clone());
+    let stream = watcher(namespaces, watcher::Config::default().labels(selector))
+        .default_backoff()
+        .reflect(writer)
+        .touched_objects()
+        .for_each(|n| {
+            if let Err(e) = n {
+                error!("Can't watch namespaces: {:?}", e)
+            }
+            futures::future::ready(())
+        });
+    tokio::spawn(stream);
+    if reader.wait_until_ready().await.is_err() {
+        error!("Namespaces matching {:?} couldn't be listed", selector)
+    }
+    reader
+}
+
+/// Check if the controller is responsible for a resource in the namespace
+pub fn in_scope(config: &RunoConfig, namespace: &str) -> bool {
+    let scope = config.namespace_scope();
+    let in_scope = if scope.denied.iter().any(|n| n == namespace) {
+        false
+    } else if !scope.namespaces.is_empty() {
+        scope.namespaces.iter().any(|n| n == namespace)
+    } else {
+        match &config.selected_namespaces {
+            Some(selected) => selected.get(&ObjectRef::new(namespace)).is_some(),
+            None => true,
+        }
+    };
+    if !in_scope {
+        debug!("Namespace {:?} is out of scope", namespace)
+    }
+    in_scope
+}
+
+#[cfg(test)]
+mod tests {
+    use super::in_scope;
+    use crate::config::{NamespaceScope, RunoConfig};
+    use crate::k8s::K8s;
+    use k8s_openapi::api::core::v1::Namespace;
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+    use kube::runtime::reflector;
+    use kube::runtime::watcher::Event;
+    use rstest::rstest;
+
+    fn build_config(namespaces: Vec<&str>, denied: Vec<&str>) -> RunoConfig {
+        RunoConfig::build(K8s { dry_run: false }, 10).with_namespace_scope(NamespaceScope {
+            namespaces: namespaces.iter().map(|n| n.to_string()).collect(),
+            selector: None,
+            denied: denied.iter().map(|n| n.to_string()).collect(),
+        })
+    }
+
+    #[rstest]
+    #[case(vec![], vec![], "default", true)]
+    #[case(vec![], vec!["kube-system"], "kube-system", false)]
+    #[case(vec![], vec!["kube-system"], "default", true)]
+    #[case(vec!["apps", "monitoring"], vec![], "apps", true)]
+    #[case(vec!["apps", "monitoring"], vec![], "default", false)]
+    #[case(vec!["apps", "monitoring"], vec!["apps"], "apps", false)]
+    fn namespace_in_scope(
+        #[case] namespaces: Vec<&str>,
+        #[case] denied: Vec<&str>,
+        #[case] namespace: &str,
+        #[case] expected: bool,
+    ) {
+        assert_eq!(
+            in_scope(&build_config(namespaces, denied), namespace),
+            expected
+        );
+    }
+
+    #[rstest]
+    fn selected_namespace_in_scope() {
+        let (reader, mut writer) = reflector::store();
+        writer.apply_watcher_event(&Event::Apply(Namespace {
+            metadata: ObjectMeta {
+                name: Some("apps".to_string()),
+                ..ObjectMeta::default()
+            },
+            ..Namespace::default()
+        }));
+        let config = build_config(vec![], vec!["denied"]).with_selected_namespaces(reader);
+        assert!(in_scope(&config, "apps"));
+        assert!(!in_scope(&config, "default"));
+        assert!(!in_scope(&config, "denied"));
+    }
+}
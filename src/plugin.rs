@@ -0,0 +1,403 @@
+use crate::annotations::{generate, id_iter, is_field_annotation, is_paused, V1Annotation};
+use crate::cli::{PluginArgs, PluginCommand};
+use crate::errors::PluginFailed;
+use crate::k8s::K8s;
+use crate::labels;
+use crate::rotate;
+use crate::schedule;
+use crate::secrets;
+use crate::status;
+use chrono::Utc;
+use k8s_openapi::api::core::v1::Secret;
+use kube::api::Patch;
+use kube::{Api, ResourceExt};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Annotations to set, `None` removes the annotation
+type AnnotationChanges = BTreeMap<String, Option<String>>;
+
+fn failed(reason: impl Into<String>) -> PluginFailed {
+    PluginFailed {
+        reason: reason.into(),
+    }
+}
+
+/// Settings of a new field, unset ones fall back to the defaults of the controller
+#[derive(Default)]
+pub struct FieldSettings {
+    pub length: Option<u32>,
+    pub charset: Option<String>,
+    pub pattern: Option<String>,
+    pub clone_from: Option<String>,
+    pub renewal_cron: Option<String>,
+    pub profile: Option<String>,
+}
+
+/// Id of the field with the given key
+pub fn find_id(obj: &Arc<Secret>, field: &str) -> Result<String, PluginFailed> {
+    id_iter(obj)
+        .into_iter()
+        .find(|id| generate(obj, id).get_value() == field)
+        .ok_or_else(|| failed(format!("{} has no field {:?}", obj.name_any(), field)))
+}
+
+/// Lowest numeric id which isn't used by a field yet
+pub fn next_free_id(obj: &Arc<Secret>) -> String {
+    let ids = id_iter(obj);
+    (0..)
+        .map(|id: u32| id.to_string())
+        .find(|id| !ids.contains(id))
+        .unwrap()
+}
+
+fn is_valid_key(field: &str) -> bool {
+    !field.is_empty()
+        && field
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+/// Annotations of a new field with the next free id
+pub fn add_field(
+    obj: &Arc<Secret>,
+    field: &str,
+    settings: FieldSettings,
+) -> Result<(String, AnnotationChanges), PluginFailed> {
+    if !is_valid_key(field) {
+        return Err(failed(format!(
+            "{:?} isn't a valid key, only alphanumeric characters, '-', '_' and '.' are allowed",
+            field
+        )));
+    }
+    if find_id(obj, field).is_ok() {
+        return Err(failed(format!(
+            "{} already has a field {:?}",
+            obj.name_any(),
+            field
+        )));
+    }
+    if let Some(pattern) = &settings.pattern {
+        secrets::validate_pattern(pattern).map_err(|e| failed(e.to_string()))?;
+    }
+    if settings.charset.as_ref().is_some_and(|c| c.is_empty()) {
+        return Err(failed("the charset is empty"));
+    }
+    if let Some(cron) = &settings.renewal_cron {
+        schedule::parse_cron(cron).map_err(|e| failed(e.to_string()))?;
+    }
+    let id = next_free_id(obj);
+    let annotations = [
+        (V1Annotation::Generate, Some(field.to_string())),
+        (V1Annotation::Length, settings.length.map(|l| l.to_string())),
+        (V1Annotation::Charset, settings.charset),
+        (V1Annotation::Pattern, settings.pattern),
+        (V1Annotation::CloneFrom, settings.clone_from),
+        (V1Annotation::RenewalCron, settings.renewal_cron),
+        (V1Annotation::Profile, settings.profile),
+    ]
+    .into_iter()
+    .filter_map(|(annotation, value)| value.map(|v| (annotation.value(&id), Some(v))))
+    .collect();
+    Ok((id, annotations))
+}
+
+/// Removal of every annotation of a field
+pub fn remove_field(obj: &Arc<Secret>, id: &str) -> AnnotationChanges {
+    obj.annotations()
+        .keys()
+        .filter(|key| is_field_annotation(key, id))
+        .map(|key| (key.clone(), None))
+        .collect()
+}
+
+/// Pause or resume a field
+pub fn set_paused(
+    obj: &Arc<Secret>,
+    id: &str,
+    paused: bool,
+) -> Result<AnnotationChanges, PluginFailed> {
+    if is_paused(obj, id) == paused {
+        return Err(failed(format!(
+            "field {:?} is {} paused",
+            generate(obj, id).get_value(),
+            if paused { "already" } else { "not" }
+        )));
+    }
+    let value = paused.then(|| "true".to_string());
+    Ok(BTreeMap::from([(V1Annotation::Pause.value(id), value)]))
+}
+
+/// Merge patch with the changed annotations. The resource version makes the patch fail if the
+/// Secret changed in the meantime, so ids can't be taken twice.
+fn build_patch(
+    obj: &Arc<Secret>,
+    annotations: AnnotationChanges,
+    managed: bool,
+) -> Patch<serde_json::Value> {
+    let mut metadata = json!({
+        "resourceVersion": obj.resource_version(),
+        "annotations": annotations,
+    });
+    if managed {
+        metadata["labels"] = json!({ labels::get_managed_label(): "true" });
+    }
+    Patch::Merge(json!({ "metadata": metadata }))
+}
+
+async fn apply(
+    secrets: &Api<Secret>,
+    obj: &Arc<Secret>,
+    annotations: AnnotationChanges,
+    managed: bool,
+) -> Result<(), PluginFailed> {
+    secrets
+        .patch(
+            &obj.name_any(),
+            &K8s::build(false).get_merge_patch_params(),
+            &build_patch(obj, annotations, managed),
+        )
+        .await
+        .map(|_| ())
+        .map_err(|e| failed(format!("can't update {}: {}", obj.name_any(), e)))
+}
+
+/// Run a command of the plugin and return the lines to print
+pub async fn run(args: PluginArgs) -> Result<Vec<String>, PluginFailed> {
+    let client = K8s::get_client().await;
+    let namespace = args
+        .namespace
+        .unwrap_or_else(|| client.default_namespace().to_string());
+    let secrets: Api<Secret> = Api::namespaced(client, &namespace);
+    let get = |name: String| {
+        let secrets = secrets.clone();
+        async move {
+            secrets
+                .get(&name)
+                .await
+                .map(Arc::new)
+                .map_err(|e| failed(format!("can't read {}: {}", name, e)))
+        }
+    };
+    match args.command {
+        PluginCommand::AddField {
+            secret,
+            field,
+            length,
+            charset,
+            pattern,
+            clone_from,
+            renewal_cron,
+            profile,
+        } => {
+            let obj = get(secret).await?;
+            let settings = FieldSettings {
+                length,
+                charset,
+                pattern,
+                clone_from,
+                renewal_cron,
+                profile,
+            };
+            let (id, annotations) = add_field(&obj, &field, settings)?;
+            apply(&secrets, &obj, annotations, true).await?;
+            Ok(vec![format!("Added field {} with id {}", field, id)])
+        }
+        PluginCommand::RemoveField { secret, field } => {
+            let obj = get(secret).await?;
+            let id = find_id(&obj, &field)?;
+            apply(&secrets, &obj, remove_field(&obj, &id), false).await?;
+            Ok(vec![format!("Removed field {} with id {}", field, id)])
+        }
+        PluginCommand::Pause { secret, field } => {
+            let obj = get(secret).await?;
+            let id = find_id(&obj, &field)?;
+            apply(&secrets, &obj, set_paused(&obj, &id, true)?, false).await?;
+            Ok(vec![format!("Paused field {} with id {}", field, id)])
+        }
+        PluginCommand::Resume { secret, field } => {
+            let obj = get(secret).await?;
+            let id = find_id(&obj, &field)?;
+            apply(&secrets, &obj, set_paused(&obj, &id, false)?, false).await?;
+            Ok(vec![format!("Resumed field {} with id {}", field, id)])
+        }
+        PluginCommand::Rotate {
+            secret,
+            field,
+            all,
+            timeout,
+        } => {
+            let id = match field {
+                Some(field) => Some(find_id(&get(secret.clone()).await?, &field)?),
+                None => None,
+            };
+            rotate::rotate(
+                &format!("{}/{}", namespace, secret),
+                id.as_deref(),
+                all,
+                timeout,
+            )
+            .await
+            .map_err(|e| failed(e.reason))
+        }
+        PluginCommand::Describe { secret, settings } => {
+            let settings = settings.load().map_err(|e| failed(e.to_string()))?;
+            let obj = get(secret).await?;
+            let statuses = status::field_statuses(&obj, &Utc::now(), &settings);
+            Ok(vec![
+                format!("Managed: {}", labels::managed_by_us(&obj)),
+                status::format_table(&statuses),
+            ])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        add_field, build_patch, find_id, next_free_id, remove_field, set_paused, FieldSettings,
+    };
+    use crate::test_utils::build_secret;
+    use k8s_openapi::api::core::v1::Secret;
+    use kube::api::Patch;
+    use rstest::rstest;
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    fn build_fields() -> Arc<Secret> {
+        build_secret(vec![
+            ("v1.secret.runo.rocks/generate-0", "username"),
+            ("v1.secret.runo.rocks/length-0", "8"),
+            ("v1.secret.runo.rocks/generate-2", "password"),
+            ("v1.secret.runo.rocks/generated-at-2", "1767225600"),
+            ("v1.secret.runo.rocks/pause-2", "true"),
+            ("v1.secret.runo.rocks/rollout-restart", "true"),
+        ])
+    }
+
+    #[rstest]
+    #[case(vec![], "0")]
+    #[case(vec![("v1.secret.runo.rocks/generate-0", "username")], "1")]
+    #[case(vec![("v1.secret.runo.rocks/generate-1", "username")], "0")]
+    #[case(vec![
+        ("v1.secret.runo.rocks/generate-0", "username"),
+        ("v1.secret.runo.rocks/generate-1", "password"),
+        ("v1.secret.runo.rocks/generate-custom", "token"),
+    ], "2")]
+    fn test_next_free_id(#[case] annotations: Vec<(&str, &str)>, #[case] expected: &str) {
+        assert_eq!(next_free_id(&build_secret(annotations)), expected);
+    }
+
+    #[rstest]
+    #[case("username", Some("0"))]
+    #[case("password", Some("2"))]
+    #[case("token", None)]
+    fn test_find_id(#[case] field: &str, #[case] expected: Option<&str>) {
+        assert_eq!(find_id(&build_fields(), field).ok().as_deref(), expected);
+    }
+
+    #[rstest]
+    fn test_add_field() {
+        let settings = FieldSettings {
+            length: Some(16),
+            renewal_cron: Some("0 3 * * 0".to_string()),
+            profile: Some("api-token".to_string()),
+            ..FieldSettings::default()
+        };
+        let (id, annotations) = add_field(&build_fields(), "token", settings).unwrap();
+        assert_eq!(id, "1");
+        assert_eq!(
+            annotations,
+            BTreeMap::from([
+                (
+                    "v1.secret.runo.rocks/generate-1".to_string(),
+                    Some("token".to_string())
+                ),
+                (
+                    "v1.secret.runo.rocks/length-1".to_string(),
+                    Some("16".to_string())
+                ),
+                (
+                    "v1.secret.runo.rocks/renewal-cron-1".to_string(),
+                    Some("0 3 * * 0".to_string())
+                ),
+                (
+                    "v1.secret.runo.rocks/profile-1".to_string(),
+                    Some("api-token".to_string())
+                ),
+            ])
+        );
+    }
+
+    #[rstest]
+    #[case("username", FieldSettings::default())]
+    #[case("my key", FieldSettings::default())]
+    #[case("token", FieldSettings { pattern: Some("[a-z]+".to_string()), ..FieldSettings::default() })]
+    #[case("token", FieldSettings { charset: Some("".to_string()), ..FieldSettings::default() })]
+    #[case("token", FieldSettings { renewal_cron: Some("never".to_string()), ..FieldSettings::default() })]
+    fn test_add_field_invalid(#[case] field: &str, #[case] settings: FieldSettings) {
+        assert!(add_field(&build_fields(), field, settings).is_err());
+    }
+
+    #[rstest]
+    fn test_remove_field() {
+        assert_eq!(
+            remove_field(&build_fields(), "2"),
+            BTreeMap::from([
+                ("v1.secret.runo.rocks/generate-2".to_string(), None),
+                ("v1.secret.runo.rocks/generated-at-2".to_string(), None),
+                ("v1.secret.runo.rocks/pause-2".to_string(), None),
+            ])
+        );
+    }
+
+    #[rstest]
+    #[case("0", true, Some(Some("true")))]
+    #[case("0", false, None)]
+    #[case("2", true, None)]
+    #[case("2", false, Some(None))]
+    fn test_set_paused(
+        #[case] id: &str,
+        #[case] paused: bool,
+        #[case] expected: Option<Option<&str>>,
+    ) {
+        let changes = set_paused(&build_fields(), id, paused).ok();
+        assert_eq!(
+            changes.map(|c| c
+                .get(&format!("v1.secret.runo.rocks/pause-{}", id))
+                .cloned()
+                .unwrap()),
+            expected.map(|v| v.map(String::from))
+        );
+    }
+
+    #[rstest]
+    fn test_build_patch() {
+        let annotations = BTreeMap::from([
+            (
+                "v1.secret.runo.rocks/generate-1".to_string(),
+                Some("token".to_string()),
+            ),
+            ("v1.secret.runo.rocks/pause-1".to_string(), None),
+        ]);
+        match build_patch(&build_fields(), annotations, true) {
+            Patch::Merge(patch) => assert_eq!(
+                patch,
+                serde_json::json!({
+                    "metadata": {
+                        "resourceVersion": "42",
+                        "annotations": {
+                            "v1.secret.runo.rocks/generate-1": "token",
+                            "v1.secret.runo.rocks/pause-1": null
+                        },
+                        "labels": {
+                            "v1.secret.runo.rocks/managed": "true"
+                        }
+                    }
+                })
+            ),
+            _ => panic!("Expected merge patch"),
+        }
+    }
+}
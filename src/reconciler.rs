@@ -1,6 +1,13 @@
-use crate::config::RunoConfig;
-use crate::{cron, labels, secrets};
-use k8s_openapi::api::core::v1::Secret;
+use crate::config::{RenewalMode, RunoConfig};
+use crate::errors::SecretUpdateError;
+use crate::limiter::RenewalPermit;
+use crate::report::{Outcome, Summary};
+use crate::resource::GeneratedResource;
+use crate::{
+    annotations, cron, labels, namespaces, replication, schedule, secrets, settings, workloads,
+};
+use chrono::Utc;
+use k8s_openapi::api::core::v1::{ConfigMap, Secret};
 use kube::runtime::controller::Action;
 use kube::runtime::watcher::Config;
 use kube::runtime::Controller;
@@ -8,7 +15,8 @@ use kube::{Api, ResourceExt};
 use std::sync::Arc;
 use std::time::Duration;
 
-use crate::k8s::K8s;
+use crate::k8s::{self, K8s};
+use futures::future::join_all;
 use futures::StreamExt;
 use kube::api::ListParams;
 use tracing::{error, info};
@@ -17,52 +25,332 @@ use tracing::{error, info};
 pub enum Error {}
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
+pub(crate) const ONE_SHOT_PAGE_SIZE: u32 = 500;
+const MAX_UPDATE_ATTEMPTS: u32 = 3;
+
 pub(crate) async fn reconcile(obj: Arc<Secret>, config: Arc<RunoConfig>) -> Result<Action> {
     info!("reconcile request: {}", obj.name_any());
+    if obj.metadata.deletion_timestamp.is_some() {
+        // Replicas which can't be deleted now are left to the sweep
+        let _ = replication::cleanup(&obj, &config).await;
+        return Ok(Action::await_change());
+    }
+    if !namespaces::in_scope(&config, &obj.namespace().unwrap_or_default()) {
+        return Ok(Action::await_change());
+    }
     if labels::managed_by_us(&obj) {
-        let maybe_secret = secrets::update(&obj, &config.k8s).await;
-        match maybe_secret {
-            Ok(_) => info!("Secret updated successfully!"),
-            Err(_) => error!("Secret update failed!"),
+        update_secret(&obj, &config).await;
+    }
+    Ok(Action::requeue(requeue_after(
+        &obj,
+        &config,
+        config.renewal_mode,
+    )))
+}
+
+/// Update the fields of a managed Secret, restart its workloads and keep its replicas and
+/// renewal CronJobs in sync
+async fn update_secret(obj: &Arc<Secret>, config: &RunoConfig) -> Outcome {
+    // The renewal permit is held until workloads are restarted and replicas are updated
+    let (obj, mut outcome, maybe_secret, _permit) =
+        update_latest(obj, config, config.renewal_mode).await;
+    let data = match maybe_secret {
+        Ok(updated_secret) => {
+            info!("Secret updated successfully!");
+            if annotations::rollout_restart(&obj) && updated_secret.data != obj.data {
+                outcome.follow_up(workloads::restart(&obj, &config.k8s).await)
+            }
+            updated_secret.data
+        }
+        Err(_) => {
+            error!("Secret update failed!");
+            obj.data.clone()
+        }
+    };
+    outcome.follow_up(replication::update(&obj, data, config).await);
+    outcome.follow_up(match config.renewal_mode {
+        RenewalMode::CronJob => {
+            cron::update(
+                &obj,
+                &config.k8s,
+                &config.cronjob_template(),
+                &config.settings(),
+            )
+            .await
         }
-        cron::update(&obj, &config.k8s).await
+        RenewalMode::Native => cron::cleanup(&obj, &config.k8s, &config.cleaned_up_cronjobs).await,
+    });
+    outcome
+}
+
+/// A conflict means the resource was changed after it was read, so the update is retried with its
+/// latest version as long as attempts are left. Other errors aren't retried.
+fn retry_update<T>(result: &std::result::Result<T, SecretUpdateError>, attempt: u32) -> bool {
+    matches!(result, Err(SecretUpdateError::Conflict)) && attempt < MAX_UPDATE_ATTEMPTS
+}
+
+/// Update the resource and retry with its latest version if it was changed after it was read.
+/// Due renewals are marked again for every attempt, so the fields are generated based on the
+/// latest version only and never twice. The permit of a renewal is returned to the caller.
+async fn update_latest<K: GeneratedResource>(
+    obj: &Arc<K>,
+    config: &RunoConfig,
+    renewal_mode: RenewalMode,
+) -> (
+    Arc<K>,
+    Outcome,
+    std::result::Result<K, SecretUpdateError>,
+    Option<RenewalPermit>,
+) {
+    let mut latest = obj.clone();
+    let mut attempt = 1;
+    loop {
+        let settings = config.settings();
+        let obj = match renewal_mode {
+            RenewalMode::Native => schedule::mark_due_renewals(&latest, &Utc::now(), &settings),
+            RenewalMode::CronJob => latest.clone(),
+        };
+        let violations = settings.violations(&obj);
+        if !violations.is_empty() {
+            for violation in violations {
+                error!("{:?}: {}", obj.name_any(), violation);
+            }
+            let outcome = Outcome::build(&obj).failed();
+            return (obj, outcome, Err(SecretUpdateError::Failed), None);
+        }
+        let permit = match secrets::needs_rotation(&obj, &settings) {
+            true => Some(
+                config
+                    .renewal_limiter
+                    .acquire(&obj.namespace().unwrap_or_default())
+                    .await,
+            ),
+            false => None,
+        };
+        let result = secrets::update(&obj, config).await;
+        if retry_update(&result, attempt) {
+            if let Some(refreshed) = secrets::refresh(&obj).await {
+                info!(
+                    "Retry update of {:?} with its latest version",
+                    obj.name_any()
+                );
+                latest = refreshed;
+                attempt += 1;
+                continue;
+            }
+        }
+        return match result {
+            Ok(updated) => {
+                let outcome = Outcome::applied(&obj, &updated, &settings);
+                (obj, outcome, Ok(updated), permit)
+            }
+            Err(e) => {
+                let outcome = Outcome::build(&obj).failed();
+                (obj, outcome, Err(e), permit)
+            }
+        };
     }
-    Ok(Action::requeue(Duration::from_secs(
-        config.requeue_duration,
+}
+
+/// ConfigMaps get the same generation and renewal as Secrets. Renewal CronJobs, workload
+/// restarts and replication are only supported for Secrets, so renewal crons of ConfigMaps are
+/// always handled natively.
+pub(crate) async fn reconcile_config_map(
+    obj: Arc<ConfigMap>,
+    config: Arc<RunoConfig>,
+) -> Result<Action> {
+    info!("reconcile request: ConfigMap {}", obj.name_any());
+    if !namespaces::in_scope(&config, &obj.namespace().unwrap_or_default()) {
+        return Ok(Action::await_change());
+    }
+    if labels::managed_by_us(&obj) {
+        update_config_map(&obj, &config).await;
+    }
+    Ok(Action::requeue(requeue_after(
+        &obj,
+        &config,
+        RenewalMode::Native,
     )))
 }
 
-pub(crate) fn error_policy(_object: Arc<Secret>, _err: &Error, _config: Arc<RunoConfig>) -> Action {
+async fn update_config_map(obj: &Arc<ConfigMap>, config: &RunoConfig) -> Outcome {
+    let (_, outcome, maybe_config_map, _) = update_latest(obj, config, RenewalMode::Native).await;
+    match maybe_config_map {
+        Ok(_) => info!("ConfigMap updated successfully!"),
+        Err(_) => error!("ConfigMap update failed!"),
+    }
+    outcome
+}
+
+fn requeue_after(
+    obj: &Arc<impl GeneratedResource>,
+    config: &RunoConfig,
+    renewal_mode: RenewalMode,
+) -> Duration {
+    let requeue_duration = Duration::from_secs(config.requeue_duration);
+    if !labels::managed_by_us(obj) {
+        return requeue_duration;
+    }
+    let now = Utc::now();
+    let settings = config.settings();
+    let mut next = schedule::until_max_age_expiry(obj, &now, &settings)
+        .into_iter()
+        .chain(schedule::until_grace_period_expiry(obj, &now, &settings))
+        .chain(schedule::until_deferred_renewal(obj, &now, &settings))
+        .min();
+    if renewal_mode == RenewalMode::Native {
+        next = next
+            .into_iter()
+            .chain(schedule::until_next_renewal(obj, &now, &settings))
+            .min();
+    }
+    match next {
+        Some(next) => next.min(requeue_duration),
+        None => requeue_duration,
+    }
+}
+
+pub(crate) fn error_policy<K>(_object: Arc<K>, _err: &Error, _config: Arc<RunoConfig>) -> Action {
     Action::requeue(Duration::from_secs(5))
 }
 
+async fn with_selected_namespaces(config: RunoConfig, client: &kube::Client) -> RunoConfig {
+    match config.namespace_scope().selector {
+        Some(selector) => {
+            let selected = namespaces::watch_selected(client, &selector).await;
+            config.with_selected_namespaces(selected)
+        }
+        None => config,
+    }
+}
+
 pub async fn run_with_reconciliation(config: RunoConfig) {
     let client = K8s::get_client().await;
-    let secrets = Api::<Secret>::all(client);
+    let config = Arc::new(with_selected_namespaces(config, &client).await);
+    if let Some(file) = config.settings_file.clone() {
+        tokio::spawn(settings::watch(
+            file,
+            config.settings.clone(),
+            config.namespace_scope.clone(),
+        ));
+    }
+    tokio::spawn(replication::sweep_periodically(config.clone()));
     let watcher_config = Config {
         label_selector: Some(labels::get_managed_label()),
         ..Default::default()
     };
-    Controller::new(secrets.clone(), watcher_config)
-        .shutdown_on_signal()
-        .run(reconcile, error_policy, Arc::new(config))
-        .filter_map(|x| async move { std::result::Result::ok(x) })
-        .for_each(|_| futures::future::ready(()))
-        .await;
+    let secret_controllers = namespaces::apis::<Secret>(&client, &config.namespace_scope())
+        .into_iter()
+        .map(|secrets| {
+            Controller::new(secrets, watcher_config.clone())
+                .shutdown_on_signal()
+                .run(reconcile, error_policy, config.clone())
+                .filter_map(|x| async move { std::result::Result::ok(x) })
+                .for_each(|_| futures::future::ready(()))
+        });
+    if !config.config_maps {
+        join_all(secret_controllers).await;
+        return;
+    }
+    let config_map_controllers = namespaces::apis::<ConfigMap>(&client, &config.namespace_scope())
+        .into_iter()
+        .map(|config_maps| {
+            Controller::new(config_maps, watcher_config.clone())
+                .shutdown_on_signal()
+                .run(reconcile_config_map, error_policy, config.clone())
+                .filter_map(|x| async move { std::result::Result::ok(x) })
+                .for_each(|_| futures::future::ready(()))
+        });
+    tokio::join!(
+        join_all(secret_controllers),
+        join_all(config_map_controllers)
+    );
+}
+
+/// List all managed resources page by page, so large clusters don't have to be listed at once
+pub(crate) async fn list_managed<K>(api: &Api<K>) -> std::result::Result<Vec<K>, kube::Error>
+where
+    K: Clone + std::fmt::Debug + serde::de::DeserializeOwned,
+{
+    let params = ListParams::default()
+        .labels(&labels::get_managed_label())
+        .limit(ONE_SHOT_PAGE_SIZE);
+    k8s::list_paged(api, params).await
 }
 
-pub async fn run_one_shot(config: RunoConfig) {
+/// Reconcile all managed resources once and collect what happened to them
+pub async fn run_one_shot(config: RunoConfig, concurrency: usize) -> Summary {
     let client = K8s::get_client().await;
-    let secrets = Api::<Secret>::all(client);
-    for secret in secrets.list(&ListParams::default()).await.unwrap() {
-        let _ = reconcile(Arc::new(secret), Arc::new(config)).await;
+    let config = Arc::new(with_selected_namespaces(config, &client).await);
+    let mut summary = Summary::default();
+    for secrets in namespaces::apis::<Secret>(&client, &config.namespace_scope()) {
+        let objs = match list_managed(&secrets).await {
+            Ok(objs) => objs,
+            Err(e) => {
+                error!("Can't list secrets: {:?}", e);
+                summary.list_failures += 1;
+                continue;
+            }
+        };
+        let outcomes = futures::stream::iter(objs.into_iter().map(Arc::new))
+            .filter(|obj| futures::future::ready(in_one_shot_scope(obj, &config)))
+            .map(|obj| {
+                let config = config.clone();
+                async move {
+                    if obj.metadata.deletion_timestamp.is_some() {
+                        let result = replication::cleanup(&obj, &config).await;
+                        return result.err().map(|e| {
+                            let mut outcome = Outcome::build(&obj);
+                            outcome.follow_up(Err(e));
+                            outcome
+                        });
+                    }
+                    Some(update_secret(&obj, &config).await)
+                }
+            })
+            .buffer_unordered(concurrency)
+            .filter_map(futures::future::ready)
+            .collect::<Vec<_>>()
+            .await;
+        summary.outcomes.extend(outcomes);
+    }
+    if !replication::sweep(&config).await {
+        summary.list_failures += 1;
+    }
+    if config.config_maps {
+        for config_maps in namespaces::apis::<ConfigMap>(&client, &config.namespace_scope()) {
+            let objs = match list_managed(&config_maps).await {
+                Ok(objs) => objs,
+                Err(e) => {
+                    error!("Can't list config maps: {:?}", e);
+                    summary.list_failures += 1;
+                    continue;
+                }
+            };
+            let outcomes = futures::stream::iter(objs.into_iter().map(Arc::new))
+                .filter(|obj| futures::future::ready(in_one_shot_scope(obj, &config)))
+                .map(|obj| {
+                    let config = config.clone();
+                    async move { update_config_map(&obj, &config).await }
+                })
+                .buffer_unordered(concurrency)
+                .collect::<Vec<_>>()
+                .await;
+            summary.outcomes.extend(outcomes);
+        }
     }
+    summary
+}
+
+fn in_one_shot_scope(obj: &Arc<impl GeneratedResource>, config: &RunoConfig) -> bool {
+    labels::managed_by_us(obj) && namespaces::in_scope(config, &obj.namespace().unwrap_or_default())
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::config::RunoConfig;
-    use crate::reconciler::reconcile;
+    use crate::config::{RenewalMode, RunoConfig};
+    use crate::errors::SecretUpdateError;
+    use crate::reconciler::{reconcile, retry_update, MAX_UPDATE_ATTEMPTS};
     use k8s_openapi::api::core::v1::Secret;
     use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
 
@@ -72,6 +360,7 @@ mod tests {
 
     use kube::{Api, Client, Config, ResourceExt};
     use regex::Regex;
+    use rstest::rstest;
     use std::collections::BTreeMap;
 
     use k8s_openapi::api::batch::v1::CronJob;
@@ -149,6 +438,20 @@ mod tests {
         }
     }
 
+    #[rstest]
+    #[case(Err(SecretUpdateError::Conflict), 1, true)]
+    #[case(Err(SecretUpdateError::Conflict), MAX_UPDATE_ATTEMPTS - 1, true)]
+    #[case(Err(SecretUpdateError::Conflict), MAX_UPDATE_ATTEMPTS, false)]
+    #[case(Err(SecretUpdateError::Failed), 1, false)]
+    #[case(Ok(()), 1, false)]
+    fn test_retry_update(
+        #[case] result: std::result::Result<(), SecretUpdateError>,
+        #[case] attempt: u32,
+        #[case] expected: bool,
+    ) {
+        assert_eq!(retry_update(&result, attempt), expected);
+    }
+
     #[tokio::test]
     async fn integration_reconcile_should_generate_secret() {
         let secret_name = "runo-generate-test-generate";
@@ -490,6 +793,65 @@ mod tests {
             .unwrap();
     }
 
+    #[tokio::test]
+    async fn integration_reconcile_should_keep_values_of_removed_fields() {
+        let secret_name = "runo-generate-test-removed-field";
+        let config = Config::from_kubeconfig(&get_kubeconfig_options())
+            .await
+            .unwrap();
+        let client = Client::try_from(config).unwrap();
+        let runo_config = Arc::new(RunoConfig::build(K8s::build(false), 300));
+
+        let secret = build_managed_secret_with_annotations(
+            secret_name.to_string(),
+            vec![
+                (
+                    String::from("v1.secret.runo.rocks/generate-0"),
+                    String::from("username"),
+                ),
+                (
+                    String::from("v1.secret.runo.rocks/generate-1"),
+                    String::from("password"),
+                ),
+            ],
+        );
+        let secrets: Api<Secret> = Api::namespaced(client.clone(), "default");
+        secrets.create(&build_post_params(), &secret).await.unwrap();
+        reconcile(Arc::new(secret), runo_config.clone())
+            .await
+            .unwrap();
+        let password = secrets.get(secret_name).await.unwrap().data.unwrap()["password"].clone();
+
+        // Remove the field of the password
+        let patch = serde_json::json!({
+            "metadata": {
+                "annotations": {
+                    "v1.secret.runo.rocks/generate-1": null
+                }
+            }
+        });
+        secrets
+            .patch(secret_name, &PatchParams::default(), &Patch::Merge(patch))
+            .await
+            .unwrap();
+        reconcile(
+            Arc::new(secrets.get(secret_name).await.unwrap()),
+            runo_config.clone(),
+        )
+        .await
+        .unwrap();
+
+        // The password isn't generated anymore, but its value is kept
+        let data = secrets.get(secret_name).await.unwrap().data.unwrap();
+        assert!(data.contains_key("username"));
+        assert_eq!(data.get("password"), Some(&password));
+
+        secrets
+            .delete(secret_name, &DeleteParams::default())
+            .await
+            .unwrap();
+    }
+
     #[tokio::test]
     async fn integration_reconcile_should_not_generate_secret_when_unmanaged() {
         let secret_name = "runo-generate-test-unmanaged";
@@ -829,7 +1191,8 @@ mod tests {
         let client = Client::try_from(config).unwrap();
 
         let k8s = K8s::build(false);
-        let runo_config = Arc::new(RunoConfig::build(k8s, 300));
+        let runo_config =
+            Arc::new(RunoConfig::build(k8s, 300).with_renewal_mode(RenewalMode::CronJob));
 
         let key_1 = String::from("v1.secret.runo.rocks/generate-0");
         let value_1 = String::from("username");
@@ -893,6 +1256,167 @@ mod tests {
             .unwrap();
     }
 
+    #[tokio::test]
+    async fn integration_reconcile_should_delete_cronjobs_in_native_mode() {
+        let secret_name = "runo-generate-test-cronjob-cleanup";
+        let config = Config::from_kubeconfig(&get_kubeconfig_options())
+            .await
+            .unwrap();
+        let client = Client::try_from(config).unwrap();
+
+        let secret = build_managed_secret_with_annotations(
+            secret_name.to_string(),
+            vec![
+                (
+                    String::from("v1.secret.runo.rocks/generate-0"),
+                    String::from("username"),
+                ),
+                (
+                    String::from("v1.secret.runo.rocks/renewal-cron-0"),
+                    String::from("0 0 * * *"),
+                ),
+            ],
+        );
+        let secrets: Api<Secret> = Api::namespaced(client.clone(), "default");
+        secrets.create(&build_post_params(), &secret).await.unwrap();
+        let secret = Arc::new(secrets.get(secret_name).await.unwrap());
+        let cronjobs: Api<CronJob> = Api::namespaced(client.clone(), "default");
+        let cron_name = build_cron_name(&secret, "0");
+
+        // CronJob mode creates the CronJob of the field
+        let cronjob_config = Arc::new(
+            RunoConfig::build(K8s::build(false), 300).with_renewal_mode(RenewalMode::CronJob),
+        );
+        reconcile(secret.clone(), cronjob_config).await.unwrap();
+        assert!(cronjobs.get_opt(&cron_name).await.unwrap().is_some());
+
+        // native mode deletes it, so the field isn't renewed twice
+        let native_config = Arc::new(RunoConfig::build(K8s::build(false), 300));
+        reconcile(secret, native_config).await.unwrap();
+        assert!(cronjobs.get_opt(&cron_name).await.unwrap().is_none());
+
+        secrets
+            .delete(secret_name, &DeleteParams::default())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn integration_reconcile_should_generate_secret_with_native_renewal() {
+        let secret_name = "runo-generate-test-native-renewal";
+        let config = Config::from_kubeconfig(&get_kubeconfig_options())
+            .await
+            .unwrap();
+        let client = Client::try_from(config).unwrap();
+
+        let k8s = K8s::build(false);
+        let runo_config = Arc::new(RunoConfig::build(k8s, 300));
+
+        let key_1 = String::from("v1.secret.runo.rocks/generate-0");
+        let value_1 = String::from("username");
+
+        let key_2 = String::from("v1.secret.runo.rocks/renewal-cron-0");
+        let value_2 = String::from("* * * * *");
+
+        let post_params = build_post_params();
+        let secret = build_managed_secret_with_annotations(
+            secret_name.to_string(),
+            vec![(key_1, value_1), (key_2, value_2)],
+        );
+        let secrets: Api<Secret> = Api::namespaced(client.clone(), "default");
+        let cronjobs: Api<CronJob> = Api::namespaced(client.clone(), "default");
+        secrets.create(&post_params, &secret).await.unwrap();
+
+        // reconcile it
+        reconcile(Arc::new(secret.clone()), runo_config.clone())
+            .await
+            .unwrap();
+        let secret_before_cron = secrets.get(secret_name).await.unwrap();
+        let username_before_cron = secret_before_cron.data.clone().unwrap();
+        sleep(Duration::from_secs(60)).await;
+
+        // reconcile again, the renewal is due by now
+        reconcile(Arc::new(secret_before_cron), runo_config)
+            .await
+            .unwrap();
+        let username_after_cron = secrets.get(secret_name).await.unwrap().data.unwrap();
+        assert_ne!(
+            username_before_cron.get("username"),
+            username_after_cron.get("username")
+        );
+
+        // No CronJob should be created in native mode
+        assert!(cronjobs
+            .get(build_cron_name(&Arc::new(secret), "0").as_str())
+            .await
+            .is_err());
+
+        secrets
+            .delete(secret_name, &DeleteParams::default())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn integration_reconcile_should_delete_cronjob_when_cron_removed() {
+        let secret_name = "runo-generate-test-cronjob-removed";
+        let config = Config::from_kubeconfig(&get_kubeconfig_options())
+            .await
+            .unwrap();
+        let client = Client::try_from(config).unwrap();
+
+        let k8s = K8s::build(false);
+        let runo_config =
+            Arc::new(RunoConfig::build(k8s, 300).with_renewal_mode(RenewalMode::CronJob));
+
+        let key_0 = String::from("v1.secret.runo.rocks/generate-0");
+        let value_0 = String::from("username");
+
+        let key_1 = String::from("v1.secret.runo.rocks/renewal-cron-0");
+        let value_1 = String::from("0 0 * * *");
+
+        let post_params = build_post_params();
+        let secret = build_managed_secret_with_annotations(
+            secret_name.to_string(),
+            vec![(key_0.clone(), value_0.clone()), (key_1, value_1)],
+        );
+        let secrets: Api<Secret> = Api::namespaced(client.clone(), "default");
+        let cronjobs: Api<CronJob> = Api::namespaced(client.clone(), "default");
+        let created = secrets.create(&post_params, &secret).await.unwrap();
+
+        // reconcile it
+        reconcile(Arc::new(created.clone()), runo_config.clone())
+            .await
+            .unwrap();
+        let cronjob = cronjobs
+            .get(build_cron_name(&Arc::new(secret.clone()), "0").as_str())
+            .await
+            .unwrap();
+        // CronJob should be owned by the secret
+        assert_eq!(
+            cronjob.owner_references().first().unwrap().uid,
+            created.uid().unwrap()
+        );
+
+        // remove the cron annotation and reconcile again
+        let mut without_cron = secrets.get(secret_name).await.unwrap();
+        without_cron
+            .annotations_mut()
+            .remove("v1.secret.runo.rocks/renewal-cron-0");
+        reconcile(Arc::new(without_cron), runo_config)
+            .await
+            .unwrap();
+        assert!(cronjobs
+            .get(build_cron_name(&Arc::new(secret), "0").as_str())
+            .await
+            .is_err());
+
+        secrets
+            .delete(secret_name, &DeleteParams::default())
+            .await
+            .unwrap();
+    }
+
     #[tokio::test]
     async fn integration_reconcile_should_not_generate_secret_when_dry_run() {
         let secret_name = "runo-generate-test-generate-dry-run";
@@ -937,7 +1461,8 @@ mod tests {
         let client = Client::try_from(config).unwrap();
 
         let k8s = K8s::build(true);
-        let runo_config = Arc::new(RunoConfig::build(k8s, 300));
+        let runo_config =
+            Arc::new(RunoConfig::build(k8s, 300).with_renewal_mode(RenewalMode::CronJob));
 
         let key_0 = String::from("v1.secret.runo.rocks/generate-0");
         let value_0 = String::from("username");
@@ -0,0 +1,394 @@
+use crate::errors::InvalidManifest;
+use crate::labels;
+use crate::resource::GeneratedResource;
+use crate::secrets;
+use crate::settings::Settings;
+use k8s_openapi::api::core::v1::{ConfigMap, Secret};
+use k8s_openapi::ByteString;
+use kube::{Resource, ResourceExt};
+use serde_json::Value;
+use std::sync::Arc;
+
+fn invalid(reason: impl Into<String>) -> InvalidManifest {
+    InvalidManifest {
+        reason: reason.into(),
+    }
+}
+
+fn is_kind<K: Resource<DynamicType = ()>>(document: &Value) -> bool {
+    document.get("kind").and_then(Value::as_str) == Some(K::kind(&()).as_ref())
+}
+
+fn parse<K: GeneratedResource>(document: &Value) -> Result<K, InvalidManifest> {
+    serde_json::from_value(document.clone())
+        .map_err(|e| invalid(format!("Can't parse {}: {}", K::kind(&()), e)))
+}
+
+fn parse_all<K: GeneratedResource>(documents: &[Value]) -> Result<Vec<Arc<K>>, InvalidManifest> {
+    documents
+        .iter()
+        .filter(|d| is_kind::<K>(d))
+        .map(|d| parse::<K>(d).map(Arc::new))
+        .collect()
+}
+
+/// `stringData` is merged into `data` by Kubernetes, so generation sees all values
+fn merge_string_data(mut secret: Secret) -> Secret {
+    if let Some(string_data) = secret.string_data.take() {
+        let data = secret.data.get_or_insert_with(Default::default);
+        for (k, v) in string_data {
+            data.insert(k, ByteString(v.into_bytes()));
+        }
+    }
+    secret
+}
+
+fn generate<K: GeneratedResource>(
+    obj: &Arc<K>,
+    resources: &[Arc<K>],
+    settings: &Settings,
+) -> Result<Option<K>, InvalidManifest> {
+    if !labels::managed_by_us(obj) {
+        return Ok(None);
+    }
+    if let Some(violation) = settings.violations(obj).first() {
+        return Err(invalid(format!(
+            "{} {}: {}",
+            K::kind(&()),
+            obj.name_any(),
+            violation
+        )));
+    }
+    if let Some(source) = secrets::unresolved_local_clone_sources(obj, resources).first() {
+        return Err(invalid(format!(
+            "{} {}: clone source {:?} can't be resolved",
+            K::kind(&()),
+            obj.name_any(),
+            source
+        )));
+    }
+    secrets::generate_offline(obj, resources, settings)
+        .map(Some)
+        .map_err(|e| invalid(format!("{} {}: {}", K::kind(&()), obj.name_any(), e)))
+}
+
+fn render_secret(
+    obj: &Arc<Secret>,
+    secrets: &[Arc<Secret>],
+    settings: &Settings,
+) -> Result<Secret, InvalidManifest> {
+    let mut secret = obj.as_ref().clone();
+    if let Some(updated) = generate(obj, secrets, settings)? {
+        secret.data = updated.data;
+        secret.metadata.annotations = updated.metadata.annotations;
+    }
+    Ok(secret)
+}
+
+fn render_config_map(
+    obj: &Arc<ConfigMap>,
+    config_maps: &[Arc<ConfigMap>],
+    settings: &Settings,
+) -> Result<ConfigMap, InvalidManifest> {
+    let mut config_map = obj.as_ref().clone();
+    if let Some(updated) = generate(obj, config_maps, settings)? {
+        config_map.data = updated.data;
+        config_map.metadata.annotations = updated.metadata.annotations;
+    }
+    Ok(config_map)
+}
+
+/// Order the resources so that every resource comes after the resources it clones from
+fn dependency_order<K: GeneratedResource>(
+    resources: &[Arc<K>],
+) -> Result<Vec<usize>, InvalidManifest> {
+    let dependencies = resources
+        .iter()
+        .enumerate()
+        .map(|(i, obj)| {
+            secrets::clone_source_refs(obj)
+                .into_iter()
+                .filter_map(|(namespace, name)| {
+                    resources.iter().position(|r| {
+                        r.name_any() == name && r.namespace().unwrap_or_default() == namespace
+                    })
+                })
+                .filter(|j| *j != i)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let mut order = Vec::new();
+    while order.len() < resources.len() {
+        let next = (0..resources.len())
+            .find(|i| !order.contains(i) && dependencies[*i].iter().all(|j| order.contains(j)));
+        match next {
+            Some(i) => order.push(i),
+            None => {
+                let cycle = (0..resources.len())
+                    .filter(|i| !order.contains(i))
+                    .map(|i| resources[i].name_any())
+                    .collect::<Vec<_>>();
+                return Err(invalid(format!(
+                    "{}s {} clone from each other",
+                    K::kind(&()),
+                    cycle.join(", ")
+                )));
+            }
+        }
+    }
+    Ok(order)
+}
+
+/// Render the resources of a kind in dependency order, so cloned fields get the values
+/// generated for their sources
+fn render_all<K: GeneratedResource>(
+    mut resources: Vec<Arc<K>>,
+    render_one: impl Fn(&Arc<K>, &[Arc<K>]) -> Result<K, InvalidManifest>,
+) -> Result<Vec<Arc<K>>, InvalidManifest> {
+    for i in dependency_order(&resources)? {
+        resources[i] = Arc::new(render_one(&resources[i], &resources)?);
+    }
+    Ok(resources)
+}
+
+/// Generate the values of all managed Secrets and ConfigMaps of a manifest with one or more
+/// documents. Other documents are kept as they are and fields can be cloned from resources of
+/// the same manifest.
+pub fn render(content: &str, settings: &Settings) -> Result<String, InvalidManifest> {
+    let documents: Vec<Value> = serde_saphyr::from_multiple(content)
+        .map_err(|_| invalid("Can't parse YAML or JSON documents"))?;
+    let secrets = parse_all::<Secret>(&documents)?
+        .into_iter()
+        .map(|s| Arc::new(merge_string_data(s.as_ref().clone())))
+        .collect::<Vec<_>>();
+    let mut secrets = render_all(secrets, |s, all| render_secret(s, all, settings))?.into_iter();
+    let mut config_maps = render_all(parse_all::<ConfigMap>(&documents)?, |c, all| {
+        render_config_map(c, all, settings)
+    })?
+    .into_iter();
+    let mut rendered = Vec::new();
+    for document in &documents {
+        let document = if is_kind::<Secret>(document) {
+            serde_json::to_value(secrets.next().as_deref())
+        } else if is_kind::<ConfigMap>(document) {
+            serde_json::to_value(config_maps.next().as_deref())
+        } else {
+            Ok(document.clone())
+        }
+        .map_err(|e| invalid(e.to_string()))?;
+        rendered.push(serde_saphyr::to_string(&document).map_err(|e| invalid(e.to_string()))?);
+    }
+    Ok(rendered.join("---\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+    use crate::settings::Settings;
+    use k8s_openapi::api::core::v1::Secret;
+    use rstest::rstest;
+
+    fn render_secrets(manifest: &str) -> Vec<Secret> {
+        serde_saphyr::from_multiple(&render(manifest, &Settings::default()).unwrap()).unwrap()
+    }
+
+    #[rstest]
+    fn render_generates_managed_secret() {
+        let secrets = render_secrets(
+            "\
+apiVersion: v1
+kind: Secret
+type: Opaque
+metadata:
+  name: example
+  labels:
+    v1.secret.runo.rocks/managed: \"true\"
+  annotations:
+    v1.secret.runo.rocks/generate-0: password
+    v1.secret.runo.rocks/length-0: \"12\"
+stringData:
+  username: admin
+",
+        );
+        let secret = &secrets[0];
+        assert_eq!(secret.type_, Some("Opaque".to_string()));
+        let data = secret.data.as_ref().unwrap();
+        assert_eq!(data.get("username").unwrap().0, b"admin".to_vec());
+        assert_eq!(data.get("password").unwrap().0.len(), 12);
+        assert!(secret.string_data.is_none());
+        let annotations = secret.metadata.annotations.as_ref().unwrap();
+        assert!(annotations.contains_key("v1.secret.runo.rocks/generated-at-0"));
+        assert!(annotations.contains_key("v1.secret.runo.rocks/config-checksum-0"));
+    }
+
+    #[rstest]
+    fn render_clones_from_other_documents() {
+        let secrets = render_secrets(
+            "\
+apiVersion: v1
+kind: Secret
+metadata:
+  name: source
+  namespace: apps
+  labels:
+    v1.secret.runo.rocks/managed: \"true\"
+  annotations:
+    v1.secret.runo.rocks/clonable-by: apps
+data:
+  token: dG9rZW4=
+---
+apiVersion: v1
+kind: Secret
+metadata:
+  name: target
+  namespace: apps
+  labels:
+    v1.secret.runo.rocks/managed: \"true\"
+  annotations:
+    v1.secret.runo.rocks/generate-0: token
+    v1.secret.runo.rocks/clone-from-0: source/token
+",
+        );
+        assert_eq!(secrets.len(), 2);
+        let data = secrets[1].data.as_ref().unwrap();
+        assert_eq!(data.get("token").unwrap().0, b"token".to_vec());
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("  annotations:\n    v1.secret.runo.rocks/clonable-by: other\n")]
+    fn render_fails_for_source_without_opt_in(#[case] annotations: &str) {
+        let manifest = format!(
+            "\
+apiVersion: v1
+kind: Secret
+metadata:
+  name: source
+  namespace: apps
+  labels:
+    v1.secret.runo.rocks/managed: \"true\"
+{}data:
+  token: dG9rZW4=
+---
+apiVersion: v1
+kind: Secret
+metadata:
+  name: target
+  namespace: apps
+  labels:
+    v1.secret.runo.rocks/managed: \"true\"
+  annotations:
+    v1.secret.runo.rocks/generate-0: token
+    v1.secret.runo.rocks/clone-from-0: source/token
+",
+            annotations
+        );
+        assert!(render(&manifest, &Settings::default()).is_err());
+    }
+
+    #[rstest]
+    fn render_clones_generated_values_of_later_documents() {
+        let secrets = render_secrets(
+            "\
+apiVersion: v1
+kind: Secret
+metadata:
+  name: target
+  namespace: apps
+  labels:
+    v1.secret.runo.rocks/managed: \"true\"
+  annotations:
+    v1.secret.runo.rocks/generate-0: token
+    v1.secret.runo.rocks/clone-from-0: source/token
+---
+apiVersion: v1
+kind: Secret
+metadata:
+  name: source
+  namespace: apps
+  labels:
+    v1.secret.runo.rocks/managed: \"true\"
+  annotations:
+    v1.secret.runo.rocks/clonable-by: apps
+    v1.secret.runo.rocks/generate-0: token
+",
+        );
+        assert_eq!(secrets[0].metadata.name, Some("target".to_string()));
+        let cloned = secrets[0].data.as_ref().unwrap().get("token").unwrap();
+        let generated = secrets[1].data.as_ref().unwrap().get("token").unwrap();
+        assert_eq!(cloned, generated);
+    }
+
+    #[rstest]
+    fn render_fails_for_cloning_cycles() {
+        let manifest = "\
+apiVersion: v1
+kind: Secret
+metadata:
+  name: a
+  labels:
+    v1.secret.runo.rocks/managed: \"true\"
+  annotations:
+    v1.secret.runo.rocks/generate-0: token
+    v1.secret.runo.rocks/clone-from-0: b/token
+---
+apiVersion: v1
+kind: Secret
+metadata:
+  name: b
+  labels:
+    v1.secret.runo.rocks/managed: \"true\"
+  annotations:
+    v1.secret.runo.rocks/generate-0: token
+    v1.secret.runo.rocks/clone-from-0: a/token
+";
+        let error = render(manifest, &Settings::default())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("a, b"), "{}", error);
+    }
+
+    #[rstest]
+    fn render_fails_for_unresolved_clone_source() {
+        let manifest = "\
+apiVersion: v1
+kind: Secret
+metadata:
+  name: target
+  labels:
+    v1.secret.runo.rocks/managed: \"true\"
+  annotations:
+    v1.secret.runo.rocks/generate-0: token
+    v1.secret.runo.rocks/clone-from-0: missing/token
+";
+        let error = render(manifest, &Settings::default())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("\"missing/token\""), "{}", error);
+    }
+
+    #[rstest]
+    fn render_resolves_profiles_of_settings() {
+        let manifest = "\
+apiVersion: v1
+kind: Secret
+metadata:
+  name: example
+  labels:
+    v1.secret.runo.rocks/managed: \"true\"
+  annotations:
+    v1.secret.runo.rocks/generate-0: pin
+    v1.secret.runo.rocks/profile-0: pin
+";
+        let settings = crate::settings::test_settings();
+        let secret =
+            serde_saphyr::from_str::<Secret>(&render(manifest, &settings).unwrap()).unwrap();
+        let pin = &secret.data.unwrap()["pin"].0;
+        assert_eq!(pin.len(), 6);
+        assert!(pin.iter().all(u8::is_ascii_digit));
+        let error = render(manifest, &Settings::default())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("profile \"pin\" doesn't exist"), "{}", error);
+    }
+}
@@ -0,0 +1,683 @@
+use crate::annotations::replicate_to;
+use crate::config::RunoConfig;
+use crate::errors::{
+    FollowUpFailed, InvalidReplicationTarget, NamespaceOutOfScope, NoNamespaceForSecret,
+    ReplicaCollision, ReplicationNamespaceNotAllowed,
+};
+use crate::k8s::{self, K8s};
+use crate::labels::{self, get_replica_of_label, get_replica_of_uid_label, to_label_value};
+use crate::namespaces;
+use crate::reconciler::ONE_SHOT_PAGE_SIZE;
+use k8s_openapi::api::core::v1::{Namespace, Secret};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::ByteString;
+use kube::api::{ListParams, Patch};
+use kube::{Api, Client, ResourceExt};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error, info};
+
+/// Finalizer which earlier versions put on replicated Secrets. It is only released anymore,
+/// replicas are found by their labels instead.
+pub fn get_replication_finalizer() -> String {
+    "v1.secret.runo.rocks/replicas".to_string()
+}
+
+/// Annotation on replicas which holds `<namespace>/<name>` of the source
+pub fn get_replica_source_annotation() -> String {
+    "v1.secret.runo.rocks/replica-source".to_string()
+}
+
+/// Prefix of `replicate-to` values which select namespaces by labels
+const SELECTOR_PREFIX: &str = "selector:";
+
+/// Namespaces the Secret is replicated to, either listed by name or selected by labels
+#[derive(Debug, PartialEq)]
+pub enum ReplicationTarget {
+    Namespaces(Vec<String>),
+    Selector(String),
+}
+
+/// Namespace names are DNS labels
+fn is_namespace_name(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 63
+        && value.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && value.ends_with(|c: char| c.is_ascii_alphanumeric())
+        && value
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+fn invalid_target(value: &str, reason: String) -> InvalidReplicationTarget {
+    InvalidReplicationTarget {
+        value: value.to_string(),
+        reason,
+    }
+}
+
+/// A comma separated list of namespace names or a label selector with the `selector:` prefix.
+/// Values which are neither are rejected instead of guessing what was meant.
+pub fn parse_target(value: &str) -> Result<ReplicationTarget, InvalidReplicationTarget> {
+    if let Some(selector) = value.strip_prefix(SELECTOR_PREFIX) {
+        return match selector.trim() {
+            "" => Err(invalid_target(value, "The selector is empty.".to_string())),
+            selector => Ok(ReplicationTarget::Selector(selector.to_string())),
+        };
+    }
+    let names: Vec<String> = value.split(',').map(|n| n.trim().to_string()).collect();
+    match names.iter().find(|n| !is_namespace_name(n)) {
+        Some(name) => Err(invalid_target(
+            value,
+            format!(
+                "{:?} isn't a namespace name, label selectors need the {:?} prefix.",
+                name, SELECTOR_PREFIX
+            ),
+        )),
+        None => Ok(ReplicationTarget::Namespaces(names)),
+    }
+}
+
+fn source_of(obj: &Arc<Secret>) -> String {
+    format!("{}/{}", obj.namespace().unwrap_or_default(), obj.name_any())
+}
+
+fn replica_of_label_value(obj: &Arc<Secret>) -> String {
+    to_label_value(&format!(
+        "{}.{}",
+        obj.namespace().unwrap_or_default(),
+        obj.name_any()
+    ))
+}
+
+fn is_replica_of(replica: &Secret, obj: &Arc<Secret>) -> bool {
+    replica.annotations().get(&get_replica_source_annotation()) == Some(&source_of(obj))
+}
+
+/// Replicas are updated in place, so mounted replicas get the new data as well. They are applied
+/// by runo's field manager and labelled as replicas, so changes are overwritten on the next
+/// update.
+pub fn build_replica(
+    obj: &Arc<Secret>,
+    namespace: &str,
+    data: Option<BTreeMap<String, ByteString>>,
+) -> Secret {
+    Secret {
+        metadata: ObjectMeta {
+            name: Some(obj.name_any()),
+            namespace: Some(namespace.to_string()),
+            labels: Some(BTreeMap::from([
+                (get_replica_of_label(), replica_of_label_value(obj)),
+                (get_replica_of_uid_label(), obj.uid().unwrap_or_default()),
+            ])),
+            annotations: Some(BTreeMap::from([(
+                get_replica_source_annotation(),
+                source_of(obj),
+            )])),
+            ..ObjectMeta::default()
+        },
+        data,
+        type_: obj.type_.clone(),
+        ..Secret::default()
+    }
+}
+
+/// Only namespaces the controller allows and is responsible for are replicated to
+fn allowed_targets(
+    namespaces: Vec<String>,
+    own_namespace: &str,
+    config: &RunoConfig,
+) -> Vec<String> {
+    namespaces
+        .into_iter()
+        .filter(|n| n != own_namespace)
+        .filter(|n| {
+            if !config.allowed_replication_namespaces.contains(n) {
+                error!(
+                    "{}",
+                    ReplicationNamespaceNotAllowed {
+                        namespace: n.clone()
+                    }
+                );
+                return false;
+            }
+            if !namespaces::in_scope(config, n) {
+                error!(
+                    "{}",
+                    NamespaceOutOfScope {
+                        namespace: n.clone()
+                    }
+                );
+                return false;
+            }
+            true
+        })
+        .collect()
+}
+
+/// Namespaces of the target, if they could be resolved. Selecting namespaces by labels needs
+/// access to all namespaces, which controllers limited to listed namespaces don't have.
+async fn target_namespaces(target: ReplicationTarget, config: &RunoConfig) -> Option<Vec<String>> {
+    match target {
+        ReplicationTarget::Namespaces(names) => Some(names),
+        ReplicationTarget::Selector(selector) => {
+            if !config.namespace_scope().namespaces.is_empty() {
+                error!(
+                    "{}",
+                    invalid_target(
+                        &selector,
+                        "Label selectors can't be used if runo is limited with --namespace."
+                            .to_string()
+                    )
+                );
+                return None;
+            }
+            let api: Api<Namespace> = Api::all(K8s::get_client().await);
+            match api.list(&ListParams::default().labels(&selector)).await {
+                Ok(list) => Some(list.into_iter().map(|n| n.name_any()).collect()),
+                Err(e) => {
+                    error!("Can't list namespaces with {:?}: {:?}", selector, e);
+                    None
+                }
+            }
+        }
+    }
+}
+
+/// An existing replica is current if it has the data and labels of the replica
+fn is_current(existing: &Secret, replica: &Secret) -> bool {
+    existing.immutable != Some(true)
+        && existing.data == replica.data
+        && existing.type_ == replica.type_
+        && replica
+            .labels()
+            .iter()
+            .all(|(key, value)| existing.labels().get(key) == Some(value))
+}
+
+/// Replicas of earlier versions are immutable and the type of a Secret can't be changed, so
+/// those replicas are recreated once
+fn needs_recreation(existing: &Secret, replica: &Secret) -> bool {
+    existing.immutable == Some(true) || existing.type_ != replica.type_
+}
+
+async fn apply_replica(replica: Secret, obj: &Arc<Secret>, k8s: &K8s) -> bool {
+    let namespace = replica.namespace().unwrap_or_default();
+    let secrets: Api<Secret> = Api::namespaced(K8s::get_client().await, &namespace);
+    match secrets.get_opt(&replica.name_any()).await {
+        Ok(Some(existing)) if !is_replica_of(&existing, obj) => {
+            error!(
+                "{}",
+                ReplicaCollision {
+                    namespace,
+                    name: replica.name_any()
+                }
+            );
+            return false;
+        }
+        Ok(Some(existing)) if is_current(&existing, &replica) => {
+            debug!("Replica in {:?} is up to date", namespace);
+            return true;
+        }
+        Ok(Some(existing)) if needs_recreation(&existing, &replica) => {
+            if let Err(e) = secrets
+                .delete(&replica.name_any(), &k8s.get_delete_params())
+                .await
+            {
+                error!("{:?}", e);
+                return false;
+            }
+        }
+        Ok(_) => (),
+        Err(e) => {
+            error!("{:?}", e);
+            return false;
+        }
+    }
+    match secrets
+        .patch(
+            &replica.name_any(),
+            &k8s.get_patch_params(),
+            &Patch::Apply(&replica),
+        )
+        .await
+    {
+        Ok(_) => {
+            debug!("Replica in {:?} updated successfully", namespace);
+            true
+        }
+        Err(e) => {
+            error!("{:?}", e);
+            false
+        }
+    }
+}
+
+async fn delete_replica(replica: &Secret, client: &Client, k8s: &K8s) -> bool {
+    let namespace = replica.namespace().unwrap_or_default();
+    let secrets: Api<Secret> = Api::namespaced(client.clone(), &namespace);
+    match secrets
+        .delete(&replica.name_any(), &k8s.get_delete_params())
+        .await
+    {
+        Ok(_) => {
+            info!(
+                "Replica of {:?} in {:?} deleted",
+                replica.annotations().get(&get_replica_source_annotation()),
+                namespace
+            );
+            true
+        }
+        Err(e) => {
+            error!("{:?}", e);
+            false
+        }
+    }
+}
+
+/// Delete all replicas of the Secret except those in the given namespaces. Replicas are listed
+/// in each namespace the controller is responsible for, so namespaced Roles are enough.
+async fn delete_replicas(obj: &Arc<Secret>, keep: &[String], config: &RunoConfig) -> bool {
+    let client = K8s::get_client().await;
+    let params = ListParams::default()
+        .labels(&format!(
+            "{}={}",
+            get_replica_of_label(),
+            replica_of_label_value(obj)
+        ))
+        .limit(ONE_SHOT_PAGE_SIZE);
+    let mut deleted = true;
+    for secrets in namespaces::apis::<Secret>(&client, &config.namespace_scope()) {
+        let replicas = match k8s::list_paged(&secrets, params.clone()).await {
+            Ok(replicas) => replicas,
+            Err(e) => {
+                error!("Can't list replicas of {:?}: {:?}", obj.name_any(), e);
+                deleted = false;
+                continue;
+            }
+        };
+        for replica in replicas
+            .iter()
+            .filter(|r| is_replica_of(r, obj))
+            .filter(|r| !keep.contains(&r.namespace().unwrap_or_default()))
+        {
+            deleted &= delete_replica(replica, &client, &config.k8s).await
+        }
+    }
+    deleted
+}
+
+fn has_finalizer(obj: &Arc<Secret>) -> bool {
+    obj.finalizers().contains(&get_replication_finalizer())
+}
+
+/// Remove the finalizer of earlier versions, so the Secret doesn't get stuck on deletion
+async fn release_finalizer(obj: &Arc<Secret>, k8s: &K8s) -> bool {
+    if !has_finalizer(obj) {
+        return true;
+    }
+    let secrets: Api<Secret> = Api::namespaced(
+        K8s::get_client().await,
+        &obj.namespace().unwrap_or_default(),
+    );
+    let finalizers: Vec<String> = obj
+        .finalizers()
+        .iter()
+        .filter(|f| **f != get_replication_finalizer())
+        .cloned()
+        .collect();
+    let patch = serde_json::json!({
+        "metadata": {
+            "finalizers": finalizers
+        }
+    });
+    match secrets
+        .patch(
+            &obj.name_any(),
+            &k8s.get_merge_patch_params(),
+            &Patch::Merge(patch),
+        )
+        .await
+    {
+        Ok(_) => true,
+        Err(e) => {
+            error!("Can't update finalizers of {:?}: {:?}", obj.name_any(), e);
+            false
+        }
+    }
+}
+
+fn failed() -> FollowUpFailed {
+    FollowUpFailed {
+        step: "replication".to_string(),
+    }
+}
+
+/// Delete the replicas of a deleted Secret. Replicas which can't be deleted now are left to the
+/// sweep, so the deletion of the Secret is never blocked.
+pub async fn cleanup(obj: &Arc<Secret>, config: &RunoConfig) -> Result<(), FollowUpFailed> {
+    let deleted = delete_replicas(obj, &[], config).await;
+    match release_finalizer(obj, &config.k8s).await && deleted {
+        true => Ok(()),
+        false => Err(failed()),
+    }
+}
+
+/// Create or update the replicas of the Secret in all allowed target namespaces and delete those
+/// in namespaces which aren't targeted anymore. Once `replicate-to` is removed, the replicas
+/// are deleted by the sweep.
+pub async fn update(
+    obj: &Arc<Secret>,
+    data: Option<BTreeMap<String, ByteString>>,
+    config: &RunoConfig,
+) -> Result<(), FollowUpFailed> {
+    let k8s = &config.k8s;
+    let own_namespace = match obj.namespace() {
+        Some(namespace) => namespace,
+        None => {
+            error!("{:?}", NoNamespaceForSecret);
+            return Err(failed());
+        }
+    };
+    let mut applied = release_finalizer(obj, k8s).await;
+    let target = match replicate_to(obj).map(|value| parse_target(&value)) {
+        Some(Ok(target)) => target,
+        Some(Err(e)) => {
+            error!("{}", e);
+            return Err(failed());
+        }
+        None if applied => return Ok(()),
+        None => return Err(failed()),
+    };
+    let namespaces = match target_namespaces(target, config).await {
+        Some(namespaces) => allowed_targets(namespaces, &own_namespace, config),
+        None => return Err(failed()),
+    };
+    for namespace in namespaces.iter() {
+        applied &= apply_replica(build_replica(obj, namespace, data.clone()), obj, k8s).await
+    }
+    match delete_replicas(obj, &namespaces, config).await && applied {
+        true => Ok(()),
+        false => Err(failed()),
+    }
+}
+
+/// A replica is orphaned if its source is gone or out of scope, was recreated or isn't
+/// replicated anymore
+fn is_orphan(replica: &Secret, source: Option<&Arc<Secret>>) -> bool {
+    match source {
+        None => true,
+        Some(obj) => {
+            obj.metadata.deletion_timestamp.is_some()
+                || !labels::managed_by_us(obj)
+                || replicate_to(obj).is_none()
+                || !is_replica_of(replica, obj)
+                || replica
+                    .labels()
+                    .get(&get_replica_of_uid_label())
+                    .is_some_and(|uid| Some(uid) != obj.uid().as_ref())
+        }
+    }
+}
+
+/// Source of the replica, which is looked up only once per sweep. Sources out of scope are
+/// treated as gone, the controller isn't responsible for them anymore.
+async fn lookup_source(
+    source: &str,
+    sources: &mut BTreeMap<String, Option<Arc<Secret>>>,
+    client: &Client,
+    config: &RunoConfig,
+) -> Result<Option<Arc<Secret>>, kube::Error> {
+    if let Some(obj) = sources.get(source) {
+        return Ok(obj.clone());
+    }
+    let obj = match source.split_once('/') {
+        Some((namespace, name)) if namespaces::in_scope(config, namespace) => {
+            let secrets: Api<Secret> = Api::namespaced(client.clone(), namespace);
+            secrets.get_opt(name).await?.map(Arc::new)
+        }
+        _ => None,
+    };
+    sources.insert(source.to_string(), obj.clone());
+    Ok(obj)
+}
+
+/// Delete the replicas of all Secrets which were deleted, aren't managed or replicated anymore or
+/// moved out of scope. The controller doesn't watch those Secrets anymore, so their replicas are
+/// found by their labels. Finalizers of earlier versions are released on the way.
+pub async fn sweep(config: &RunoConfig) -> bool {
+    let client = K8s::get_client().await;
+    let params = ListParams::default()
+        .labels(&get_replica_of_label())
+        .limit(ONE_SHOT_PAGE_SIZE);
+    let mut sources = BTreeMap::new();
+    let mut swept = true;
+    for secrets in namespaces::apis::<Secret>(&client, &config.namespace_scope()) {
+        let replicas = match k8s::list_paged(&secrets, params.clone()).await {
+            Ok(replicas) => replicas,
+            Err(e) => {
+                error!("Can't list replicas: {:?}", e);
+                swept = false;
+                continue;
+            }
+        };
+        for replica in replicas {
+            let source = match replica.annotations().get(&get_replica_source_annotation()) {
+                Some(source) => source.clone(),
+                None => continue,
+            };
+            let known = sources.contains_key(&source);
+            let obj = match lookup_source(&source, &mut sources, &client, config).await {
+                Ok(obj) => obj,
+                Err(e) => {
+                    error!("Can't get {:?}: {:?}", source, e);
+                    swept = false;
+                    continue;
+                }
+            };
+            if let Some(obj) = obj.as_ref().filter(|_| !known) {
+                swept &= release_finalizer(obj, &config.k8s).await
+            }
+            if is_orphan(&replica, obj.as_ref()) {
+                swept &= delete_replica(&replica, &client, &config.k8s).await
+            }
+        }
+    }
+    swept
+}
+
+/// Sweep orphaned replicas in the interval managed Secrets are reconciled in
+pub async fn sweep_periodically(config: Arc<RunoConfig>) {
+    loop {
+        sweep(&config).await;
+        tokio::time::sleep(Duration::from_secs(config.requeue_duration)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        allowed_targets, build_replica, is_current, is_orphan, is_replica_of, needs_recreation,
+        parse_target, ReplicationTarget,
+    };
+    use crate::config::{NamespaceScope, RunoConfig};
+    use crate::k8s::K8s;
+    use k8s_openapi::api::core::v1::Secret;
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, Time};
+    use k8s_openapi::ByteString;
+    use kube::ResourceExt;
+    use rstest::rstest;
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    fn build_secret(namespace: &str, name: &str) -> Arc<Secret> {
+        Arc::new(Secret {
+            metadata: ObjectMeta {
+                name: Some(name.to_string()),
+                namespace: Some(namespace.to_string()),
+                labels: Some(BTreeMap::from([(
+                    "v1.secret.runo.rocks/managed".to_string(),
+                    "true".to_string(),
+                )])),
+                annotations: Some(BTreeMap::from([(
+                    "v1.secret.runo.rocks/generate-0".to_string(),
+                    "token".to_string(),
+                )])),
+                uid: Some(format!("uid-{}-{}", namespace, name)),
+                ..ObjectMeta::default()
+            },
+            type_: Some("Opaque".to_string()),
+            ..Secret::default()
+        })
+    }
+
+    #[rstest]
+    #[case("monitoring", ReplicationTarget::Namespaces(vec!["monitoring".to_string()]))]
+    #[case("monitoring, apps", ReplicationTarget::Namespaces(vec!["monitoring".to_string(), "apps".to_string()]))]
+    #[case("selector:team=observability", ReplicationTarget::Selector("team=observability".to_string()))]
+    #[case("selector: env in (prod, staging)", ReplicationTarget::Selector("env in (prod, staging)".to_string()))]
+    #[case("selector:!legacy", ReplicationTarget::Selector("!legacy".to_string()))]
+    #[case("selector:team", ReplicationTarget::Selector("team".to_string()))]
+    fn test_parse_target(#[case] value: &str, #[case] expected: ReplicationTarget) {
+        assert_eq!(parse_target(value).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case("team=observability")]
+    #[case("monitoring,!legacy")]
+    #[case("Monitoring")]
+    #[case("-monitoring")]
+    #[case("monitoring,")]
+    #[case("selector: ")]
+    fn test_parse_invalid_target(#[case] value: &str) {
+        assert!(parse_target(value).is_err());
+    }
+
+    #[rstest]
+    fn test_build_replica() {
+        let secret = build_secret("default", "scrape-token");
+        let data = BTreeMap::from([("token".to_string(), ByteString("value".as_bytes().to_vec()))]);
+        let replica = build_replica(&secret, "monitoring", Some(data.clone()));
+        assert_eq!(replica.name_any(), "scrape-token");
+        assert_eq!(replica.namespace(), Some("monitoring".to_string()));
+        assert_eq!(replica.data, Some(data));
+        assert_eq!(replica.type_, Some("Opaque".to_string()));
+        // Replicas are updated in place, so mounted replicas get rotated values
+        assert_eq!(replica.immutable, None);
+        assert_eq!(
+            replica.labels().get("v1.secret.runo.rocks/replica-of"),
+            Some(&"default.scrape-token".to_string())
+        );
+        assert_eq!(
+            replica.labels().get("v1.secret.runo.rocks/replica-of-uid"),
+            Some(&"uid-default-scrape-token".to_string())
+        );
+        // Replicas aren't managed, so they are never regenerated
+        assert!(!replica
+            .labels()
+            .contains_key("v1.secret.runo.rocks/managed"));
+        assert!(!replica
+            .annotations()
+            .contains_key("v1.secret.runo.rocks/generate-0"));
+        assert!(is_replica_of(&replica, &secret));
+    }
+
+    #[rstest]
+    #[case(vec!["monitoring", "apps"], vec!["monitoring"], vec![], vec!["monitoring"])]
+    #[case(vec!["kube-system"], vec!["monitoring"], vec![], vec![])]
+    #[case(vec!["default", "monitoring"], vec!["default", "monitoring"], vec![], vec!["monitoring"])]
+    #[case(vec!["monitoring"], vec![], vec![], vec![])]
+    #[case(vec!["apps", "monitoring"], vec!["apps", "monitoring"], vec!["apps"], vec!["monitoring"])]
+    fn test_allowed_targets(
+        #[case] namespaces: Vec<&str>,
+        #[case] allowed: Vec<&str>,
+        #[case] denied: Vec<&str>,
+        #[case] expected: Vec<&str>,
+    ) {
+        let config = RunoConfig::build(K8s { dry_run: false }, 10)
+            .with_allowed_replication_namespaces(allowed.into_iter().map(String::from).collect())
+            .with_namespace_scope(NamespaceScope {
+                denied: denied.into_iter().map(String::from).collect(),
+                ..NamespaceScope::default()
+            });
+        assert_eq!(
+            allowed_targets(
+                namespaces.into_iter().map(String::from).collect(),
+                "default",
+                &config
+            ),
+            expected
+        );
+    }
+
+    #[rstest]
+    fn test_is_current() {
+        let secret = build_secret("default", "scrape-token");
+        let data = BTreeMap::from([("token".to_string(), ByteString("value".as_bytes().to_vec()))]);
+        let replica = build_replica(&secret, "monitoring", Some(data.clone()));
+        assert!(is_current(&replica, &replica));
+        assert!(!needs_recreation(&replica, &replica));
+        let mut immutable = replica.clone();
+        immutable.immutable = Some(true);
+        assert!(!is_current(&immutable, &replica));
+        assert!(needs_recreation(&immutable, &replica));
+        let rotated = build_replica(&secret, "monitoring", Some(BTreeMap::new()));
+        assert!(!is_current(&replica, &rotated));
+        assert!(!needs_recreation(&replica, &rotated));
+        let mut unlabelled = replica.clone();
+        unlabelled
+            .labels_mut()
+            .remove("v1.secret.runo.rocks/replica-of-uid");
+        assert!(!is_current(&unlabelled, &replica));
+    }
+
+    #[rstest]
+    fn test_is_orphan() {
+        let mut secret = build_secret("default", "scrape-token");
+        Arc::make_mut(&mut secret).annotations_mut().insert(
+            "v1.secret.runo.rocks/replicate-to".to_string(),
+            "monitoring".to_string(),
+        );
+        let replica = build_replica(&secret, "monitoring", None);
+        assert!(!is_orphan(&replica, Some(&secret)));
+        assert!(is_orphan(&replica, None));
+
+        let mut recreated = (*secret).clone();
+        recreated.metadata.uid = Some("other".to_string());
+        assert!(is_orphan(&replica, Some(&Arc::new(recreated))));
+
+        let mut unmanaged = (*secret).clone();
+        unmanaged.labels_mut().clear();
+        assert!(is_orphan(&replica, Some(&Arc::new(unmanaged))));
+
+        let mut not_replicated = (*secret).clone();
+        not_replicated
+            .annotations_mut()
+            .remove("v1.secret.runo.rocks/replicate-to");
+        assert!(is_orphan(&replica, Some(&Arc::new(not_replicated))));
+
+        let mut deleted = (*secret).clone();
+        deleted.metadata.deletion_timestamp = Some(Time(k8s_openapi::jiff::Timestamp::UNIX_EPOCH));
+        assert!(is_orphan(&replica, Some(&Arc::new(deleted))));
+
+        // Replicas of earlier versions have no uid label
+        let mut legacy = replica.clone();
+        legacy
+            .labels_mut()
+            .remove("v1.secret.runo.rocks/replica-of-uid");
+        assert!(!is_orphan(&legacy, Some(&secret)));
+    }
+
+    #[rstest]
+    fn test_is_not_replica_of_other_secret() {
+        let secret = build_secret("default", "scrape-token");
+        let other = build_secret("other", "scrape-token");
+        let replica = build_replica(&secret, "monitoring", None);
+        assert!(!is_replica_of(&replica, &other));
+        assert!(!is_replica_of(
+            &build_secret("monitoring", "scrape-token"),
+            &secret
+        ));
+    }
+}
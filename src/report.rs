@@ -0,0 +1,232 @@
+use crate::annotations::{generate, id_iter, needs_clone, needs_generation};
+use crate::errors::FollowUpFailed;
+use crate::resource::GeneratedResource;
+use crate::secrets::pending_key;
+use crate::settings::Settings;
+use kube::ResourceExt;
+use std::fmt;
+use std::sync::Arc;
+
+/// Fields changed by the update of a single resource
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Outcome {
+    pub(crate) resource: String,
+    pub(crate) generated: Vec<String>,
+    pub(crate) renewed: Vec<String>,
+    pub(crate) cloned: Vec<String>,
+    pub(crate) failed: bool,
+    pub(crate) failed_follow_ups: Vec<String>,
+}
+
+impl Outcome {
+    pub fn build<K: GeneratedResource>(obj: &Arc<K>) -> Outcome {
+        Outcome {
+            resource: format!(
+                "{} {}/{}",
+                K::kind(&()),
+                obj.namespace().unwrap_or_default(),
+                obj.name_any()
+            ),
+            ..Outcome::default()
+        }
+    }
+
+    /// Collect the fields whose values were changed by the update of the resource. `obj` is the
+    /// resource the update was based on and `applied` the one returned by the API server.
+    pub fn applied<K: GeneratedResource>(
+        obj: &Arc<K>,
+        applied: &K,
+        settings: &Settings,
+    ) -> Outcome {
+        let mut outcome = Outcome::build(obj);
+        let before = obj.values().unwrap_or_default();
+        let after = applied.values().unwrap_or_default();
+        for id in id_iter(obj) {
+            let field = generate(obj, &id).get_value();
+            let changed = [field.clone(), pending_key(&field)]
+                .iter()
+                .any(|key| before.get(key) != after.get(key));
+            if !changed {
+                continue;
+            }
+            if needs_clone(obj, &id) {
+                outcome.cloned.push(field);
+            } else if needs_generation(obj, &id, settings) {
+                outcome.generated.push(field);
+            } else {
+                outcome.renewed.push(field);
+            }
+        }
+        outcome
+    }
+
+    pub fn failed(mut self) -> Outcome {
+        self.failed = true;
+        self
+    }
+
+    /// Record a failed step which follows the update, like restarts or replication
+    pub fn follow_up(&mut self, result: Result<(), FollowUpFailed>) {
+        if let Err(e) = result {
+            self.failed_follow_ups.push(e.step);
+        }
+    }
+
+    pub fn is_failed(&self) -> bool {
+        self.failed || !self.failed_follow_ups.is_empty()
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: generated [{}], renewed [{}], cloned [{}]",
+            self.resource,
+            self.generated.join(", "),
+            self.renewed.join(", "),
+            self.cloned.join(", "),
+        )?;
+        if self.failed {
+            write!(f, ", failed")?;
+        }
+        match self.failed_follow_ups.is_empty() {
+            true => Ok(()),
+            false => write!(f, ", failed [{}]", self.failed_follow_ups.join(", ")),
+        }
+    }
+}
+
+/// Outcomes of a one-shot run
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Summary {
+    pub(crate) outcomes: Vec<Outcome>,
+    pub(crate) list_failures: usize,
+}
+
+impl Summary {
+    pub fn failures(&self) -> usize {
+        self.outcomes.iter().filter(|o| o.is_failed()).count() + self.list_failures
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for outcome in &self.outcomes {
+            writeln!(f, "{}", outcome)?;
+        }
+        write!(
+            f,
+            "{} resources reconciled, {} failed",
+            self.outcomes.len(),
+            self.failures()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Outcome, Summary};
+    use crate::errors::FollowUpFailed;
+    use crate::settings::Settings;
+    use crate::test_utils::build_secret_with_data;
+    use rstest::rstest;
+
+    fn fields() -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("v1.secret.runo.rocks/generate-0", "username"),
+            ("v1.secret.runo.rocks/generate-1", "password"),
+            ("v1.secret.runo.rocks/renewal-1", "true"),
+            ("v1.secret.runo.rocks/generate-2", "copy"),
+            ("v1.secret.runo.rocks/clone-from-2", "other/token"),
+            ("v1.secret.runo.rocks/generate-3", "salt"),
+        ]
+    }
+
+    #[rstest]
+    fn applied_outcome_lists_changed_fields() {
+        let secret = build_secret_with_data(fields(), vec![("password", "old"), ("salt", "salt")]);
+        let applied = build_secret_with_data(
+            fields(),
+            vec![
+                ("username", "new"),
+                ("password", "new"),
+                ("copy", "new"),
+                ("salt", "salt"),
+            ],
+        );
+        let outcome = Outcome::applied(&secret, &applied, &Settings::default());
+        assert_eq!(outcome.resource, "Secret apps/db");
+        assert_eq!(outcome.generated, vec!["username"]);
+        assert_eq!(outcome.renewed, vec!["password"]);
+        assert_eq!(outcome.cloned, vec!["copy"]);
+        assert!(!outcome.is_failed());
+    }
+
+    #[rstest]
+    fn applied_outcome_lists_staged_fields_as_renewed() {
+        let secret = build_secret_with_data(fields(), vec![("password", "old")]);
+        let applied = build_secret_with_data(
+            fields(),
+            vec![("password", "old"), ("password.pending", "new")],
+        );
+        let outcome = Outcome::applied(&secret, &applied, &Settings::default());
+        assert_eq!(outcome.renewed, vec!["password"]);
+    }
+
+    #[rstest]
+    fn applied_outcome_is_empty_without_changes() {
+        let secret = build_secret_with_data(fields(), vec![("password", "old")]);
+        let outcome = Outcome::applied(&secret, &secret, &Settings::default());
+        assert_eq!(outcome, Outcome::build(&secret));
+    }
+
+    #[rstest]
+    fn summary_counts_failures() {
+        let summary = Summary {
+            outcomes: vec![
+                Outcome {
+                    resource: "Secret default/a".to_string(),
+                    generated: vec!["password".to_string()],
+                    ..Outcome::default()
+                },
+                Outcome {
+                    resource: "Secret default/b".to_string(),
+                    ..Outcome::default()
+                }
+                .failed(),
+            ],
+            list_failures: 1,
+        };
+        assert_eq!(summary.failures(), 2);
+        assert_eq!(
+            summary.to_string(),
+            "Secret default/a: generated [password], renewed [], cloned []\n\
+             Secret default/b: generated [], renewed [], cloned [], failed\n\
+             2 resources reconciled, 2 failed"
+        )
+    }
+
+    #[rstest]
+    fn summary_counts_failed_follow_ups() {
+        let mut outcome = Outcome {
+            resource: "Secret default/a".to_string(),
+            renewed: vec!["password".to_string()],
+            ..Outcome::default()
+        };
+        outcome.follow_up(Ok(()));
+        outcome.follow_up(Err(FollowUpFailed {
+            step: "replication".to_string(),
+        }));
+        let summary = Summary {
+            outcomes: vec![outcome],
+            list_failures: 0,
+        };
+        assert_eq!(summary.failures(), 1);
+        assert_eq!(
+            summary.to_string(),
+            "Secret default/a: generated [], renewed [password], cloned [], failed [replication]\n\
+             1 resources reconciled, 1 failed"
+        )
+    }
+}
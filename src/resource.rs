@@ -0,0 +1,112 @@
+use k8s_openapi::api::core::v1::{ConfigMap, Secret};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::{ByteString, NamespaceResourceScope};
+use kube::Resource;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+/// A namespaced resource whose values are generated by runo. Values are always handled as
+/// bytes, so generation, history and cloning work the same for all resources.
+pub trait GeneratedResource:
+    Resource<DynamicType = (), Scope = NamespaceResourceScope>
+    + Clone
+    + Debug
+    + DeserializeOwned
+    + Serialize
+    + Send
+    + Sync
+    + 'static
+{
+    fn values(&self) -> Option<BTreeMap<String, ByteString>>;
+
+    /// Build the resource runo applies with its values and annotations
+    fn build(values: BTreeMap<String, ByteString>, annotations: BTreeMap<String, String>) -> Self;
+}
+
+impl GeneratedResource for Secret {
+    fn values(&self) -> Option<BTreeMap<String, ByteString>> {
+        self.data.clone()
+    }
+
+    fn build(values: BTreeMap<String, ByteString>, annotations: BTreeMap<String, String>) -> Self {
+        Secret {
+            metadata: ObjectMeta {
+                annotations: Some(annotations),
+                ..ObjectMeta::default()
+            },
+            data: Some(values),
+            ..Secret::default()
+        }
+    }
+}
+
+impl GeneratedResource for ConfigMap {
+    fn values(&self) -> Option<BTreeMap<String, ByteString>> {
+        self.data.as_ref().map(|data| {
+            data.iter()
+                .map(|(k, v)| (k.clone(), ByteString(v.as_bytes().to_vec())))
+                .collect()
+        })
+    }
+
+    fn build(values: BTreeMap<String, ByteString>, annotations: BTreeMap<String, String>) -> Self {
+        ConfigMap {
+            metadata: ObjectMeta {
+                annotations: Some(annotations),
+                ..ObjectMeta::default()
+            },
+            data: Some(
+                values
+                    .into_iter()
+                    .map(|(k, v)| (k, String::from_utf8_lossy(&v.0).to_string()))
+                    .collect(),
+            ),
+            ..ConfigMap::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GeneratedResource;
+    use k8s_openapi::api::core::v1::{ConfigMap, Secret};
+    use k8s_openapi::ByteString;
+    use kube::ResourceExt;
+    use std::collections::BTreeMap;
+
+    fn values() -> BTreeMap<String, ByteString> {
+        BTreeMap::from([("salt".to_string(), ByteString("abcd".as_bytes().to_vec()))])
+    }
+
+    fn annotations() -> BTreeMap<String, String> {
+        BTreeMap::from([(
+            "v1.secret.runo.rocks/generate-0".to_string(),
+            "salt".to_string(),
+        )])
+    }
+
+    #[test]
+    fn build_secret() {
+        let secret = Secret::build(values(), annotations());
+        assert_eq!(secret.values(), Some(values()));
+        assert_eq!(secret.annotations(), &annotations());
+    }
+
+    #[test]
+    fn build_config_map() {
+        let config_map = ConfigMap::build(values(), annotations());
+        assert_eq!(
+            config_map.data.as_ref().unwrap().get("salt"),
+            Some(&"abcd".to_string())
+        );
+        assert_eq!(config_map.values(), Some(values()));
+        assert_eq!(config_map.annotations(), &annotations());
+    }
+
+    #[test]
+    fn config_map_without_data() {
+        assert_eq!(ConfigMap::default().values(), None);
+    }
+}
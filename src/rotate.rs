@@ -0,0 +1,230 @@
+use crate::annotations::{
+    generate, generated_at, has_pending, id_iter, is_paused, is_rotate_requested, needs_clone,
+    V1Annotation,
+};
+use crate::errors::RotationFailed;
+use crate::k8s::K8s;
+use crate::labels;
+use chrono::DateTime;
+use k8s_openapi::api::core::v1::Secret;
+use kube::api::Patch;
+use kube::runtime::wait::await_condition;
+use kube::{Api, ResourceExt};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn failed(reason: impl Into<String>) -> RotationFailed {
+    RotationFailed {
+        reason: reason.into(),
+    }
+}
+
+/// Parse `<namespace>/<secret>`
+pub fn parse_target(target: &str) -> Result<(String, String), RotationFailed> {
+    match target.split_once('/') {
+        Some((namespace, name)) if !namespace.is_empty() && !name.is_empty() => {
+            Ok((namespace.to_string(), name.to_string()))
+        }
+        _ => Err(failed(format!(
+            "{:?} isn't a secret in the form <namespace>/<secret>",
+            target
+        ))),
+    }
+}
+
+fn can_rotate(obj: &Arc<Secret>, id: &str) -> Result<(), RotationFailed> {
+    if !generate(obj, id).exists() {
+        return Err(failed(format!("there is no field with id {:?}", id)));
+    }
+    if needs_clone(obj, id) {
+        return Err(failed(format!(
+            "field with id {:?} is cloned and can't be rotated",
+            id
+        )));
+    }
+    if is_paused(obj, id) {
+        return Err(failed(format!("field with id {:?} is paused", id)));
+    }
+    Ok(())
+}
+
+/// Ids of the fields to rotate. With `all`, every field which can be rotated is selected.
+pub fn select_ids(
+    obj: &Arc<Secret>,
+    id: Option<&str>,
+    all: bool,
+) -> Result<Vec<String>, RotationFailed> {
+    if !labels::managed_by_us(obj) {
+        return Err(failed(format!("{} isn't managed by runo", obj.name_any())));
+    }
+    let ids = match (id, all) {
+        (Some(id), _) => {
+            can_rotate(obj, id)?;
+            vec![id.to_string()]
+        }
+        (None, true) => id_iter(obj)
+            .into_iter()
+            .filter(|id| can_rotate(obj, id).is_ok())
+            .collect(),
+        (None, false) => return Err(failed("either --id or --all is required")),
+    };
+    match ids.is_empty() {
+        true => Err(failed(format!(
+            "{} has no fields which can be rotated",
+            obj.name_any()
+        ))),
+        false => Ok(ids),
+    }
+}
+
+/// The controller resets the rotate request as soon as it renewed the field
+pub fn is_rotated(obj: &Arc<Secret>, ids: &[String]) -> bool {
+    ids.iter().all(|id| !is_rotate_requested(obj, id))
+}
+
+fn describe(obj: &Arc<Secret>, id: &str) -> String {
+    let field_name = generate(obj, id).get_value();
+    if has_pending(obj, id) {
+        return format!(
+            "{} (id {}): new value is pending until it's promoted",
+            field_name, id
+        );
+    }
+    let generated_at = generated_at(obj, id).get_value();
+    let generated_at = generated_at
+        .parse::<i64>()
+        .ok()
+        .and_then(|t| DateTime::from_timestamp(t, 0))
+        .map(|t| t.to_rfc3339())
+        .unwrap_or(generated_at);
+    format!("{} (id {}): generated at {}", field_name, id, generated_at)
+}
+
+/// Request the renewal of fields of a Secret and wait until the controller has renewed them
+pub async fn rotate(
+    target: &str,
+    id: Option<&str>,
+    all: bool,
+    timeout: Duration,
+) -> Result<Vec<String>, RotationFailed> {
+    let (namespace, name) = parse_target(target)?;
+    let secrets: Api<Secret> = Api::namespaced(K8s::get_client().await, &namespace);
+    let obj = secrets
+        .get(&name)
+        .await
+        .map(Arc::new)
+        .map_err(|e| failed(format!("can't read {}: {}", target, e)))?;
+    let ids = select_ids(&obj, id, all)?;
+    let annotations = ids
+        .iter()
+        .map(|id| (V1Annotation::RotateRequested.value(id), "true".to_string()))
+        .collect::<BTreeMap<String, String>>();
+    let patch = serde_json::json!({
+        "metadata": {
+            "annotations": annotations
+        }
+    });
+    secrets
+        .patch(
+            &name,
+            &K8s::build(false).get_merge_patch_params(),
+            &Patch::Merge(patch),
+        )
+        .await
+        .map_err(|e| failed(format!("can't request renewal of {}: {}", target, e)))?;
+    let rotated = await_condition(secrets, &name, |obj: Option<&Secret>| {
+        obj.is_some_and(|o| is_rotated(&Arc::new(o.clone()), &ids))
+    });
+    match tokio::time::timeout(timeout, rotated).await {
+        Ok(Ok(Some(obj))) => {
+            let obj = Arc::new(obj);
+            Ok(ids.iter().map(|id| describe(&obj, id)).collect())
+        }
+        Ok(Ok(None)) => Err(failed(format!("{} was deleted", target))),
+        Ok(Err(e)) => Err(failed(format!("can't watch {}: {}", target, e))),
+        Err(_) => Err(failed(format!(
+            "the controller didn't renew {} within {:?}",
+            target, timeout
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_rotated, parse_target, select_ids};
+    use crate::test_utils::build_secret;
+    use k8s_openapi::api::core::v1::Secret;
+    use rstest::rstest;
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    fn build_fields() -> Arc<Secret> {
+        let mut secret = build_secret(vec![
+            ("v1.secret.runo.rocks/generate-0", "username"),
+            ("v1.secret.runo.rocks/generate-1", "password"),
+            ("v1.secret.runo.rocks/generate-2", "paused"),
+            ("v1.secret.runo.rocks/pause-2", "true"),
+            ("v1.secret.runo.rocks/generate-3", "copy"),
+            ("v1.secret.runo.rocks/clone-from-3", "1"),
+        ])
+        .as_ref()
+        .clone();
+        secret.metadata.labels = Some(BTreeMap::from([(
+            "v1.secret.runo.rocks/managed".to_string(),
+            "true".to_string(),
+        )]));
+        Arc::new(secret)
+    }
+
+    #[rstest]
+    #[case("apps/db", Some(("apps", "db")))]
+    #[case("db", None)]
+    #[case("/db", None)]
+    #[case("apps/", None)]
+    fn test_parse_target(#[case] target: &str, #[case] expected: Option<(&str, &str)>) {
+        assert_eq!(
+            parse_target(target).ok(),
+            expected.map(|(n, s)| (n.to_string(), s.to_string()))
+        );
+    }
+
+    #[rstest]
+    #[case(Some("1"), false, Some(vec!["1"]))]
+    #[case(None, true, Some(vec!["0", "1"]))]
+    #[case(None, false, None)]
+    #[case(Some("2"), false, None)]
+    #[case(Some("3"), false, None)]
+    #[case(Some("9"), false, None)]
+    fn test_select_ids(
+        #[case] id: Option<&str>,
+        #[case] all: bool,
+        #[case] expected: Option<Vec<&str>>,
+    ) {
+        assert_eq!(
+            select_ids(&build_fields(), id, all).ok(),
+            expected.map(|ids| ids.iter().map(|i| i.to_string()).collect())
+        );
+    }
+
+    #[rstest]
+    fn test_select_ids_unmanaged() {
+        let mut secret = build_fields().as_ref().clone();
+        secret.metadata.labels = None;
+        assert!(select_ids(&Arc::new(secret), Some("0"), false).is_err());
+    }
+
+    #[rstest]
+    #[case(vec![], true)]
+    #[case(vec![("v1.secret.runo.rocks/rotate-requested-0", "false")], true)]
+    #[case(vec![("v1.secret.runo.rocks/rotate-requested-0", "true")], false)]
+    #[case(vec![("v1.secret.runo.rocks/rotate-requested-1", "true")], false)]
+    #[case(vec![("v1.secret.runo.rocks/renewal-0", "true")], true)]
+    fn test_is_rotated(#[case] annotations: Vec<(&str, &str)>, #[case] expected: bool) {
+        let secret = build_secret(annotations);
+        assert_eq!(
+            is_rotated(&secret, &["0".to_string(), "1".to_string()]),
+            expected
+        );
+    }
+}
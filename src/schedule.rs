@@ -0,0 +1,961 @@
+use crate::annotations::{
+    generated_at, grace_period, has_cron, id_iter, is_paused, max_age, pending_since, renewal_cron,
+    renewal_jitter, renewal_timezone, V1Annotation,
+};
+use crate::errors::{InvalidCronSpec, InvalidDuration, InvalidTimezone};
+use crate::resource::GeneratedResource;
+use crate::settings::Settings;
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use cron::Schedule;
+use kube::ResourceExt;
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error, info};
+
+/// Parse a Kubernetes cron spec (5 fields, Sunday = 0) into a schedule of the cron crate
+/// (seconds field, Sunday = 1).
+pub fn parse_cron(spec: &str) -> Result<Schedule, InvalidCronSpec> {
+    let spec = spec.trim();
+    let expression = match spec {
+        "@annually" => "@yearly".to_string(),
+        "@midnight" => "@daily".to_string(),
+        s if s.starts_with('@') => s.to_string(),
+        s => {
+            let fields: Vec<&str> = s.split_whitespace().collect();
+            if fields.len() != 5 {
+                return Err(InvalidCronSpec {
+                    spec: spec.to_string(),
+                });
+            }
+            format!(
+                "0 {} {} {} {} {}",
+                fields[0],
+                fields[1],
+                fields[2],
+                fields[3],
+                convert_day_of_week(fields[4])
+            )
+        }
+    };
+    Schedule::from_str(expression.as_str()).map_err(|_| InvalidCronSpec {
+        spec: spec.to_string(),
+    })
+}
+
+fn convert_day_of_week(field: &str) -> String {
+    field
+        .split(',')
+        .map(|item| {
+            let (range, step) = match item.split_once('/') {
+                Some((r, s)) => (r, Some(s)),
+                None => (item, None),
+            };
+            // Sunday is 7 at the end of a range, it's moved to a separate item as 1
+            let (converted, sunday) = match range.split_once('-') {
+                Some((start, end)) => match (start.parse::<u8>(), end.parse::<u8>()) {
+                    (Ok(0), Ok(7)) => ("1-7".to_string(), false),
+                    (Ok(s), Ok(7)) => {
+                        let sunday = match step.map(|st| st.parse::<u8>()) {
+                            None => true,
+                            Some(Ok(st)) => st > 0 && (7 - s) % st == 0,
+                            Some(Err(_)) => false,
+                        };
+                        (format!("{}-7", s + 1), sunday)
+                    }
+                    (Ok(s), Ok(e)) => (format!("{}-{}", s % 7 + 1, e % 7 + 1), false),
+                    _ => (range.to_string(), false),
+                },
+                None => match range.parse::<u8>() {
+                    Ok(d) => ((d % 7 + 1).to_string(), false),
+                    Err(_) => (range.to_string(), false),
+                },
+            };
+            let converted = match step {
+                Some(s) => format!("{}/{}", converted, s),
+                None => converted,
+            };
+            match sunday {
+                true => format!("{},1", converted),
+                false => converted,
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Parse a timezone name of the IANA time zone database like `Europe/Berlin`
+pub fn parse_timezone(value: &str) -> Result<Tz, InvalidTimezone> {
+    Tz::from_str(value.trim()).map_err(|_| InvalidTimezone {
+        value: value.to_string(),
+    })
+}
+
+/// Timezone the renewal cron of a field is evaluated in, UTC if it isn't set
+fn renewal_tz(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    settings: &Settings,
+) -> Result<Tz, InvalidTimezone> {
+    let timezone = renewal_timezone(obj, id, settings);
+    match timezone.exists() {
+        true => parse_timezone(timezone.get_value().as_str()),
+        false => Ok(Tz::UTC),
+    }
+}
+
+/// Check that the renewal cron and timezone of a field are valid before they are used
+pub fn validate_renewal(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    settings: &Settings,
+) -> Result<(), String> {
+    parse_cron(renewal_cron(obj, id, settings).get_value().as_str()).map_err(|e| e.to_string())?;
+    renewal_tz(obj, id, settings).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Parse a duration like `90s`, `15m`, `720h`, `30d`, `2w` or a combination like `1d12h`
+pub fn parse_duration(value: &str) -> Result<Duration, InvalidDuration> {
+    let invalid = || InvalidDuration {
+        value: value.to_string(),
+    };
+    let mut seconds: u64 = 0;
+    let mut number = String::new();
+    for c in value.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let factor = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+        let n: u64 = number.parse().map_err(|_| invalid())?;
+        seconds = n
+            .checked_mul(factor)
+            .and_then(|s| s.checked_add(seconds))
+            .ok_or_else(invalid)?;
+        number.clear();
+    }
+    if !number.is_empty() || seconds == 0 {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(seconds))
+}
+
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    value
+        .parse::<i64>()
+        .ok()
+        .and_then(|ts| Utc.timestamp_opt(ts, 0).single())
+}
+
+fn last_generation(obj: &Arc<impl GeneratedResource>, id: &str) -> Option<DateTime<Utc>> {
+    let generated_at = generated_at(obj, id);
+    if !generated_at.exists() {
+        return None;
+    }
+    parse_timestamp(generated_at.get_value().as_str())
+}
+
+/// Next time the renewal cron of a field fires after the given point in time
+pub fn next_renewal(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    after: &DateTime<Utc>,
+    settings: &Settings,
+) -> Option<DateTime<Utc>> {
+    if !has_cron(obj, id, settings) {
+        return None;
+    }
+    let schedule = parse_cron(renewal_cron(obj, id, settings).get_value().as_str())
+        .map_err(|e| e.to_string())
+        .and_then(|s| {
+            renewal_tz(obj, id, settings)
+                .map(|tz| (s, tz))
+                .map_err(|e| e.to_string())
+        });
+    match schedule {
+        Ok((schedule, tz)) => schedule
+            .after(&after.with_timezone(&tz))
+            .next()
+            .map(|next| next.with_timezone(&Utc)),
+        Err(e) => {
+            error!("{} for {:?} and id {}", e, obj.name_any(), id);
+            None
+        }
+    }
+}
+
+/// A renewal is due if the cron fired at least once since the field was generated
+pub fn renewal_due(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    now: &DateTime<Utc>,
+    settings: &Settings,
+) -> bool {
+    match last_generation(obj, id) {
+        Some(last) => match next_renewal(obj, id, &last, settings) {
+            Some(next) => next <= *now,
+            None => false,
+        },
+        None => false,
+    }
+}
+
+/// Offset within the jitter window of a field. It's derived from namespace, name and id of the
+/// field, so it stays the same across reconciliations but differs between Secrets.
+pub fn jitter_offset(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    settings: &Settings,
+) -> Option<chrono::Duration> {
+    let jitter = renewal_jitter(obj, id, settings);
+    if !jitter.exists() {
+        return None;
+    }
+    let window = match parse_duration(jitter.get_value().as_str()) {
+        Ok(d) => d,
+        Err(e) => {
+            error!("{} for {:?} and id {}", e, obj.name_any(), id);
+            return None;
+        }
+    };
+    let mut hasher = Sha256::new();
+    hasher.update(format!(
+        "{}/{}/{}",
+        obj.namespace().unwrap_or_default(),
+        obj.name_any(),
+        id
+    ));
+    let hash = hasher.finalize();
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&hash[..8]);
+    let offset = u64::from_be_bytes(bytes) % window.as_secs();
+    chrono::Duration::try_seconds(offset as i64)
+}
+
+/// Point in time until a renewal of a field triggered by its cron is deferred by the jitter
+fn renewal_deferred_until(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    settings: &Settings,
+) -> Option<DateTime<Utc>> {
+    let offset = jitter_offset(obj, id, settings)?;
+    let last = last_generation(obj, id)?;
+    next_renewal(obj, id, &last, settings).and_then(|next| next.checked_add_signed(offset))
+}
+
+/// A renewal is deferred if the cron of the field fired, but the jitter offset isn't over yet
+pub fn renewal_deferred(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    now: &DateTime<Utc>,
+    settings: &Settings,
+) -> bool {
+    renewal_due(obj, id, now, settings)
+        && renewal_deferred_until(obj, id, settings).is_some_and(|t| t > *now)
+}
+
+/// Time until the deferred renewal of any field that isn't paused is carried out
+pub fn until_deferred_renewal(
+    obj: &Arc<impl GeneratedResource>,
+    now: &DateTime<Utc>,
+    settings: &Settings,
+) -> Option<Duration> {
+    let next = id_iter(obj)
+        .iter()
+        .filter(|id| !is_paused(obj, id))
+        .filter(|id| renewal_deferred(obj, id, now, settings))
+        .filter_map(|id| renewal_deferred_until(obj, id, settings))
+        .min()?;
+    (next - *now).to_std().ok()
+}
+
+/// Point in time when the value of a field exceeds its max age, delayed by the jitter offset
+pub fn max_age_expiry(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    settings: &Settings,
+) -> Option<DateTime<Utc>> {
+    let max_age = max_age(obj, id, settings);
+    if !max_age.exists() {
+        return None;
+    }
+    let max_age = match parse_duration(max_age.get_value().as_str()) {
+        Ok(d) => d,
+        Err(e) => {
+            error!("{} for {:?} and id {}", e, obj.name_any(), id);
+            return None;
+        }
+    };
+    let last = last_generation(obj, id)?;
+    let offset = jitter_offset(obj, id, settings).unwrap_or_default();
+    chrono::Duration::from_std(max_age)
+        .ok()
+        .and_then(|d| last.checked_add_signed(d + offset))
+}
+
+pub fn max_age_exceeded(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    now: &DateTime<Utc>,
+    settings: &Settings,
+) -> bool {
+    match max_age_expiry(obj, id, settings) {
+        Some(expiry) => expiry <= *now,
+        None => false,
+    }
+}
+
+/// Time until the value of any field that isn't paused exceeds its max age
+pub fn until_max_age_expiry(
+    obj: &Arc<impl GeneratedResource>,
+    now: &DateTime<Utc>,
+    settings: &Settings,
+) -> Option<Duration> {
+    let next = id_iter(obj)
+        .iter()
+        .filter(|id| !is_paused(obj, id))
+        .filter_map(|id| max_age_expiry(obj, id, settings))
+        .filter(|expiry| expiry > now)
+        .min()?;
+    (next - *now).to_std().ok()
+}
+
+/// Point in time when the grace period of a pending value is over
+pub fn grace_period_expiry(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    settings: &Settings,
+) -> Option<DateTime<Utc>> {
+    let grace_period = grace_period(obj, id, settings);
+    if !grace_period.exists() {
+        return None;
+    }
+    let grace_period = match parse_duration(grace_period.get_value().as_str()) {
+        Ok(d) => d,
+        Err(e) => {
+            error!("{} for {:?} and id {}", e, obj.name_any(), id);
+            return None;
+        }
+    };
+    let pending_since = parse_timestamp(pending_since(obj, id).get_value().as_str())?;
+    chrono::Duration::from_std(grace_period)
+        .ok()
+        .and_then(|d| pending_since.checked_add_signed(d))
+}
+
+pub fn grace_period_exceeded(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    now: &DateTime<Utc>,
+    settings: &Settings,
+) -> bool {
+    match grace_period_expiry(obj, id, settings) {
+        Some(expiry) => expiry <= *now,
+        None => false,
+    }
+}
+
+/// Time until the grace period of any pending value that isn't paused is over
+pub fn until_grace_period_expiry(
+    obj: &Arc<impl GeneratedResource>,
+    now: &DateTime<Utc>,
+    settings: &Settings,
+) -> Option<Duration> {
+    let next = id_iter(obj)
+        .iter()
+        .filter(|id| !is_paused(obj, id))
+        .filter_map(|id| grace_period_expiry(obj, id, settings))
+        .filter(|expiry| expiry > now)
+        .min()?;
+    (next - *now).to_std().ok()
+}
+
+/// Mark all fields with a due renewal cron as to be renewed, the same way the renewal CronJob
+/// does by setting the renewal annotation. The returned resource is only held in memory.
+pub fn mark_due_renewals<K: GeneratedResource>(
+    obj: &Arc<K>,
+    now: &DateTime<Utc>,
+    settings: &Settings,
+) -> Arc<K> {
+    let due: Vec<String> = id_iter(obj)
+        .into_iter()
+        .filter(|id| {
+            renewal_due(obj, id, now, settings) && !renewal_deferred(obj, id, now, settings)
+        })
+        .collect();
+    if due.is_empty() {
+        return obj.clone();
+    }
+    let mut resource = obj.as_ref().clone();
+    let annotations = resource.annotations_mut();
+    for id in due {
+        info!("Renewal cron for {:?} and id {} is due", obj.name_any(), id);
+        annotations.insert(V1Annotation::Renewal.value(&id), "true".to_string());
+    }
+    Arc::new(resource)
+}
+
+/// Time until the next renewal cron of any field that isn't paused fires, including the jitter
+/// offset of the field
+pub fn until_next_renewal(
+    obj: &Arc<impl GeneratedResource>,
+    now: &DateTime<Utc>,
+    settings: &Settings,
+) -> Option<Duration> {
+    let next = id_iter(obj)
+        .iter()
+        .filter(|id| !is_paused(obj, id))
+        .filter_map(|id| {
+            let offset = jitter_offset(obj, id, settings).unwrap_or_default();
+            next_renewal(obj, id, now, settings).and_then(|next| next.checked_add_signed(offset))
+        })
+        .min()?;
+    debug!("Next renewal for {:?} at {}", obj.name_any(), next);
+    (next - *now).to_std().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        grace_period_exceeded, jitter_offset, mark_due_renewals, max_age_exceeded, next_renewal,
+        parse_cron, parse_duration, parse_timezone, renewal_deferred, renewal_due,
+        until_deferred_renewal, until_grace_period_expiry, until_max_age_expiry,
+        until_next_renewal, validate_renewal,
+    };
+    use crate::settings::Settings;
+    use crate::test_utils::build_secret_with_annotations;
+    use chrono::{DateTime, TimeZone, Utc};
+    use k8s_openapi::api::core::v1::Secret;
+    use kube::ResourceExt;
+    use rstest::rstest;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn at(ts: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(ts, 0).unwrap()
+    }
+
+    #[rstest]
+    #[case("* * * * *")]
+    #[case("0 * * * *")]
+    #[case("*/5 2 * * 1-5")]
+    #[case("0 0 * * 0")]
+    #[case("0 0 * * 5-7")]
+    #[case("0 0 1 JAN *")]
+    #[case("@daily")]
+    #[case("@midnight")]
+    fn parse_valid_cron(#[case] spec: &str) {
+        assert!(parse_cron(spec).is_ok());
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("true")]
+    #[case("* * * *")]
+    #[case("0 * * * * *")]
+    #[case("61 * * * *")]
+    #[case("@sometimes")]
+    fn parse_invalid_cron(#[case] spec: &str) {
+        assert!(parse_cron(spec).is_err());
+    }
+
+    #[rstest]
+    // 1970-01-04 was a Sunday
+    #[case("0 0 * * 0", 0, 3 * 86400)]
+    #[case("0 0 * * 7", 0, 3 * 86400)]
+    #[case("0 0 * * 1", 0, 4 * 86400)]
+    #[case("0 0 * * 1-7/2", 2 * 86400, 3 * 86400)]
+    #[case("0 0 * * 2-7/2", 2 * 86400, 5 * 86400)]
+    fn parse_cron_day_of_week(#[case] spec: &str, #[case] after: i64, #[case] expected: i64) {
+        let schedule = parse_cron(spec).unwrap();
+        assert_eq!(schedule.after(&at(after)).next().unwrap(), at(expected));
+    }
+
+    #[rstest]
+    #[case(0, 59, false)]
+    #[case(0, 60, true)]
+    #[case(0, 3600, true)]
+    fn renewal_due_after_generation(#[case] generated: i64, #[case] now: i64, #[case] due: bool) {
+        let secret = Arc::new(build_secret_with_annotations(vec![
+            (
+                "v1.secret.runo.rocks/generate-0".to_string(),
+                "username".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/renewal-cron-0".to_string(),
+                "* * * * *".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/generated-at-0".to_string(),
+                generated.to_string(),
+            ),
+        ]));
+        assert_eq!(
+            renewal_due(&secret, "0", &at(now), &Settings::default()),
+            due
+        );
+    }
+
+    #[rstest]
+    fn renewal_not_due_without_generation() {
+        let secret = Arc::new(build_secret_with_annotations(vec![
+            (
+                "v1.secret.runo.rocks/generate-0".to_string(),
+                "username".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/renewal-cron-0".to_string(),
+                "* * * * *".to_string(),
+            ),
+        ]));
+        assert!(!renewal_due(&secret, "0", &at(3600), &Settings::default()));
+    }
+
+    #[rstest]
+    fn mark_due_renewals_sets_renewal_annotation() {
+        let secret = Arc::new(build_secret_with_annotations(vec![
+            (
+                "v1.secret.runo.rocks/generate-0".to_string(),
+                "username".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/renewal-cron-0".to_string(),
+                "* * * * *".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/generated-at-0".to_string(),
+                "0".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/generate-1".to_string(),
+                "password".to_string(),
+            ),
+        ]));
+        let marked = mark_due_renewals(&secret, &at(60), &Settings::default());
+        assert_eq!(
+            marked.annotations().get("v1.secret.runo.rocks/renewal-0"),
+            Some(&"true".to_string())
+        );
+        assert!(!marked
+            .annotations()
+            .contains_key("v1.secret.runo.rocks/renewal-1"));
+    }
+
+    #[rstest]
+    fn until_next_renewal_picks_earliest_field() {
+        let secret = Arc::new(build_secret_with_annotations(vec![
+            (
+                "v1.secret.runo.rocks/generate-0".to_string(),
+                "username".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/renewal-cron-0".to_string(),
+                "0 * * * *".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/generate-1".to_string(),
+                "password".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/renewal-cron-1".to_string(),
+                "*/5 * * * *".to_string(),
+            ),
+        ]));
+        assert_eq!(
+            until_next_renewal(&secret, &at(60), &Settings::default()),
+            Some(Duration::from_secs(240))
+        );
+    }
+
+    #[rstest]
+    fn until_next_renewal_skips_paused_field() {
+        let secret = Arc::new(build_secret_with_annotations(vec![
+            (
+                "v1.secret.runo.rocks/generate-0".to_string(),
+                "username".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/renewal-cron-0".to_string(),
+                "*/5 * * * *".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/pause-0".to_string(),
+                "true".to_string(),
+            ),
+        ]));
+        assert_eq!(
+            until_next_renewal(&secret, &at(60), &Settings::default()),
+            None
+        );
+    }
+
+    fn build_jittered_secret(name: &str, jitter: &str) -> Arc<Secret> {
+        let mut secret = build_secret_with_annotations(vec![
+            (
+                "v1.secret.runo.rocks/generate-0".to_string(),
+                "password".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/renewal-cron-0".to_string(),
+                "0 * * * *".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/generated-at-0".to_string(),
+                "0".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/renewal-jitter-0".to_string(),
+                jitter.to_string(),
+            ),
+        ]);
+        secret.metadata.name = Some(name.to_string());
+        secret.metadata.namespace = Some("default".to_string());
+        Arc::new(secret)
+    }
+
+    #[rstest]
+    fn jitter_offset_is_stable_and_within_window() {
+        let secret = build_jittered_secret("secret", "30m");
+        let offset = jitter_offset(&secret, "0", &Settings::default()).unwrap();
+        assert!(offset >= chrono::Duration::zero());
+        assert!(offset < chrono::Duration::minutes(30));
+        assert_eq!(
+            jitter_offset(&secret, "0", &Settings::default()),
+            Some(offset)
+        );
+    }
+
+    #[rstest]
+    fn jitter_offset_differs_between_secrets() {
+        let offsets: std::collections::BTreeSet<i64> = (0..10)
+            .map(|i| build_jittered_secret(&format!("secret-{}", i), "1h"))
+            .filter_map(|s| jitter_offset(&s, "0", &Settings::default()))
+            .map(|o| o.num_seconds())
+            .collect();
+        assert!(offsets.len() > 1);
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("1y")]
+    fn jitter_offset_without_valid_window(#[case] jitter: &str) {
+        let secret = build_jittered_secret("secret", jitter);
+        assert_eq!(jitter_offset(&secret, "0", &Settings::default()), None);
+    }
+
+    #[rstest]
+    fn renewal_deferred_until_offset_is_over() {
+        let secret = build_jittered_secret("secret", "30m");
+        let offset = jitter_offset(&secret, "0", &Settings::default())
+            .unwrap()
+            .num_seconds();
+        // Cron didn't fire yet, e.g. a manual renewal isn't deferred
+        assert!(!renewal_deferred(
+            &secret,
+            "0",
+            &at(3599),
+            &Settings::default()
+        ));
+        assert_eq!(
+            renewal_deferred(&secret, "0", &at(3600 + offset - 1), &Settings::default()),
+            offset > 0
+        );
+        assert!(!renewal_deferred(
+            &secret,
+            "0",
+            &at(3600 + offset),
+            &Settings::default()
+        ));
+    }
+
+    #[rstest]
+    fn mark_due_renewals_skips_deferred_field() {
+        let secret = build_jittered_secret("secret", "30m");
+        let offset = jitter_offset(&secret, "0", &Settings::default())
+            .unwrap()
+            .num_seconds();
+        let marked = mark_due_renewals(&secret, &at(3600 + offset), &Settings::default());
+        assert!(marked
+            .annotations()
+            .contains_key("v1.secret.runo.rocks/renewal-0"));
+        if offset > 0 {
+            let marked = mark_due_renewals(&secret, &at(3600), &Settings::default());
+            assert!(!marked
+                .annotations()
+                .contains_key("v1.secret.runo.rocks/renewal-0"));
+        }
+    }
+
+    #[rstest]
+    fn until_deferred_renewal_waits_for_offset() {
+        let secret = build_jittered_secret("secret", "30m");
+        let offset = jitter_offset(&secret, "0", &Settings::default())
+            .unwrap()
+            .num_seconds() as u64;
+        match offset {
+            0 => assert_eq!(
+                until_deferred_renewal(&secret, &at(3600), &Settings::default()),
+                None
+            ),
+            _ => assert_eq!(
+                until_deferred_renewal(&secret, &at(3600), &Settings::default()),
+                Some(Duration::from_secs(offset))
+            ),
+        }
+    }
+
+    #[rstest]
+    fn until_next_renewal_includes_jitter() {
+        let secret = build_jittered_secret("secret", "30m");
+        let offset = jitter_offset(&secret, "0", &Settings::default())
+            .unwrap()
+            .num_seconds() as u64;
+        assert_eq!(
+            until_next_renewal(&secret, &at(60), &Settings::default()),
+            Some(Duration::from_secs(3540 + offset))
+        );
+    }
+
+    #[rstest]
+    #[case("Europe/Berlin")]
+    #[case("America/New_York")]
+    #[case("UTC")]
+    fn parse_valid_timezone(#[case] value: &str) {
+        assert!(parse_timezone(value).is_ok())
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("Europe/Atlantis")]
+    #[case("+02:00")]
+    fn parse_invalid_timezone(#[case] value: &str) {
+        assert!(parse_timezone(value).is_err())
+    }
+
+    #[rstest]
+    // 1970-01-01 00:00 UTC is 01:00 in Berlin, so midnight in Berlin is 23:00 UTC
+    #[case(Some("Europe/Berlin"), 23 * 3600)]
+    #[case(Some("America/New_York"), 5 * 3600)]
+    #[case(None, 86400)]
+    fn next_renewal_in_timezone(#[case] timezone: Option<&str>, #[case] expected: i64) {
+        let mut annotations = vec![(
+            "v1.secret.runo.rocks/renewal-cron-0".to_string(),
+            "0 0 * * *".to_string(),
+        )];
+        if let Some(tz) = timezone {
+            annotations.push((
+                "v1.secret.runo.rocks/renewal-timezone-0".to_string(),
+                tz.to_string(),
+            ));
+        }
+        let secret = Arc::new(build_secret_with_annotations(annotations));
+        assert_eq!(
+            next_renewal(&secret, "0", &at(0), &Settings::default()),
+            Some(at(expected))
+        );
+    }
+
+    #[rstest]
+    #[case("0 0 * * *", None, true)]
+    #[case("0 0 * * *", Some("Europe/Berlin"), true)]
+    #[case("0 0 * * * *", None, false)]
+    #[case("0 0 * * *", Some("Mars/Olympus"), false)]
+    fn validate_renewal_cron_and_timezone(
+        #[case] spec: &str,
+        #[case] timezone: Option<&str>,
+        #[case] valid: bool,
+    ) {
+        let mut annotations = vec![(
+            "v1.secret.runo.rocks/renewal-cron-0".to_string(),
+            spec.to_string(),
+        )];
+        if let Some(tz) = timezone {
+            annotations.push((
+                "v1.secret.runo.rocks/renewal-timezone-0".to_string(),
+                tz.to_string(),
+            ));
+        }
+        let secret = Arc::new(build_secret_with_annotations(annotations));
+        assert_eq!(
+            validate_renewal(&secret, "0", &Settings::default()).is_ok(),
+            valid
+        );
+    }
+
+    #[rstest]
+    fn until_next_renewal_without_cron() {
+        let secret = Arc::new(build_secret_with_annotations(vec![(
+            "v1.secret.runo.rocks/generate-0".to_string(),
+            "username".to_string(),
+        )]));
+        assert_eq!(
+            until_next_renewal(&secret, &at(60), &Settings::default()),
+            None
+        );
+    }
+
+    #[rstest]
+    #[case("90s", 90)]
+    #[case("15m", 900)]
+    #[case("720h", 2_592_000)]
+    #[case("30d", 2_592_000)]
+    #[case("2w", 1_209_600)]
+    #[case("1d12h", 129_600)]
+    fn parse_valid_duration(#[case] value: &str, #[case] seconds: u64) {
+        assert_eq!(parse_duration(value).unwrap(), Duration::from_secs(seconds));
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("30")]
+    #[case("0d")]
+    #[case("d")]
+    #[case("-1d")]
+    #[case("1y")]
+    #[case("1.5h")]
+    fn parse_invalid_duration(#[case] value: &str) {
+        assert!(parse_duration(value).is_err());
+    }
+
+    #[rstest]
+    #[case(0, 3599, false)]
+    #[case(0, 3600, true)]
+    fn max_age_exceeded_after_generation(
+        #[case] generated: i64,
+        #[case] now: i64,
+        #[case] exceeded: bool,
+    ) {
+        let secret = Arc::new(build_secret_with_annotations(vec![
+            (
+                "v1.secret.runo.rocks/generate-0".to_string(),
+                "username".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/max-age-0".to_string(),
+                "1h".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/generated-at-0".to_string(),
+                generated.to_string(),
+            ),
+        ]));
+        assert_eq!(
+            max_age_exceeded(&secret, "0", &at(now), &Settings::default()),
+            exceeded
+        );
+    }
+
+    #[rstest]
+    fn max_age_not_exceeded_with_invalid_duration() {
+        let secret = Arc::new(build_secret_with_annotations(vec![
+            (
+                "v1.secret.runo.rocks/generate-0".to_string(),
+                "username".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/max-age-0".to_string(),
+                "sometimes".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/generated-at-0".to_string(),
+                "0".to_string(),
+            ),
+        ]));
+        assert!(!max_age_exceeded(
+            &secret,
+            "0",
+            &at(3600),
+            &Settings::default()
+        ));
+    }
+
+    #[rstest]
+    fn until_max_age_expiry_picks_earliest_field() {
+        let secret = Arc::new(build_secret_with_annotations(vec![
+            (
+                "v1.secret.runo.rocks/generate-0".to_string(),
+                "username".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/max-age-0".to_string(),
+                "1d".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/generated-at-0".to_string(),
+                "0".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/generate-1".to_string(),
+                "password".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/max-age-1".to_string(),
+                "1h".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/generated-at-1".to_string(),
+                "0".to_string(),
+            ),
+        ]));
+        assert_eq!(
+            until_max_age_expiry(&secret, &at(60), &Settings::default()),
+            Some(Duration::from_secs(3540))
+        );
+    }
+
+    #[rstest]
+    #[case(0, 3599, false)]
+    #[case(0, 3600, true)]
+    fn grace_period_exceeded_after_staging(
+        #[case] pending_since: i64,
+        #[case] now: i64,
+        #[case] exceeded: bool,
+    ) {
+        let secret = Arc::new(build_secret_with_annotations(vec![
+            (
+                "v1.secret.runo.rocks/generate-0".to_string(),
+                "password".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/grace-period-0".to_string(),
+                "1h".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/pending-since-0".to_string(),
+                pending_since.to_string(),
+            ),
+        ]));
+        assert_eq!(
+            grace_period_exceeded(&secret, "0", &at(now), &Settings::default()),
+            exceeded
+        );
+    }
+
+    #[rstest]
+    fn until_grace_period_expiry_without_pending_value() {
+        let secret = Arc::new(build_secret_with_annotations(vec![
+            (
+                "v1.secret.runo.rocks/generate-0".to_string(),
+                "password".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/grace-period-0".to_string(),
+                "1h".to_string(),
+            ),
+        ]));
+        assert_eq!(
+            until_grace_period_expiry(&secret, &at(60), &Settings::default()),
+            None
+        );
+    }
+}
@@ -1,35 +1,43 @@
 use crate::annotations::{
-    charset, create_checksum, generate, generated_with_checksum, id_iter, length, needs_clone,
-    needs_generation, needs_renewal, pattern,
+    charset, create_checksum, generate, generated_at, generated_with_checksum,
+    generated_with_legacy_checksum, has_pending, history, history_generated_at, id_iter, is_staged,
+    is_state_annotation, length, needs_clone, needs_generation, needs_promotion, needs_renewal,
+    pattern, uses_charset,
 };
+use crate::resource::GeneratedResource;
+use crate::settings::Settings;
 use chrono::{DateTime, Utc};
-use k8s_openapi::api::core::v1::Secret;
 use k8s_openapi::ByteString;
 use kube::api::Patch;
 use kube::{Api, ResourceExt};
 use rand::RngExt;
 
 use crate::errors::{
-    AnnotationUpdateError, CantCreateStringFromRegex, DataUpdateError, DuplicateKeysError,
-    InvalidRegexPattern, SecretUpdateError,
+    AnnotationUpdateError, CantCreateStringFromRegex, CloneNamespaceNotAllowed,
+    CloneSourceNotAllowed, DataUpdateError, DuplicateKeysError, InvalidRegexPattern,
+    NamespaceOutOfScope, SecretUpdateError,
 };
 use std::collections::BTreeMap;
 
 use crate::annotations;
+use crate::annotations::clonable_by;
+use crate::config::RunoConfig;
 use crate::k8s::K8s;
+use crate::{labels, namespaces};
 use std::sync::Arc;
 use std::time::SystemTime;
-use tracing::error;
 use tracing::log::debug;
+use tracing::{error, info};
 
 pub fn generate_random_string(
-    obj: &Arc<Secret>,
+    obj: &Arc<impl GeneratedResource>,
     id: &str,
+    settings: &Settings,
 ) -> Result<String, CantCreateStringFromRegex> {
-    let length = length(obj, id);
-    let charset = charset(obj, id);
-    let pattern = pattern(obj, id);
-    let random_string = if !charset.is_default() {
+    let length = length(obj, id, settings);
+    let charset = charset(obj, id, settings);
+    let pattern = pattern(obj, id, settings);
+    let random_string = if uses_charset(obj, id, settings) {
         Ok(generate_random_string_from_charset(
             length.get_value(),
             charset.get_value().as_str(),
@@ -59,7 +67,7 @@ fn generate_random_string_from_charset(length: usize, charset: &str) -> String {
     random_string
 }
 
-fn validate_pattern(pattern: &str) -> Result<&str, InvalidRegexPattern> {
+pub fn validate_pattern(pattern: &str) -> Result<&str, InvalidRegexPattern> {
     let forbidden_chars = vec!["+", "?", "*", "{", "}"];
     for char in forbidden_chars {
         if pattern.contains(char) {
@@ -95,9 +103,10 @@ fn generate_random_string_from_pattern(
 }
 
 fn update_annotations(
-    obj: &Arc<Secret>,
+    obj: &Arc<impl GeneratedResource>,
+    settings: &Settings,
 ) -> Result<BTreeMap<String, String>, AnnotationUpdateError> {
-    let mut secret_annotations = match &obj.metadata.annotations {
+    let mut secret_annotations = match &obj.meta().annotations {
         Some(annotations) => annotations.clone(),
         None => BTreeMap::new(),
     };
@@ -109,10 +118,19 @@ fn update_annotations(
                 annotations::V1Annotation::GeneratedWithChecksum.key(),
                 id
             );
-            let checksum = create_checksum(obj, id.as_str());
+            let checksum = create_checksum(obj, id.as_str(), settings);
             secret_annotations.insert(generated_with_checksum_v1, checksum);
         }
-        if needs_generation(obj, id.as_str()) {
+        if generated_with_legacy_checksum(obj, &id, settings) {
+            // Migration code for fields generated before the config checksum ignored state
+            // annotations, their value is kept
+            info!("Migrate legacy checksum of field with id {}", id);
+            secret_annotations.insert(
+                annotations::V1Annotation::GeneratedWithChecksum.value(&id),
+                create_checksum(obj, id.as_str(), settings),
+            );
+        }
+        if needs_generation(obj, id.as_str(), settings) {
             debug!(
                 "{:?} annotations for id {:?} will be updated",
                 obj.name_any(),
@@ -127,16 +145,53 @@ fn update_annotations(
                 annotations::V1Annotation::GeneratedWithChecksum.key(),
                 id
             );
-            let checksum = create_checksum(obj, id.as_str());
+            let checksum = create_checksum(obj, id.as_str(), settings);
             secret_annotations.insert(generated_with_checksum_v1, checksum);
         }
-        if needs_renewal(obj, id.as_str()) {
+        if keeps_history(obj, id.as_str(), settings) {
+            secret_annotations.insert(
+                annotations::V1Annotation::HistoryGeneratedAt.value(&id),
+                shift_history_generated_at(obj, &id, settings),
+            );
+        }
+        if needs_promotion(obj, id.as_str(), settings)
+            && !needs_generation(obj, id.as_str(), settings)
+        {
+            let now: DateTime<Utc> = SystemTime::now().into();
+            secret_annotations.insert(
+                annotations::V1Annotation::GeneratedAt.value(&id),
+                now.timestamp().to_string(),
+            );
+            secret_annotations.remove(&annotations::V1Annotation::PendingSince.value(&id));
+            let promote_v1 = annotations::V1Annotation::Promote.value(&id);
+            if secret_annotations.contains_key(&promote_v1) {
+                secret_annotations.insert(promote_v1, "false".to_string());
+            }
+        }
+        if needs_staging(obj, id.as_str(), settings) {
+            let now: DateTime<Utc> = SystemTime::now().into();
+            secret_annotations.insert(
+                annotations::V1Annotation::PendingSince.value(&id),
+                now.timestamp().to_string(),
+            );
+        }
+        if needs_renewal(obj, id.as_str(), settings) {
             secret_annotations.insert(
                 format!("{}-{}", annotations::V1Annotation::Renewal.key(), id),
                 "false".to_string(),
             );
+            let rotate_requested_v1 = annotations::V1Annotation::RotateRequested.value(&id);
+            if secret_annotations.contains_key(&rotate_requested_v1) {
+                secret_annotations.insert(rotate_requested_v1, "false".to_string());
+            }
+            if !is_staged(obj, id.as_str(), settings) {
+                let generated_at_v1 =
+                    format!("{}-{}", annotations::V1Annotation::GeneratedAt.key(), id);
+                let now: DateTime<Utc> = SystemTime::now().into();
+                secret_annotations.insert(generated_at_v1, now.timestamp().to_string());
+            }
         }
-        let checksum = create_checksum(obj, id.as_str());
+        let checksum = create_checksum(obj, id.as_str(), settings);
         debug!("Adding checksum {:?} for config with ID {:?}", checksum, id);
         let checksum_v1: String =
             format!("{}-{}", annotations::V1Annotation::ConfigChecksum.key(), id);
@@ -146,7 +201,9 @@ fn update_annotations(
 }
 
 /// Collect all field names that will be generated/updated and check for duplicates
-fn validate_no_duplicate_keys(obj: &Arc<Secret>) -> Result<(), DuplicateKeysError> {
+pub fn validate_no_duplicate_keys(
+    obj: &Arc<impl GeneratedResource>,
+) -> Result<(), DuplicateKeysError> {
     use std::collections::HashSet;
     let mut field_names = HashSet::new();
     let mut duplicates = Vec::new();
@@ -167,45 +224,174 @@ fn validate_no_duplicate_keys(obj: &Arc<Secret>) -> Result<(), DuplicateKeysErro
     }
 }
 
-fn update_data(obj: &Arc<Secret>) -> Result<BTreeMap<String, ByteString>, DataUpdateError> {
+fn update_data(
+    obj: &Arc<impl GeneratedResource>,
+    clone_sources: &BTreeMap<String, ByteString>,
+    settings: &Settings,
+) -> Result<BTreeMap<String, ByteString>, DataUpdateError> {
     // Validate no duplicate keys before processing
     if let Err(e) = validate_no_duplicate_keys(obj) {
         error!("{}", e);
         return Err(DataUpdateError);
     }
 
-    let mut data = match &obj.data {
-        Some(data) => data.clone(),
-        None => BTreeMap::new(),
-    };
+    let mut data = obj.values().unwrap_or_default();
     for id in id_iter(obj) {
-        if needs_generation(obj, id.as_str()) {
+        if keeps_history(obj, id.as_str(), settings) {
             debug!(
-                "{:?} data for id {:?} will be generated",
+                "{:?} previous value for id {:?} will be kept",
                 obj.name_any(),
                 id
             );
-            data = update_data_field(data, obj, &id)?;
+            data = shift_history(
+                data,
+                &generate(obj, &id).get_value(),
+                history_size(obj, &id, settings),
+            );
         }
-        if needs_renewal(obj, id.as_str()) {
+        if needs_generation(obj, id.as_str(), settings) {
+            debug!(
+                "{:?} data for id {:?} will be generated",
+                obj.name_any(),
+                id
+            );
+            data = update_data_field(data, obj, &id, settings)?;
+        } else if needs_promotion(obj, id.as_str(), settings) {
+            debug!(
+                "{:?} pending value for id {:?} will be promoted",
+                obj.name_any(),
+                id
+            );
+            data = promote_pending_field(data, obj, &id)?;
+        } else if needs_staging(obj, id.as_str(), settings) {
+            debug!(
+                "{:?} for id {:?} needs to be renewed in two phases",
+                obj.name_any(),
+                id
+            );
+            data = stage_pending_field(data, obj, &id, settings)?;
+        } else if needs_renewal(obj, id.as_str(), settings)
+            && !is_staged(obj, id.as_str(), settings)
+        {
             debug!("{:?} for id {:?} needs to be renewed", obj.name_any(), id);
-            data = update_data_field(data, obj, &id)?;
+            data = update_data_field(data, obj, &id, settings)?;
         }
         if needs_clone(obj, id.as_str()) {
             debug!("{:?} for id {:?} needs to get cloned", obj.name_any(), id);
-            data = clone_data_field(data, obj, &id)?;
+            data = match parse_clone_source(&annotations::clone_from(obj, &id).get_value()) {
+                Some(_) => clone_external_field(data, obj, &id, clone_sources)?,
+                None => clone_data_field(data, obj, &id)?,
+            };
         }
     }
     Ok(data)
 }
 
+/// Check if an update of the Secret rotates an existing value of any field
+pub fn needs_rotation(obj: &Arc<impl GeneratedResource>, settings: &Settings) -> bool {
+    id_iter(obj).iter().any(|id| {
+        !needs_generation(obj, id, settings)
+            && (needs_renewal(obj, id, settings) || needs_promotion(obj, id, settings))
+    })
+}
+
+/// A renewal of a field with two-phase rotation stages a pending value first, unless there
+/// is already one waiting for promotion
+fn needs_staging(obj: &Arc<impl GeneratedResource>, id: &str, settings: &Settings) -> bool {
+    is_staged(obj, id, settings)
+        && !has_pending(obj, id)
+        && !needs_generation(obj, id, settings)
+        && needs_renewal(obj, id, settings)
+}
+
+/// Fields with two-phase rotation always keep at least the demoted value
+fn history_size(obj: &Arc<impl GeneratedResource>, id: &str, settings: &Settings) -> usize {
+    let history = history(obj, id, settings).get_value();
+    match is_staged(obj, id, settings) {
+        true => history.max(1),
+        false => history,
+    }
+}
+
+/// A field keeps its previous values if a history is configured and an existing value
+/// gets replaced by a new one
+fn keeps_history(obj: &Arc<impl GeneratedResource>, id: &str, settings: &Settings) -> bool {
+    if history_size(obj, id, settings) == 0 || needs_clone(obj, id) {
+        return false;
+    }
+    let has_value = match obj.values().as_ref() {
+        Some(d) => d.contains_key(&generate(obj, id).get_value()),
+        None => false,
+    };
+    has_value
+        && (needs_generation(obj, id, settings)
+            || needs_promotion(obj, id, settings)
+            || (needs_renewal(obj, id, settings) && !is_staged(obj, id, settings)))
+}
+
+pub(crate) fn pending_key(field_name: &str) -> String {
+    format!("{}.pending", field_name)
+}
+
+fn history_key(field_name: &str, index: usize) -> String {
+    match index {
+        1 => format!("{}.previous", field_name),
+        i => format!("{}.previous-{}", field_name, i),
+    }
+}
+
+/// Move the current value of a field to `<field>.previous` and every older value one
+/// position further, dropping values that exceed the history size
+fn shift_history(
+    mut secret_data: BTreeMap<String, ByteString>,
+    field_name: &str,
+    size: usize,
+) -> BTreeMap<String, ByteString> {
+    let mut index = size + 1;
+    while secret_data
+        .remove(&history_key(field_name, index))
+        .is_some()
+    {
+        index += 1;
+    }
+    for i in (1..size).rev() {
+        if let Some(v) = secret_data.remove(&history_key(field_name, i)) {
+            secret_data.insert(history_key(field_name, i + 1), v);
+        }
+    }
+    if let Some(current) = secret_data.get(field_name).cloned() {
+        secret_data.insert(history_key(field_name, 1), current);
+    }
+    secret_data
+}
+
+/// Timestamps of the values in the history, newest first
+fn shift_history_generated_at(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    settings: &Settings,
+) -> String {
+    let generated_at = generated_at(obj, id);
+    let current = match generated_at.exists() {
+        true => generated_at.get_value(),
+        false => "unknown".to_string(),
+    };
+    let previous = history_generated_at(obj, id).get_value();
+    std::iter::once(current.as_str())
+        .chain(previous.split(',').filter(|t| !t.is_empty()))
+        .take(history_size(obj, id, settings))
+        .collect::<Vec<&str>>()
+        .join(",")
+}
+
 fn update_data_field(
     mut secret_data: BTreeMap<String, ByteString>,
-    obj: &Arc<Secret>,
+    obj: &Arc<impl GeneratedResource>,
     id: &str,
+    settings: &Settings,
 ) -> Result<BTreeMap<String, ByteString>, DataUpdateError> {
     let key = annotations::generate(obj, id);
-    let value = generate_random_string(obj, id);
+    let value = generate_random_string(obj, id, settings);
     match value {
         Ok(v) => {
             secret_data.insert(
@@ -225,14 +411,62 @@ fn update_data_field(
     }
 }
 
-fn should_clone_already_cloned_field(obj: &Arc<Secret>, clone_from_id: &str) -> bool {
+fn stage_pending_field(
+    mut secret_data: BTreeMap<String, ByteString>,
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    settings: &Settings,
+) -> Result<BTreeMap<String, ByteString>, DataUpdateError> {
+    let key = pending_key(&annotations::generate(obj, id).get_value());
+    match generate_random_string(obj, id, settings) {
+        Ok(v) => {
+            secret_data.insert(key, ByteString(v.as_bytes().to_vec()));
+            Ok(secret_data)
+        }
+        Err(e) => {
+            error!(
+                "Can't generate random string for {:?}: {:?}",
+                obj.name_any(),
+                e
+            );
+            Err(DataUpdateError)
+        }
+    }
+}
+
+fn promote_pending_field(
+    mut secret_data: BTreeMap<String, ByteString>,
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+) -> Result<BTreeMap<String, ByteString>, DataUpdateError> {
+    let field_name = annotations::generate(obj, id).get_value();
+    match secret_data.remove(&pending_key(&field_name)) {
+        Some(pending) => {
+            secret_data.insert(field_name, pending);
+            Ok(secret_data)
+        }
+        None => {
+            error!(
+                "Can't promote pending value for {:?} and id {:?}! Data field is empty",
+                obj.name_any(),
+                id
+            );
+            Err(DataUpdateError)
+        }
+    }
+}
+
+fn should_clone_already_cloned_field(
+    obj: &Arc<impl GeneratedResource>,
+    clone_from_id: &str,
+) -> bool {
     let maybe_clone_from = annotations::clone_from(obj, clone_from_id);
     maybe_clone_from.exists()
 }
 
 fn clone_data_field(
     mut secret_data: BTreeMap<String, ByteString>,
-    obj: &Arc<Secret>,
+    obj: &Arc<impl GeneratedResource>,
     id: &str,
 ) -> Result<BTreeMap<String, ByteString>, DataUpdateError> {
     let maybe_generate = annotations::generate(obj, id);
@@ -269,44 +503,415 @@ fn clone_data_field(
     Ok(secret_data)
 }
 
-fn get_updated_secret(obj: &Arc<Secret>) -> Result<Secret, SecretUpdateError> {
-    let maybe_data = update_data(obj);
-    let maybe_annotations = update_annotations(obj);
-    let mut secret = Secret {
-        ..Secret::default()
+/// Field of another Secret referenced by `clone-from`, either as `<secret>/<id-or-key>` in the
+/// same namespace or as `<namespace>/<secret>/<id-or-key>`
+#[derive(Debug, PartialEq)]
+pub struct CloneSource {
+    namespace: Option<String>,
+    name: String,
+    field: String,
+}
+
+/// Parse the value of `clone-from`. A plain id refers to a field of the same Secret, so there
+/// is no source to resolve.
+pub fn parse_clone_source(value: &str) -> Option<CloneSource> {
+    match value.split('/').collect::<Vec<&str>>().as_slice() {
+        [name, field] => Some(CloneSource {
+            namespace: None,
+            name: name.to_string(),
+            field: field.to_string(),
+        }),
+        [namespace, name, field] => Some(CloneSource {
+            namespace: Some(namespace.to_string()),
+            name: name.to_string(),
+            field: field.to_string(),
+        }),
+        _ => None,
+    }
+}
+
+/// Value of a field of the source Secret. The field is looked up by id first and by key if
+/// there is no such id.
+fn source_value(source: &Arc<impl GeneratedResource>, field: &str) -> Option<ByteString> {
+    let key = match id_iter(source).iter().any(|id| id == field) {
+        true => generate(source, field).get_value(),
+        false => field.to_string(),
     };
+    source.values().and_then(|d| d.get(&key).cloned())
+}
+
+/// Sources have to opt in to be cloned, otherwise anyone who can annotate a resource could read
+/// every other resource through runo
+pub fn check_clone_source(
+    source: &Arc<impl GeneratedResource>,
+    namespace: &str,
+) -> Result<(), CloneSourceNotAllowed> {
+    match labels::managed_by_us(source) && clonable_by(source).iter().any(|ns| ns == namespace) {
+        true => Ok(()),
+        false => Err(CloneSourceNotAllowed {
+            source: format!(
+                "{}/{}",
+                source.namespace().unwrap_or_default(),
+                source.name_any()
+            ),
+            namespace: namespace.to_string(),
+        }),
+    }
+}
+
+/// Other namespaces have to be allowed for cloning and in the scope of the controller
+fn check_clone_namespace(
+    config: &RunoConfig,
+    own_namespace: &str,
+    namespace: &str,
+) -> Result<(), String> {
+    if namespace == own_namespace {
+        return Ok(());
+    }
+    if !config
+        .allowed_clone_namespaces
+        .iter()
+        .any(|n| n == namespace)
+    {
+        return Err(CloneNamespaceNotAllowed {
+            namespace: namespace.to_string(),
+        }
+        .to_string());
+    }
+    if !namespaces::in_scope(config, namespace) {
+        return Err(NamespaceOutOfScope {
+            namespace: namespace.to_string(),
+        }
+        .to_string());
+    }
+    Ok(())
+}
+
+/// Fetch the values of all fields cloned from other resources of the same kind, keyed by the
+/// id of the field
+async fn resolve_clone_sources<K: GeneratedResource>(
+    obj: &Arc<K>,
+    config: &RunoConfig,
+) -> BTreeMap<String, ByteString> {
+    let own_namespace = obj.namespace().unwrap_or_default();
+    let mut values = BTreeMap::new();
+    for id in id_iter(obj) {
+        if !needs_clone(obj, &id) {
+            continue;
+        }
+        let source = match parse_clone_source(&annotations::clone_from(obj, &id).get_value()) {
+            Some(source) => source,
+            None => continue,
+        };
+        let namespace = source.namespace.unwrap_or(own_namespace.clone());
+        if let Err(e) = check_clone_namespace(config, &own_namespace, &namespace) {
+            error!("{}", e);
+            continue;
+        }
+        let resources: Api<K> = Api::namespaced(K8s::get_client().await, &namespace);
+        match resources.get_opt(&source.name).await {
+            Ok(Some(resource)) => {
+                let resource = Arc::new(resource);
+                if let Err(e) = check_clone_source(&resource, &own_namespace) {
+                    error!("{}", e);
+                    continue;
+                }
+                match source_value(&resource, &source.field) {
+                    Some(value) => {
+                        values.insert(id, value);
+                    }
+                    None => error!(
+                        "Can't clone field! {} {:?} in {:?} has no field {:?}",
+                        K::kind(&()),
+                        source.name,
+                        namespace,
+                        source.field
+                    ),
+                }
+            }
+            Ok(None) => error!(
+                "Can't clone field! {} {:?} in {:?} doesn't exist",
+                K::kind(&()),
+                source.name,
+                namespace
+            ),
+            Err(e) => error!("{:?}", e),
+        }
+    }
+    values
+}
+
+/// Look up the values of all fields cloned from other resources in the given resources instead
+/// of the cluster, e.g. from other documents of a manifest
+fn resolve_local_clone_sources<K: GeneratedResource>(
+    obj: &Arc<K>,
+    resources: &[Arc<K>],
+) -> BTreeMap<String, ByteString> {
+    let own_namespace = obj.namespace().unwrap_or_default();
+    let mut values = BTreeMap::new();
+    for id in id_iter(obj) {
+        if !needs_clone(obj, &id) {
+            continue;
+        }
+        let source = match parse_clone_source(&annotations::clone_from(obj, &id).get_value()) {
+            Some(source) => source,
+            None => continue,
+        };
+        let namespace = source.namespace.unwrap_or(own_namespace.clone());
+        let value = resources
+            .iter()
+            .find(|r| r.name_any() == source.name && r.namespace().unwrap_or_default() == namespace)
+            .filter(|r| match check_clone_source(r, &own_namespace) {
+                Ok(()) => true,
+                Err(e) => {
+                    error!("{}", e);
+                    false
+                }
+            })
+            .and_then(|r| source_value(r, &source.field));
+        if let Some(value) = value {
+            values.insert(id, value);
+        }
+    }
+    values
+}
+
+/// Namespace and name of the resources the fields of a resource are cloned from
+pub fn clone_source_refs(obj: &Arc<impl GeneratedResource>) -> Vec<(String, String)> {
+    let own_namespace = obj.namespace().unwrap_or_default();
+    id_iter(obj)
+        .iter()
+        .filter(|id| needs_clone(obj, id))
+        .filter_map(|id| parse_clone_source(&annotations::clone_from(obj, id).get_value()))
+        .map(|source| {
+            (
+                source.namespace.unwrap_or(own_namespace.clone()),
+                source.name,
+            )
+        })
+        .collect()
+}
+
+/// `clone-from` values of the fields which can't be cloned from the given resources
+pub fn unresolved_local_clone_sources<K: GeneratedResource>(
+    obj: &Arc<K>,
+    resources: &[Arc<K>],
+) -> Vec<String> {
+    let resolved = resolve_local_clone_sources(obj, resources);
+    id_iter(obj)
+        .into_iter()
+        .filter(|id| needs_clone(obj, id) && !resolved.contains_key(id))
+        .map(|id| annotations::clone_from(obj, &id).get_value())
+        .filter(|value| parse_clone_source(value).is_some())
+        .collect()
+}
+
+fn clone_external_field(
+    mut secret_data: BTreeMap<String, ByteString>,
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    clone_sources: &BTreeMap<String, ByteString>,
+) -> Result<BTreeMap<String, ByteString>, DataUpdateError> {
+    match clone_sources.get(id) {
+        Some(value) => {
+            secret_data.insert(generate(obj, id).get_value(), value.clone());
+            Ok(secret_data)
+        }
+        None => {
+            error!(
+                "Can't clone field! Source {:?} of field with id {:?} couldn't be resolved",
+                annotations::clone_from(obj, id).get_value(),
+                id
+            );
+            Err(DataUpdateError)
+        }
+    }
+}
+
+/// Generate the values of a resource without a cluster. Fields cloned from other resources are
+/// looked up in the given resources.
+pub fn generate_offline<K: GeneratedResource>(
+    obj: &Arc<K>,
+    resources: &[Arc<K>],
+    settings: &Settings,
+) -> Result<K, SecretUpdateError> {
+    get_updated_secret(obj, &resolve_local_clone_sources(obj, resources), settings)
+}
+
+/// Values runo generates for a field, including its pending and previous values
+fn is_owned_key(obj: &Arc<impl GeneratedResource>, key: &str) -> bool {
+    id_iter(obj).iter().any(|id| {
+        let field_name = generate(obj, id).get_value();
+        if key == field_name || key == pending_key(&field_name) {
+            return true;
+        }
+        match key.strip_prefix(&history_key(&field_name, 1)) {
+            Some("") => true,
+            Some(index) => index
+                .strip_prefix('-')
+                .is_some_and(|i| i.parse::<usize>().is_ok()),
+            None => false,
+        }
+    })
+}
+
+fn get_updated_secret<K: GeneratedResource>(
+    obj: &Arc<K>,
+    clone_sources: &BTreeMap<String, ByteString>,
+    settings: &Settings,
+) -> Result<K, SecretUpdateError> {
+    let maybe_data = update_data(obj, clone_sources, settings);
+    let maybe_annotations = update_annotations(obj, settings);
     if maybe_data.is_err() || maybe_annotations.is_err() {
-        return Err(SecretUpdateError);
+        return Err(SecretUpdateError::Failed);
+    }
+    Ok(K::build(maybe_data.unwrap(), maybe_annotations.unwrap()))
+}
+
+/// Data keys of the last apply of runo, taken from the managed fields of the resource
+fn applied_keys(obj: &Arc<impl GeneratedResource>) -> Vec<String> {
+    obj.meta()
+        .managed_fields
+        .iter()
+        .flatten()
+        .filter(|f| f.manager.as_deref() == Some("runo") && f.operation.as_deref() == Some("Apply"))
+        .filter_map(|f| f.fields_v1.as_ref())
+        .filter_map(|f| f.0.get("f:data").and_then(|data| data.as_object()))
+        .flat_map(|data| data.keys())
+        .filter_map(|k| k.strip_prefix("f:"))
+        .map(String::from)
+        .collect()
+}
+
+/// Values which runo applied before, but which don't belong to any field anymore, e.g. because
+/// the field was removed or renamed. Server-side apply would delete them with the next apply.
+fn released_values(obj: &Arc<impl GeneratedResource>) -> BTreeMap<String, ByteString> {
+    let values = obj.values().unwrap_or_default();
+    applied_keys(obj)
+        .into_iter()
+        .filter(|k| !is_owned_key(obj, k))
+        .filter_map(|k| values.get(&k).map(|v| (k, v.clone())))
+        .collect()
+}
+
+/// Hand released values to another field manager, so they are kept. Returns the resource
+/// version the update has to be based on afterwards.
+async fn release<K: GeneratedResource>(
+    obj: &Arc<K>,
+    secrets: &Api<K>,
+    k8s: &K8s,
+) -> Result<Option<String>, SecretUpdateError> {
+    let released = released_values(obj);
+    if released.is_empty() {
+        return Ok(obj.resource_version());
+    }
+    info!(
+        "{:?} keeps {:?}, which don't belong to a field anymore",
+        obj.name_any(),
+        released.keys().collect::<Vec<_>>()
+    );
+    let mut patch = K::build(released, BTreeMap::new());
+    patch.meta_mut().resource_version = obj.resource_version();
+    match secrets
+        .patch(
+            &obj.name_any(),
+            &k8s.get_release_patch_params(),
+            &Patch::Apply(&patch),
+        )
+        .await
+    {
+        Ok(released) => Ok(released.resource_version()),
+        Err(kube::Error::Api(e)) if e.code == 409 => Err(SecretUpdateError::Conflict),
+        Err(e) => {
+            error!("Can't release values of {:?}: {:?}", obj.name_any(), e);
+            Err(SecretUpdateError::Failed)
+        }
     }
-    secret.data = Some(maybe_data.unwrap());
-    secret.metadata.annotations = Some(maybe_annotations.unwrap());
-    Ok(secret)
 }
 
-pub async fn update(obj: &Arc<Secret>, k8s: &K8s) -> Result<Secret, SecretUpdateError> {
-    let secrets: Api<Secret> =
+/// The applied resource only contains the values and annotations written by runo. Server-side
+/// apply deletes fields which are only owned by runo and missing in the patch, while fields of
+/// other managers are neither taken over nor reverted. The resource version of the read
+/// resource is a precondition, so values are never generated based on an outdated state.
+fn get_apply_patch<K: GeneratedResource>(obj: &Arc<K>, updated_secret: &K) -> K {
+    let ids = id_iter(obj);
+    let data = updated_secret
+        .values()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(k, _)| is_owned_key(obj, k))
+        .collect();
+    let annotations = updated_secret
+        .annotations()
+        .iter()
+        .filter(|(k, _)| ids.iter().any(|id| is_state_annotation(k, id)))
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    let mut patch = K::build(data, annotations);
+    patch.meta_mut().resource_version = obj.resource_version();
+    patch
+}
+
+/// Apply the generated values and annotations and return the resource as stored by the server
+pub async fn update<K: GeneratedResource>(
+    obj: &Arc<K>,
+    config: &RunoConfig,
+) -> Result<K, SecretUpdateError> {
+    let k8s = &config.k8s;
+    let secrets: Api<K> =
         Api::namespaced(K8s::get_client().await, obj.namespace().unwrap().as_str());
-    let updated_secret = get_updated_secret(obj)?;
+    let clone_sources = resolve_clone_sources(obj, config).await;
+    let updated_secret = get_updated_secret(obj, &clone_sources, &config.settings())?;
+    let mut patch = get_apply_patch(obj, &updated_secret);
+    patch.meta_mut().resource_version = release(obj, &secrets, k8s).await?;
     match secrets
         .patch(
             &obj.name_any(),
             &k8s.get_patch_params(),
-            &Patch::Apply(&updated_secret),
+            &Patch::Apply(&patch),
         )
         .await
     {
-        Ok(_) => Ok(updated_secret),
-        Err(_) => Err(SecretUpdateError),
+        Ok(applied) => Ok(applied),
+        Err(kube::Error::Api(e)) if e.code == 409 => {
+            info!("{:?} was changed in the meantime", obj.name_any());
+            Err(SecretUpdateError::Conflict)
+        }
+        Err(e) => {
+            error!("Can't apply {:?}: {:?}", obj.name_any(), e);
+            Err(SecretUpdateError::Failed)
+        }
+    }
+}
+
+/// Read the latest version of the resource, e.g. after a conflict
+pub async fn refresh<K: GeneratedResource>(obj: &Arc<K>) -> Option<Arc<K>> {
+    let api: Api<K> = Api::namespaced(
+        K8s::get_client().await,
+        obj.namespace().unwrap_or_default().as_str(),
+    );
+    match api.get(&obj.name_any()).await {
+        Ok(latest) => Some(Arc::new(latest)),
+        Err(e) => {
+            error!("Can't read latest version of {:?}: {:?}", obj.name_any(), e);
+            None
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::annotations::create_checksum;
-    use crate::secrets::{generate_random_string, update_annotations, update_data};
+    use crate::config::{NamespaceScope, RunoConfig};
+    use crate::k8s::K8s;
+    use crate::secrets::{generate_random_string, shift_history, update_annotations, update_data};
+    use crate::settings::Settings;
+    use crate::test_utils::build_secret_with_annotations;
     use chrono::{DateTime, Utc};
-    use k8s_openapi::api::core::v1::Secret;
-    use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+    use k8s_openapi::api::core::v1::{ConfigMap, Secret};
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::{
+        FieldsV1, ManagedFieldsEntry, ObjectMeta,
+    };
 
     use k8s_openapi::ByteString;
     use regex::Regex;
@@ -315,20 +920,11 @@ mod tests {
     use std::sync::Arc;
     use std::time::SystemTime;
 
-    use super::clone_data_field;
-
-    fn build_secret_with_annotations(annotations: Vec<(String, String)>) -> Secret {
-        let annotation_map = annotations
-            .into_iter()
-            .collect::<BTreeMap<String, String>>();
-        Secret {
-            metadata: ObjectMeta {
-                annotations: Some(annotation_map),
-                ..ObjectMeta::default()
-            },
-            ..Secret::default()
-        }
-    }
+    use super::{
+        check_clone_namespace, check_clone_source, clone_data_field, clone_external_field,
+        get_apply_patch, get_updated_secret, is_owned_key, parse_clone_source, released_values,
+        source_value, CloneSource,
+    };
 
     #[rstest]
     #[case("v1.secret.runo.rocks/length-0", "1", 1)]
@@ -339,7 +935,7 @@ mod tests {
         #[case] count: usize,
     ) {
         let secret = build_secret_with_annotations(vec![(key, value)]);
-        let result = generate_random_string(&Arc::from(secret), "0").unwrap();
+        let result = generate_random_string(&Arc::from(secret), "0", &Settings::default()).unwrap();
         debug_assert_eq!(result.chars().count(), count);
     }
 
@@ -347,7 +943,7 @@ mod tests {
     #[case("v1.secret.runo.rocks/charset-0", "abcd")]
     fn test_generate_random_string_charset_match(#[case] key: String, #[case] value: String) {
         let secret = build_secret_with_annotations(vec![(key, value)]);
-        let result = generate_random_string(&Arc::from(secret), "0").unwrap();
+        let result = generate_random_string(&Arc::from(secret), "0", &Settings::default()).unwrap();
         let re = Regex::new(r"[abcd]+").unwrap();
         assert!(re.is_match(result.as_str()));
     }
@@ -356,7 +952,7 @@ mod tests {
     #[case("v1.secret.runo.rocks/charset-0", "abcd")]
     fn test_generate_random_string_charset_no_match(#[case] key: String, #[case] value: String) {
         let secret = build_secret_with_annotations(vec![(key, value)]);
-        let result = generate_random_string(&Arc::from(secret), "0").unwrap();
+        let result = generate_random_string(&Arc::from(secret), "0", &Settings::default()).unwrap();
         let re = Regex::new(r"[e-zA-Z]+").unwrap();
         assert!(!re.is_match(result.as_str()));
     }
@@ -365,7 +961,7 @@ mod tests {
     #[case("v1.secret.runo.rocks/pattern-0", "\\S")]
     fn test_generate_random_string_pattern_match(#[case] key: String, #[case] value: String) {
         let secret = build_secret_with_annotations(vec![(key, value)]);
-        let result = generate_random_string(&Arc::from(secret), "0").unwrap();
+        let result = generate_random_string(&Arc::from(secret), "0", &Settings::default()).unwrap();
         let re = Regex::new(r"[\S]+").unwrap();
         assert!(re.is_match(result.as_str()));
     }
@@ -374,7 +970,7 @@ mod tests {
     #[case("v1.secret.runo.rocks/pattern-0", "\\S")]
     fn test_generate_random_string_pattern_no_match(#[case] key: String, #[case] value: String) {
         let secret = build_secret_with_annotations(vec![(key, value)]);
-        let result = generate_random_string(&Arc::from(secret), "0").unwrap();
+        let result = generate_random_string(&Arc::from(secret), "0", &Settings::default()).unwrap();
         let re = Regex::new(r"[\s]+").unwrap();
         assert!(!re.is_match(result.as_str()));
     }
@@ -383,7 +979,7 @@ mod tests {
     #[case("v1.secret.runo.rocks/pattern-0", "")]
     fn test_generate_random_string_pattern_error(#[case] key: String, #[case] value: String) {
         let secret = build_secret_with_annotations(vec![(key, value)]);
-        let result = generate_random_string(&Arc::from(secret), "0");
+        let result = generate_random_string(&Arc::from(secret), "0", &Settings::default());
         assert!(result.is_err())
     }
 
@@ -394,7 +990,7 @@ mod tests {
     #[case("v1.secret.runo.rocks/pattern-0", "[abcd]{1, 10}")]
     fn test_generate_random_string_pattern_invalid(#[case] key: String, #[case] value: String) {
         let secret = build_secret_with_annotations(vec![(key, value)]);
-        let result = generate_random_string(&Arc::from(secret), "0");
+        let result = generate_random_string(&Arc::from(secret), "0", &Settings::default());
         assert!(result.is_err())
     }
 
@@ -404,7 +1000,7 @@ mod tests {
     fn test_update_annotations(#[case] annotations: Vec<(String, String)>) {
         let secret = build_secret_with_annotations(annotations);
         let start: DateTime<Utc> = SystemTime::now().into();
-        let annotations = update_annotations(&Arc::from(secret)).unwrap();
+        let annotations = update_annotations(&Arc::from(secret), &Settings::default()).unwrap();
         let end: DateTime<Utc> = SystemTime::now().into();
         assert!(annotations.contains_key("v1.secret.runo.rocks/generated-at-0"));
         assert!(annotations.contains_key("v1.secret.runo.rocks/config-checksum-0"));
@@ -424,7 +1020,7 @@ mod tests {
     ("v1.secret.runo.rocks/renewal-0".to_string(), "true".to_string())])]
     fn test_update_annotations_needs_renewal(#[case] annotations: Vec<(String, String)>) {
         let secret = build_secret_with_annotations(annotations);
-        let annotations = update_annotations(&Arc::from(secret)).unwrap();
+        let annotations = update_annotations(&Arc::from(secret), &Settings::default()).unwrap();
         assert!(annotations.contains_key("v1.secret.runo.rocks/renewal-0"));
         let needs_renewal: bool = annotations
             .get("v1.secret.runo.rocks/renewal-0")
@@ -434,11 +1030,28 @@ mod tests {
         assert!(!needs_renewal);
     }
 
+    #[rstest]
+    #[case(vec![("v1.secret.runo.rocks/generate-0".to_string(), "username".to_string()),
+    ("v1.secret.runo.rocks/generated-at-0".to_string(), "0".to_string()),
+    ("v1.secret.runo.rocks/renewal-0".to_string(), "true".to_string())])]
+    fn test_update_annotations_renewal_updates_generated_at(
+        #[case] annotations: Vec<(String, String)>,
+    ) {
+        let secret = build_secret_with_annotations(annotations);
+        let annotations = update_annotations(&Arc::from(secret), &Settings::default()).unwrap();
+        let timestamp: i64 = annotations
+            .get("v1.secret.runo.rocks/generated-at-0")
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(timestamp > 0);
+    }
+
     #[rstest]
     #[case("v1.secret.runo.rocks/renewal-0", "false")]
     fn test_update_annotations_no_need_for_renewal(#[case] key: String, #[case] value: String) {
         let secret = build_secret_with_annotations(vec![(key, value)]);
-        let annotations = update_annotations(&Arc::from(secret)).unwrap();
+        let annotations = update_annotations(&Arc::from(secret), &Settings::default()).unwrap();
         assert!(annotations.contains_key("v1.secret.runo.rocks/renewal-0"));
         let needs_renewal: bool = annotations
             .get("v1.secret.runo.rocks/renewal-0")
@@ -448,11 +1061,30 @@ mod tests {
         assert!(!needs_renewal);
     }
 
+    #[rstest]
+    fn test_update_annotations_resets_rotate_request() {
+        let secret = build_secret_with_annotations(vec![
+            (
+                "v1.secret.runo.rocks/generate-0".to_string(),
+                "username".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/rotate-requested-0".to_string(),
+                "true".to_string(),
+            ),
+        ]);
+        let annotations = update_annotations(&Arc::from(secret), &Settings::default()).unwrap();
+        assert_eq!(
+            annotations["v1.secret.runo.rocks/rotate-requested-0"],
+            "false"
+        );
+    }
+
     #[rstest]
     #[case("v1.secret.runo.rocks/generate-0", "username")]
     fn test_update_data(#[case] key: String, #[case] value: String) {
         let secret = build_secret_with_annotations(vec![(key, value)]);
-        let data = update_data(&Arc::from(secret)).unwrap();
+        let data = update_data(&Arc::from(secret), &BTreeMap::new(), &Settings::default()).unwrap();
         assert!(data.contains_key("username"));
     }
 
@@ -460,12 +1092,220 @@ mod tests {
     #[case("v1.secret.runo.rocks/generate-0", "username")]
     fn test_update_annotations_creates_config_checksum(#[case] key: String, #[case] value: String) {
         let secret = build_secret_with_annotations(vec![(key, value)]);
-        let annotations = update_annotations(&Arc::from(secret.clone())).unwrap();
+        let annotations =
+            update_annotations(&Arc::from(secret.clone()), &Settings::default()).unwrap();
         assert!(annotations.contains_key("v1.secret.runo.rocks/config-checksum-0"));
         let checksum = annotations
             .get("v1.secret.runo.rocks/config-checksum-0")
             .unwrap();
-        assert_eq!(*checksum, create_checksum(&Arc::from(secret), "0"));
+        assert_eq!(
+            *checksum,
+            create_checksum(&Arc::from(secret), "0", &Settings::default())
+        );
+    }
+
+    #[rstest]
+    fn test_update_annotations_migrates_legacy_checksum() {
+        // generated by a release which included renewal-0 in the checksum
+        let annotations = vec![
+            ("v1.secret.runo.rocks/generate-0", "password"),
+            ("v1.secret.runo.rocks/generated-at-0", "1767225600"),
+            ("v1.secret.runo.rocks/renewal-0", "false"),
+            ("v1.secret.runo.rocks/renewal-cron-0", "0 0 * * *"),
+            (
+                "v1.secret.runo.rocks/generated-with-checksum-0",
+                "06363924a76b702f3251c9222b63ab123d898f7921a2ee9b0a0c9fa329844523",
+            ),
+            (
+                "v1.secret.runo.rocks/config-checksum-0",
+                "06363924a76b702f3251c9222b63ab123d898f7921a2ee9b0a0c9fa329844523",
+            ),
+        ];
+        let secret = Arc::new(build_secret_with_annotations(
+            annotations
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        ));
+        let annotations = update_annotations(&secret, &Settings::default()).unwrap();
+        assert_eq!(
+            annotations["v1.secret.runo.rocks/generated-with-checksum-0"],
+            create_checksum(&secret, "0", &Settings::default())
+        );
+        assert_eq!(
+            annotations["v1.secret.runo.rocks/generated-at-0"],
+            "1767225600"
+        );
+    }
+
+    #[rstest]
+    #[case("0", None)]
+    #[case("db/0", Some(CloneSource { namespace: None, name: "db".to_string(), field: "0".to_string() }))]
+    #[case("databases/db/password", Some(CloneSource {
+        namespace: Some("databases".to_string()),
+        name: "db".to_string(),
+        field: "password".to_string()
+    }))]
+    #[case("a/b/c/d", None)]
+    fn test_parse_clone_source(#[case] value: &str, #[case] expected: Option<CloneSource>) {
+        assert_eq!(parse_clone_source(value), expected);
+    }
+
+    #[rstest]
+    #[case("0", Some("generated"))]
+    #[case("password", Some("generated"))]
+    #[case("static", Some("value"))]
+    #[case("1", None)]
+    #[case("missing", None)]
+    fn test_source_value(#[case] field: &str, #[case] expected: Option<&str>) {
+        let mut source = build_secret_with_annotations(vec![(
+            "v1.secret.runo.rocks/generate-0".to_string(),
+            "password".to_string(),
+        )]);
+        source.data = Some(BTreeMap::from([
+            (
+                "password".to_string(),
+                ByteString("generated".as_bytes().to_vec()),
+            ),
+            (
+                "static".to_string(),
+                ByteString("value".as_bytes().to_vec()),
+            ),
+        ]));
+        assert_eq!(
+            source_value(&Arc::new(source), field),
+            expected.map(|v| ByteString(v.as_bytes().to_vec()))
+        );
+    }
+
+    #[rstest]
+    #[case(true, Some("apps"), true)]
+    #[case(true, Some("apps, other"), true)]
+    #[case(true, Some("other"), false)]
+    #[case(true, None, false)]
+    #[case(false, Some("apps"), false)]
+    fn test_check_clone_source(
+        #[case] managed: bool,
+        #[case] clonable_by: Option<&str>,
+        #[case] allowed: bool,
+    ) {
+        let mut source = build_secret_with_annotations(
+            clonable_by
+                .map(|ns| {
+                    (
+                        "v1.secret.runo.rocks/clonable-by".to_string(),
+                        ns.to_string(),
+                    )
+                })
+                .into_iter()
+                .collect(),
+        );
+        if managed {
+            source.metadata.labels = Some(BTreeMap::from([(
+                "v1.secret.runo.rocks/managed".to_string(),
+                "true".to_string(),
+            )]));
+        }
+        assert_eq!(
+            check_clone_source(&Arc::new(source), "apps").is_ok(),
+            allowed
+        );
+    }
+
+    #[rstest]
+    #[case("apps", vec![], vec![], true)]
+    #[case("shared", vec!["shared"], vec![], true)]
+    #[case("shared", vec![], vec![], false)]
+    #[case("shared", vec!["shared"], vec!["shared"], false)]
+    fn test_check_clone_namespace(
+        #[case] namespace: &str,
+        #[case] allowed: Vec<&str>,
+        #[case] denied: Vec<&str>,
+        #[case] expected: bool,
+    ) {
+        let config = RunoConfig::build(K8s { dry_run: false }, 10)
+            .with_allowed_clone_namespaces(allowed.into_iter().map(String::from).collect())
+            .with_namespace_scope(NamespaceScope {
+                denied: denied.into_iter().map(String::from).collect(),
+                ..NamespaceScope::default()
+            });
+        assert_eq!(
+            check_clone_namespace(&config, "apps", namespace).is_ok(),
+            expected
+        );
+    }
+
+    #[rstest]
+    fn test_update_data_generates_config_map_values() {
+        let config_map = ConfigMap {
+            metadata: ObjectMeta {
+                annotations: Some(BTreeMap::from([
+                    (
+                        "v1.secret.runo.rocks/generate-0".to_string(),
+                        "salt".to_string(),
+                    ),
+                    (
+                        "v1.secret.runo.rocks/length-0".to_string(),
+                        "16".to_string(),
+                    ),
+                ])),
+                ..ObjectMeta::default()
+            },
+            data: Some(BTreeMap::from([(
+                "static".to_string(),
+                "value".to_string(),
+            )])),
+            ..ConfigMap::default()
+        };
+        let data = update_data(
+            &Arc::new(config_map),
+            &BTreeMap::new(),
+            &Settings::default(),
+        )
+        .unwrap();
+        assert_eq!(data.get("salt").unwrap().0.len(), 16);
+        assert_eq!(
+            data.get("static"),
+            Some(&ByteString("value".as_bytes().to_vec()))
+        );
+    }
+
+    #[rstest]
+    fn test_update_data_clones_from_other_secret() {
+        let secret = build_secret_with_annotations(vec![
+            (
+                "v1.secret.runo.rocks/generate-0".to_string(),
+                "password".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/clone-from-0".to_string(),
+                "db/password".to_string(),
+            ),
+        ]);
+        let clone_sources =
+            BTreeMap::from([("0".to_string(), ByteString("from-db".as_bytes().to_vec()))]);
+        let data = update_data(&Arc::new(secret), &clone_sources, &Settings::default()).unwrap();
+        assert_eq!(
+            data.get("password"),
+            Some(&ByteString("from-db".as_bytes().to_vec()))
+        );
+    }
+
+    #[rstest]
+    fn test_clone_external_field_unresolved() {
+        let secret = build_secret_with_annotations(vec![
+            (
+                "v1.secret.runo.rocks/generate-0".to_string(),
+                "password".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/clone-from-0".to_string(),
+                "db/password".to_string(),
+            ),
+        ]);
+        let result =
+            clone_external_field(BTreeMap::new(), &Arc::new(secret), "0", &BTreeMap::new());
+        assert!(result.is_err());
     }
 
     #[rstest]
@@ -575,7 +1415,7 @@ mod tests {
     ])]
     fn test_update_data_fails_on_duplicate_keys(#[case] annotations: Vec<(String, String)>) {
         let secret = build_secret_with_annotations(annotations);
-        let result = update_data(&Arc::from(secret));
+        let result = update_data(&Arc::from(secret), &BTreeMap::new(), &Settings::default());
         // Should fail because both generate-0 and generate-1 produce "username"
         assert!(result.is_err());
     }
@@ -587,7 +1427,7 @@ mod tests {
     ])]
     fn test_update_data_succeeds_with_unique_keys(#[case] annotations: Vec<(String, String)>) {
         let secret = build_secret_with_annotations(annotations);
-        let result = update_data(&Arc::from(secret));
+        let result = update_data(&Arc::from(secret), &BTreeMap::new(), &Settings::default());
         assert!(result.is_ok());
         let data = result.unwrap();
         assert!(data.contains_key("username"));
@@ -602,7 +1442,7 @@ mod tests {
     ])]
     fn test_update_data_fails_with_multiple_duplicates(#[case] annotations: Vec<(String, String)>) {
         let secret = build_secret_with_annotations(annotations);
-        let result = update_data(&Arc::from(secret));
+        let result = update_data(&Arc::from(secret), &BTreeMap::new(), &Settings::default());
         // Should fail because generate-0 and generate-1 both produce "username"
         assert!(result.is_err());
     }
@@ -617,7 +1457,7 @@ mod tests {
         #[case] annotations: Vec<(String, String)>,
     ) {
         let secret = build_secret_with_annotations(annotations);
-        let result = update_data(&Arc::from(secret));
+        let result = update_data(&Arc::from(secret), &BTreeMap::new(), &Settings::default());
         // generate-0 produces "username", generate-1 produces "username" - duplicate!
         assert!(result.is_err());
     }
@@ -633,7 +1473,7 @@ mod tests {
         #[case] annotations: Vec<(String, String)>,
     ) {
         let secret = build_secret_with_annotations(annotations);
-        let result = update_data(&Arc::from(secret));
+        let result = update_data(&Arc::from(secret), &BTreeMap::new(), &Settings::default());
         // generate-0 produces "username", generate-2 produces "username" - duplicate!
         assert!(result.is_err());
     }
@@ -648,11 +1488,374 @@ mod tests {
         #[case] annotations: Vec<(String, String)>,
     ) {
         let secret = build_secret_with_annotations(annotations);
-        let result = update_data(&Arc::from(secret));
+        let result = update_data(&Arc::from(secret), &BTreeMap::new(), &Settings::default());
         // This should succeed - generate-0 produces "username", generate-1 produces "username-cloned", clone copies username to username-cloned
         assert!(result.is_ok());
         let data = result.unwrap();
         assert!(data.contains_key("username"));
         assert!(data.contains_key("username-cloned"));
     }
+
+    #[rstest]
+    #[case(vec![
+        ("v1.secret.runo.rocks/generate-0".to_string(), "password".to_string()),
+        ("v1.secret.runo.rocks/generated-at-0".to_string(), "100".to_string()),
+        ("v1.secret.runo.rocks/history-0".to_string(), "1".to_string()),
+        ("v1.secret.runo.rocks/renewal-0".to_string(), "true".to_string())
+    ])]
+    fn test_update_data_keeps_previous_value_on_renewal(
+        #[case] annotations: Vec<(String, String)>,
+    ) {
+        let mut secret = build_secret_with_annotations(annotations);
+        let mut secret_data = BTreeMap::new();
+        secret_data.insert(
+            "password".to_string(),
+            ByteString("old".as_bytes().to_vec()),
+        );
+        secret.data = Some(secret_data);
+        let data = update_data(
+            &Arc::from(secret.clone()),
+            &BTreeMap::new(),
+            &Settings::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            data.get("password.previous").unwrap().0,
+            "old".as_bytes().to_vec()
+        );
+        assert_ne!(data.get("password").unwrap().0, "old".as_bytes().to_vec());
+        let annotations = update_annotations(&Arc::from(secret), &Settings::default()).unwrap();
+        assert_eq!(
+            annotations
+                .get("v1.secret.runo.rocks/history-generated-at-0")
+                .unwrap(),
+            "100"
+        );
+    }
+
+    #[rstest]
+    #[case(vec![
+        ("v1.secret.runo.rocks/generate-0".to_string(), "password".to_string()),
+        ("v1.secret.runo.rocks/generated-at-0".to_string(), "100".to_string()),
+        ("v1.secret.runo.rocks/renewal-0".to_string(), "true".to_string())
+    ])]
+    fn test_update_data_keeps_no_previous_value_without_history(
+        #[case] annotations: Vec<(String, String)>,
+    ) {
+        let mut secret = build_secret_with_annotations(annotations);
+        let mut secret_data = BTreeMap::new();
+        secret_data.insert(
+            "password".to_string(),
+            ByteString("old".as_bytes().to_vec()),
+        );
+        secret.data = Some(secret_data);
+        let data = update_data(
+            &Arc::from(secret.clone()),
+            &BTreeMap::new(),
+            &Settings::default(),
+        )
+        .unwrap();
+        assert!(!data.contains_key("password.previous"));
+        let annotations = update_annotations(&Arc::from(secret), &Settings::default()).unwrap();
+        assert!(!annotations.contains_key("v1.secret.runo.rocks/history-generated-at-0"));
+    }
+
+    #[rstest]
+    #[case(vec![
+        ("v1.secret.runo.rocks/generate-0".to_string(), "password".to_string()),
+        ("v1.secret.runo.rocks/generated-at-0".to_string(), "300".to_string()),
+        ("v1.secret.runo.rocks/history-0".to_string(), "2".to_string()),
+        ("v1.secret.runo.rocks/history-generated-at-0".to_string(), "200,100".to_string()),
+        ("v1.secret.runo.rocks/renewal-0".to_string(), "true".to_string())
+    ])]
+    fn test_update_annotations_shifts_history_generated_at(
+        #[case] annotations: Vec<(String, String)>,
+    ) {
+        let mut secret = build_secret_with_annotations(annotations);
+        let mut secret_data = BTreeMap::new();
+        secret_data.insert(
+            "password".to_string(),
+            ByteString("old".as_bytes().to_vec()),
+        );
+        secret.data = Some(secret_data);
+        let annotations = update_annotations(&Arc::from(secret), &Settings::default()).unwrap();
+        assert_eq!(
+            annotations
+                .get("v1.secret.runo.rocks/history-generated-at-0")
+                .unwrap(),
+            "300,200"
+        );
+    }
+
+    #[rstest]
+    #[case(3, vec!["password", "password.previous", "password.previous-2", "password.previous-3"])]
+    #[case(1, vec!["password", "password.previous"])]
+    fn test_shift_history(#[case] size: usize, #[case] keys: Vec<&str>) {
+        let mut secret_data = BTreeMap::new();
+        secret_data.insert("password".to_string(), ByteString(b"3".to_vec()));
+        secret_data.insert("password.previous".to_string(), ByteString(b"2".to_vec()));
+        secret_data.insert("password.previous-2".to_string(), ByteString(b"1".to_vec()));
+        let data = shift_history(secret_data, "password", size);
+        assert_eq!(data.keys().collect::<Vec<&String>>(), keys);
+        assert_eq!(data.get("password.previous").unwrap().0, b"3".to_vec());
+        if size == 3 {
+            assert_eq!(data.get("password.previous-2").unwrap().0, b"2".to_vec());
+            assert_eq!(data.get("password.previous-3").unwrap().0, b"1".to_vec());
+        }
+    }
+
+    fn build_staged_secret(annotations: Vec<(String, String)>) -> Secret {
+        let mut secret = build_secret_with_annotations(
+            vec![
+                (
+                    "v1.secret.runo.rocks/generate-0".to_string(),
+                    "password".to_string(),
+                ),
+                (
+                    "v1.secret.runo.rocks/generated-at-0".to_string(),
+                    "100".to_string(),
+                ),
+                (
+                    "v1.secret.runo.rocks/staged-rotation-0".to_string(),
+                    "true".to_string(),
+                ),
+            ]
+            .into_iter()
+            .chain(annotations)
+            .collect(),
+        );
+        let mut secret_data = BTreeMap::new();
+        secret_data.insert("password".to_string(), ByteString(b"old".to_vec()));
+        secret.data = Some(secret_data);
+        secret
+    }
+
+    #[rstest]
+    fn test_staged_renewal_generates_pending_value() {
+        let secret = Arc::new(build_staged_secret(vec![(
+            "v1.secret.runo.rocks/renewal-0".to_string(),
+            "true".to_string(),
+        )]));
+        let data = update_data(&secret, &BTreeMap::new(), &Settings::default()).unwrap();
+        assert_eq!(data.get("password").unwrap().0, b"old".to_vec());
+        assert!(data.contains_key("password.pending"));
+        assert!(!data.contains_key("password.previous"));
+        let annotations = update_annotations(&secret, &Settings::default()).unwrap();
+        assert!(annotations.contains_key("v1.secret.runo.rocks/pending-since-0"));
+        assert_eq!(
+            annotations
+                .get("v1.secret.runo.rocks/generated-at-0")
+                .unwrap(),
+            "100"
+        );
+        assert_eq!(
+            annotations.get("v1.secret.runo.rocks/renewal-0").unwrap(),
+            "false"
+        );
+    }
+
+    #[rstest]
+    fn test_staged_renewal_waits_for_promotion() {
+        let mut secret = build_staged_secret(vec![
+            (
+                "v1.secret.runo.rocks/renewal-0".to_string(),
+                "true".to_string(),
+            ),
+            (
+                "v1.secret.runo.rocks/pending-since-0".to_string(),
+                Utc::now().timestamp().to_string(),
+            ),
+        ]);
+        secret
+            .data
+            .as_mut()
+            .unwrap()
+            .insert("password.pending".to_string(), ByteString(b"new".to_vec()));
+        let data = update_data(&Arc::new(secret), &BTreeMap::new(), &Settings::default()).unwrap();
+        assert_eq!(data.get("password").unwrap().0, b"old".to_vec());
+        assert_eq!(data.get("password.pending").unwrap().0, b"new".to_vec());
+    }
+
+    #[rstest]
+    #[case("v1.secret.runo.rocks/promote-0", "true")]
+    #[case("v1.secret.runo.rocks/grace-period-0", "1m")]
+    fn test_staged_renewal_promotes_pending_value(#[case] key: String, #[case] value: String) {
+        let mut secret = build_staged_secret(vec![
+            (key, value),
+            (
+                "v1.secret.runo.rocks/pending-since-0".to_string(),
+                "200".to_string(),
+            ),
+        ]);
+        secret
+            .data
+            .as_mut()
+            .unwrap()
+            .insert("password.pending".to_string(), ByteString(b"new".to_vec()));
+        let secret = Arc::new(secret);
+        let data = update_data(&secret, &BTreeMap::new(), &Settings::default()).unwrap();
+        assert_eq!(data.get("password").unwrap().0, b"new".to_vec());
+        assert_eq!(data.get("password.previous").unwrap().0, b"old".to_vec());
+        assert!(!data.contains_key("password.pending"));
+        let annotations = update_annotations(&secret, &Settings::default()).unwrap();
+        assert!(!annotations.contains_key("v1.secret.runo.rocks/pending-since-0"));
+        assert_ne!(
+            annotations
+                .get("v1.secret.runo.rocks/generated-at-0")
+                .unwrap(),
+            "100"
+        );
+        assert_eq!(
+            annotations
+                .get("v1.secret.runo.rocks/history-generated-at-0")
+                .unwrap(),
+            "100"
+        );
+    }
+
+    #[rstest]
+    #[case("password", true)]
+    #[case("password.pending", true)]
+    #[case("password.previous", true)]
+    #[case("password.previous-3", true)]
+    #[case("password.previous-x", false)]
+    #[case("password-hint", false)]
+    #[case("username", false)]
+    fn test_is_owned_key(#[case] key: &str, #[case] expected: bool) {
+        let secret = Arc::new(build_secret_with_annotations(vec![(
+            "v1.secret.runo.rocks/generate-0".to_string(),
+            "password".to_string(),
+        )]));
+        assert_eq!(is_owned_key(&secret, key), expected);
+    }
+
+    #[rstest]
+    fn test_apply_patch_only_contains_fields_of_runo() {
+        let mut secret = build_secret_with_annotations(vec![
+            (
+                "v1.secret.runo.rocks/generate-0".to_string(),
+                "password".to_string(),
+            ),
+            ("team".to_string(), "platform".to_string()),
+        ]);
+        secret.data = Some(BTreeMap::from([(
+            "username".to_string(),
+            ByteString(b"admin".to_vec()),
+        )]));
+        secret.metadata.resource_version = Some("42".to_string());
+        let secret = Arc::new(secret);
+        let updated_secret =
+            get_updated_secret(&secret, &BTreeMap::new(), &Settings::default()).unwrap();
+        assert!(updated_secret
+            .data
+            .as_ref()
+            .unwrap()
+            .contains_key("username"));
+        let patch = get_apply_patch(&secret, &updated_secret);
+        let data = patch.data.unwrap();
+        assert_eq!(data.keys().collect::<Vec<_>>(), vec!["password"]);
+        let annotations = patch.metadata.annotations.unwrap();
+        assert!(annotations.contains_key("v1.secret.runo.rocks/generated-at-0"));
+        assert!(annotations.contains_key("v1.secret.runo.rocks/config-checksum-0"));
+        assert!(!annotations.contains_key("v1.secret.runo.rocks/generate-0"));
+        assert!(!annotations.contains_key("team"));
+        assert_eq!(patch.metadata.resource_version, Some("42".to_string()));
+    }
+
+    fn build_applied_secret(annotations: Vec<(&str, &str)>, applied_keys: Vec<&str>) -> Secret {
+        let mut secret = build_secret_with_annotations(
+            annotations
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        );
+        let applied_data = applied_keys
+            .iter()
+            .map(|k| (format!("f:{}", k), serde_json::json!({})))
+            .collect::<serde_json::Map<_, _>>();
+        secret.metadata.managed_fields = Some(vec![
+            ManagedFieldsEntry {
+                manager: Some("runo".to_string()),
+                operation: Some("Apply".to_string()),
+                fields_v1: Some(FieldsV1(serde_json::json!({ "f:data": applied_data }))),
+                ..ManagedFieldsEntry::default()
+            },
+            ManagedFieldsEntry {
+                manager: Some("kubectl".to_string()),
+                operation: Some("Update".to_string()),
+                fields_v1: Some(FieldsV1(serde_json::json!({
+                    "f:data": { "f:token": {} }
+                }))),
+                ..ManagedFieldsEntry::default()
+            },
+        ]);
+        secret.data = Some(
+            ["password", "password.pending", "password.previous", "token"]
+                .into_iter()
+                .map(|k| (k.to_string(), ByteString(k.as_bytes().to_vec())))
+                .collect(),
+        );
+        secret
+    }
+
+    #[rstest]
+    #[case(
+        vec![("v1.secret.runo.rocks/generate-0", "password")],
+        vec!["password", "password.pending", "password.previous"],
+        vec![]
+    )]
+    #[case(
+        vec![("v1.secret.runo.rocks/generate-1", "username")],
+        vec!["password", "password.pending", "password.previous"],
+        vec!["password", "password.pending", "password.previous"]
+    )]
+    #[case(
+        vec![("v1.secret.runo.rocks/generate-0", "passphrase")],
+        vec!["password", "password.previous"],
+        vec!["password", "password.previous"]
+    )]
+    #[case(vec![], vec!["password", "missing"], vec!["password"])]
+    fn test_released_values_of_removed_fields(
+        #[case] annotations: Vec<(&str, &str)>,
+        #[case] applied_keys: Vec<&str>,
+        #[case] expected: Vec<&str>,
+    ) {
+        let secret = Arc::new(build_applied_secret(annotations, applied_keys));
+        let released = released_values(&secret);
+        assert_eq!(released.keys().collect::<Vec<_>>(), expected);
+        for (key, value) in released {
+            assert_eq!(value, ByteString(key.as_bytes().to_vec()));
+        }
+    }
+
+    #[rstest]
+    fn test_updated_secret_is_not_generated_again() {
+        let secret = Arc::new(build_secret_with_annotations(vec![(
+            "v1.secret.runo.rocks/generate-0".to_string(),
+            "password".to_string(),
+        )]));
+        let mut updated_secret =
+            get_updated_secret(&secret, &BTreeMap::new(), &Settings::default()).unwrap();
+        let password = updated_secret
+            .data
+            .as_ref()
+            .unwrap()
+            .get("password")
+            .cloned();
+        let mut annotations = secret.metadata.annotations.clone().unwrap();
+        annotations.extend(updated_secret.metadata.annotations.take().unwrap());
+        updated_secret.metadata.annotations = Some(annotations);
+        let updated_secret = Arc::new(updated_secret);
+        let updated_again =
+            get_updated_secret(&updated_secret, &BTreeMap::new(), &Settings::default()).unwrap();
+        assert_eq!(
+            updated_again
+                .data
+                .as_ref()
+                .unwrap()
+                .get("password")
+                .cloned(),
+            password
+        );
+    }
 }
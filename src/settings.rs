@@ -0,0 +1,575 @@
+use crate::annotations::{self, clone_from, id_iter, uses_charset, V1Annotation};
+use crate::config::{CronJobTemplate, NamespaceScope};
+use crate::errors::{InvalidSettings, PolicyViolation};
+use crate::resource::GeneratedResource;
+use crate::schedule;
+use crate::secrets::validate_pattern;
+use kube::ResourceExt;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{error, info};
+
+pub const DEFAULT_LENGTH: usize = 32;
+pub const DEFAULT_CHARSET: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+pub const DEFAULT_PATTERN: &str = "[a-zA-Z0-9\\-\\_\\(\\)\\%\\$\\@]";
+pub const RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Settings of the controller config file which can be shared and reloaded while running
+pub type SharedSettings = Arc<RwLock<Arc<Settings>>>;
+
+/// How the value of a field is generated
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Generator {
+    Charset,
+    Pattern,
+    Clone,
+}
+
+impl Generator {
+    pub fn of(obj: &Arc<impl GeneratedResource>, id: &str, settings: &Settings) -> Generator {
+        if clone_from(obj, id).exists() {
+            Generator::Clone
+        } else if uses_charset(obj, id, settings) {
+            Generator::Charset
+        } else {
+            Generator::Pattern
+        }
+    }
+}
+
+/// Values of fields without `length`, `charset` or `pattern` annotation
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct Defaults {
+    pub length: usize,
+    /// Fields without `charset` and `pattern` annotation are generated from this charset
+    /// instead of the default pattern
+    pub charset: Option<String>,
+    pub pattern: String,
+}
+
+impl Default for Defaults {
+    fn default() -> Self {
+        Defaults {
+            length: DEFAULT_LENGTH,
+            charset: None,
+            pattern: DEFAULT_PATTERN.to_string(),
+        }
+    }
+}
+
+/// Named bundle of generation and rotation settings, referenced by fields with
+/// `profile-<id>`. Annotations of the field take precedence over the profile.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct Profile {
+    pub length: Option<usize>,
+    pub charset: Option<String>,
+    pub pattern: Option<String>,
+    pub history: Option<usize>,
+    pub staged_rotation: Option<bool>,
+    pub grace_period: Option<String>,
+    pub max_age: Option<String>,
+    pub renewal_cron: Option<String>,
+    pub renewal_timezone: Option<String>,
+    pub renewal_jitter: Option<String>,
+    /// Regenerate the fields of the profile when it changes
+    pub regenerate: bool,
+}
+
+impl Profile {
+    /// Value the profile sets for an annotation
+    pub fn value(&self, annotation: &V1Annotation) -> Option<String> {
+        match annotation {
+            V1Annotation::Length => self.length.map(|l| l.to_string()),
+            V1Annotation::Charset => self.charset.clone(),
+            V1Annotation::Pattern => self.pattern.clone(),
+            V1Annotation::History => self.history.map(|h| h.to_string()),
+            V1Annotation::StagedRotation => self.staged_rotation.map(|s| s.to_string()),
+            V1Annotation::GracePeriod => self.grace_period.clone(),
+            V1Annotation::MaxAge => self.max_age.clone(),
+            V1Annotation::RenewalCron => self.renewal_cron.clone(),
+            V1Annotation::RenewalTimezone => self.renewal_timezone.clone(),
+            V1Annotation::RenewalJitter => self.renewal_jitter.clone(),
+            _ => None,
+        }
+    }
+
+    /// Settings of the profile which influence the generated value. New settings which aren't
+    /// set don't change it, so existing profiles keep their fingerprint.
+    pub fn fingerprint(&self) -> String {
+        [
+            ("length", V1Annotation::Length),
+            ("charset", V1Annotation::Charset),
+            ("pattern", V1Annotation::Pattern),
+        ]
+        .iter()
+        .filter_map(|(name, annotation)| self.value(annotation).map(|v| format!("{}={};", name, v)))
+        .collect()
+    }
+
+    fn validate(&self, name: &str, max_length: Option<usize>) -> Result<(), InvalidSettings> {
+        let invalid = |reason: String| invalid(format!("profile {:?}: {}", name, reason));
+        if let Some(length) = self.length {
+            if !(1..=100).contains(&length) {
+                return Err(invalid("length has to be > 0 and <= 100".to_string()));
+            }
+            if max_length.is_some_and(|max| length > max) {
+                return Err(invalid("length exceeds maxLength".to_string()));
+            }
+        }
+        if self.charset.is_some() && self.pattern.is_some() {
+            return Err(invalid("either charset or pattern can be set".to_string()));
+        }
+        if let Some(charset) = &self.charset {
+            if charset.is_empty() || !charset.is_ascii() {
+                return Err(invalid(
+                    "charset has to be a non-empty ASCII string".to_string(),
+                ));
+            }
+        }
+        if let Some(pattern) = &self.pattern {
+            validate_pattern(pattern).map_err(|e| invalid(e.to_string()))?;
+            regex::Regex::new(pattern).map_err(|e| invalid(e.to_string()))?;
+        }
+        if self.history.is_some_and(|h| h > 10) {
+            return Err(invalid("history has to be <= 10".to_string()));
+        }
+        for duration in [&self.grace_period, &self.max_age, &self.renewal_jitter]
+            .into_iter()
+            .flatten()
+        {
+            schedule::parse_duration(duration).map_err(|e| invalid(e.to_string()))?;
+        }
+        if let Some(cron) = &self.renewal_cron {
+            schedule::parse_cron(cron).map_err(|e| invalid(e.to_string()))?;
+        }
+        if let Some(timezone) = &self.renewal_timezone {
+            schedule::parse_timezone(timezone).map_err(|e| invalid(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Cluster-wide settings of the controller config file
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct Settings {
+    pub defaults: Defaults,
+    /// Maximum length of generated values
+    pub max_length: Option<usize>,
+    /// Generators fields may use, all if it's not set
+    pub allowed_generators: Option<Vec<Generator>>,
+    /// Replaces the CronJob settings of the command line
+    pub cronjob: Option<CronJobTemplate>,
+    /// Replaces the namespace scope of the command line
+    pub namespace_scope: Option<NamespaceScope>,
+    /// Profiles fields can reference by name
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+fn invalid(reason: impl Into<String>) -> InvalidSettings {
+    InvalidSettings {
+        reason: reason.into(),
+    }
+}
+
+impl Settings {
+    fn validate(self) -> Result<Settings, InvalidSettings> {
+        let defaults = &self.defaults;
+        if !(1..=100).contains(&defaults.length) {
+            return Err(invalid("defaults.length has to be > 0 and <= 100"));
+        }
+        if let Some(charset) = &defaults.charset {
+            if charset.is_empty() || !charset.is_ascii() {
+                return Err(invalid(
+                    "defaults.charset has to be a non-empty ASCII string",
+                ));
+            }
+        }
+        validate_pattern(&defaults.pattern).map_err(|e| invalid(e.to_string()))?;
+        regex::Regex::new(&defaults.pattern).map_err(|e| invalid(e.to_string()))?;
+        if let Some(max_length) = self.max_length {
+            if !(1..=100).contains(&max_length) {
+                return Err(invalid("maxLength has to be > 0 and <= 100"));
+            }
+            if defaults.length > max_length {
+                return Err(invalid("defaults.length exceeds maxLength"));
+            }
+        }
+        if self
+            .allowed_generators
+            .as_ref()
+            .is_some_and(|g| g.is_empty())
+        {
+            return Err(invalid(
+                "allowedGenerators has to contain at least one generator",
+            ));
+        }
+        for (name, profile) in &self.profiles {
+            profile.validate(name, self.max_length)?;
+        }
+        Ok(self)
+    }
+
+    /// Fields of the resource which aren't allowed by the settings
+    pub fn violations(&self, obj: &Arc<impl GeneratedResource>) -> Vec<PolicyViolation> {
+        let mut violations = Vec::new();
+        for id in id_iter(obj) {
+            let profile = annotations::profile(obj, &id);
+            if profile.exists() {
+                let name = profile.get_value();
+                if !self.profiles.contains_key(&name) {
+                    violations.push(PolicyViolation {
+                        id: id.clone(),
+                        reason: format!("profile {:?} doesn't exist", name),
+                    });
+                }
+            }
+            let generator = Generator::of(obj, &id, self);
+            if let Some(allowed) = &self.allowed_generators {
+                if !allowed.contains(&generator) {
+                    violations.push(PolicyViolation {
+                        id: id.clone(),
+                        reason: format!("generator {:?} isn't allowed", generator),
+                    });
+                }
+            }
+            let length = obj
+                .annotations()
+                .get(&V1Annotation::Length.value(&id))
+                .and_then(|l| l.parse::<usize>().ok());
+            match (self.max_length, length) {
+                (Some(max), Some(length)) if generator != Generator::Clone && length > max => {
+                    violations.push(PolicyViolation {
+                        id: id.clone(),
+                        reason: format!("length {} exceeds the maximum of {}", length, max),
+                    })
+                }
+                _ => {}
+            }
+        }
+        violations
+    }
+}
+
+/// Parse the content of a config file in YAML or JSON
+pub fn parse(content: &str) -> Result<Settings, InvalidSettings> {
+    match content.trim().is_empty() {
+        true => Ok(Settings::default()),
+        false => serde_saphyr::from_str::<Settings>(content)
+            .map_err(|e| invalid(e.to_string()))?
+            .validate(),
+    }
+}
+
+pub fn load(path: &Path) -> Result<Settings, InvalidSettings> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| invalid(format!("can't read {:?}: {}", path, e)))?;
+    parse(&content)
+}
+
+/// Make the settings the current ones of the controller
+pub fn apply(shared: &SharedSettings, settings: Settings) {
+    *shared.write().unwrap() = Arc::new(settings);
+}
+
+/// The controllers and the selector of the watched namespaces are set up at startup, so changes
+/// of `namespaces` and `selector` are rejected on reload and the previous ones are kept. Changes
+/// of `denied` are checked on every reconciliation and apply right away.
+fn keep_watched_namespaces(
+    previous: &Settings,
+    mut settings: Settings,
+    command_line: &NamespaceScope,
+) -> Settings {
+    let effective = |s: &Settings| s.namespace_scope.clone().unwrap_or(command_line.clone());
+    let (watched, scope) = (effective(previous), effective(&settings));
+    if scope.namespaces != watched.namespaces || scope.selector != watched.selector {
+        error!(
+            "{} Keeping the watched namespaces until the controller is restarted.",
+            invalid("namespaceScope.namespaces and namespaceScope.selector can't be reloaded.")
+        );
+        settings.namespace_scope = Some(NamespaceScope {
+            denied: scope.denied,
+            ..watched
+        });
+    }
+    settings
+}
+
+/// Reload the config file whenever it changed. Invalid versions are logged and the previous
+/// settings are kept. Files of mounted ConfigMaps are replaced on updates, so the content is
+/// compared instead of watching for events.
+pub async fn watch(path: PathBuf, shared: SharedSettings, command_line: NamespaceScope) {
+    let mut last = std::fs::read_to_string(&path).ok();
+    loop {
+        tokio::time::sleep(RELOAD_INTERVAL).await;
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                error!("Can't read config file {:?}: {}", path, e);
+                continue;
+            }
+        };
+        if last.as_ref() == Some(&content) {
+            continue;
+        }
+        last = Some(content.clone());
+        match parse(&content) {
+            Ok(settings) => {
+                info!("Reloaded config file {:?}", path);
+                let previous = shared.read().unwrap().clone();
+                apply(
+                    &shared,
+                    keep_watched_namespaces(&previous, settings, &command_line),
+                );
+            }
+            Err(e) => error!("{} Keeping the previous settings.", e),
+        }
+    }
+}
+
+/// Settings with the profiles of the tests
+#[cfg(test)]
+pub(crate) fn test_settings() -> Settings {
+    parse(
+        "\
+profiles:
+  pin:
+    length: 6
+    charset: \"0123456789\"
+    history: 2
+  api-token:
+    length: 48
+    pattern: \"[a-f0-9]\"
+    renewalCron: \"0 3 * * 0\"
+    regenerate: true
+",
+    )
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        keep_watched_namespaces, parse, test_settings, Defaults, Generator, Profile, Settings,
+    };
+    use crate::config::{ConcurrencyPolicy, NamespaceScope};
+    use crate::test_utils::build_secret;
+    use rstest::rstest;
+
+    #[rstest]
+    fn parse_settings() {
+        let settings = parse(
+            "\
+defaults:
+  length: 24
+  charset: abc
+maxLength: 64
+allowedGenerators: [charset, clone]
+cronjob:
+  image: registry.example.com/kubectl
+  nodeSelector:
+    kubernetes.io/os: linux
+  concurrencyPolicy: Forbid
+namespaceScope:
+  denied: [kube-system]
+",
+        )
+        .unwrap();
+        assert_eq!(settings.defaults.length, 24);
+        assert_eq!(settings.defaults.charset.as_deref(), Some("abc"));
+        assert_eq!(settings.defaults.pattern, Defaults::default().pattern);
+        assert_eq!(settings.max_length, Some(64));
+        assert_eq!(
+            settings.allowed_generators,
+            Some(vec![Generator::Charset, Generator::Clone])
+        );
+        let cronjob = settings.cronjob.unwrap();
+        assert_eq!(cronjob.image, "registry.example.com/kubectl");
+        assert_eq!(cronjob.service_account, "runo-cronjob");
+        assert_eq!(
+            cronjob.node_selector,
+            vec![("kubernetes.io/os".to_string(), "linux".to_string())]
+        );
+        assert_eq!(cronjob.concurrency_policy, Some(ConcurrencyPolicy::Forbid));
+        assert_eq!(
+            settings.namespace_scope.unwrap().denied,
+            vec!["kube-system"]
+        );
+    }
+
+    #[rstest]
+    fn parse_empty_settings() {
+        assert_eq!(parse("").unwrap(), Settings::default());
+    }
+
+    #[rstest]
+    #[case("defaults:\n  length: 0\n")]
+    #[case("defaults:\n  length: 101\n")]
+    #[case("defaults:\n  charset: \"\"\n")]
+    #[case("defaults:\n  pattern: \"[a-z]+\"\n")]
+    #[case("defaults:\n  pattern: \"[a-z\"\n")]
+    #[case("maxLength: 16\n")]
+    #[case("allowedGenerators: []\n")]
+    #[case("allowedGenerators: [random]\n")]
+    #[case("unknown: true\n")]
+    fn parse_invalid_settings(#[case] content: &str) {
+        assert!(parse(content).is_err());
+    }
+
+    #[rstest]
+    #[case(vec![], Generator::Pattern)]
+    #[case(vec![("v1.secret.runo.rocks/pattern-0", "[a-z]")], Generator::Pattern)]
+    #[case(vec![("v1.secret.runo.rocks/charset-0", "abc")], Generator::Charset)]
+    #[case(vec![("v1.secret.runo.rocks/clone-from-0", "1")], Generator::Clone)]
+    fn generator_of_field(#[case] annotations: Vec<(&str, &str)>, #[case] expected: Generator) {
+        let mut annotations = annotations;
+        annotations.push(("v1.secret.runo.rocks/generate-0", "password"));
+        assert_eq!(
+            Generator::of(&build_secret(annotations), "0", &Settings::default()),
+            expected
+        );
+    }
+
+    #[rstest]
+    fn violations_of_fields() {
+        let settings = parse("maxLength: 40\nallowedGenerators: [pattern]\n").unwrap();
+        let secret = build_secret(vec![
+            ("v1.secret.runo.rocks/generate-0", "username"),
+            ("v1.secret.runo.rocks/length-0", "40"),
+            ("v1.secret.runo.rocks/generate-1", "password"),
+            ("v1.secret.runo.rocks/length-1", "64"),
+            ("v1.secret.runo.rocks/generate-2", "token"),
+            ("v1.secret.runo.rocks/charset-2", "abc"),
+        ]);
+        let violations = settings.violations(&secret);
+        assert_eq!(
+            violations.iter().map(|v| v.id.as_str()).collect::<Vec<_>>(),
+            vec!["1", "2"]
+        );
+        assert!(Settings::default().violations(&secret).is_empty());
+    }
+
+    #[rstest]
+    fn parse_profiles() {
+        let settings = parse(
+            "\
+profiles:
+  pin:
+    length: 6
+    charset: \"0123456789\"
+    maxAge: 90d
+    stagedRotation: true
+",
+        )
+        .unwrap();
+        assert_eq!(
+            settings.profiles.get("pin"),
+            Some(&Profile {
+                length: Some(6),
+                charset: Some("0123456789".to_string()),
+                max_age: Some("90d".to_string()),
+                staged_rotation: Some(true),
+                ..Profile::default()
+            })
+        );
+    }
+
+    #[rstest]
+    #[case("profiles:\n  pin:\n    length: 0\n")]
+    #[case("maxLength: 8\nprofiles:\n  pin:\n    length: 16\n")]
+    #[case("profiles:\n  pin:\n    charset: abc\n    pattern: \"[a-z]\"\n")]
+    #[case("profiles:\n  pin:\n    history: 11\n")]
+    #[case("profiles:\n  pin:\n    maxAge: soon\n")]
+    #[case("profiles:\n  pin:\n    renewalCron: never\n")]
+    #[case("profiles:\n  pin:\n    renewalTimezone: Mars/Olympus\n")]
+    #[case("profiles:\n  pin:\n    policy: strict\n")]
+    fn parse_invalid_profiles(#[case] content: &str) {
+        assert!(parse(content).is_err());
+    }
+
+    #[rstest]
+    fn profile_fingerprint() {
+        let profile = Profile {
+            length: Some(6),
+            charset: Some("0123456789".to_string()),
+            renewal_cron: Some("0 3 * * 0".to_string()),
+            ..Profile::default()
+        };
+        assert_eq!(profile.fingerprint(), "length=6;charset=0123456789;");
+    }
+
+    #[rstest]
+    fn violations_of_unknown_profile() {
+        let settings = parse("profiles:\n  pin:\n    length: 6\n").unwrap();
+        let secret = build_secret(vec![
+            ("v1.secret.runo.rocks/generate-0", "pin"),
+            ("v1.secret.runo.rocks/profile-0", "pin"),
+            ("v1.secret.runo.rocks/generate-1", "password"),
+            ("v1.secret.runo.rocks/profile-1", "postgres-password"),
+        ]);
+        let violations = settings.violations(&secret);
+        assert_eq!(
+            violations.iter().map(|v| v.id.as_str()).collect::<Vec<_>>(),
+            vec!["1"]
+        );
+    }
+
+    #[rstest]
+    fn generator_of_profile() {
+        let settings = test_settings();
+        let secret = build_secret(vec![
+            ("v1.secret.runo.rocks/generate-0", "pin"),
+            ("v1.secret.runo.rocks/profile-0", "pin"),
+            ("v1.secret.runo.rocks/generate-1", "token"),
+            ("v1.secret.runo.rocks/profile-1", "api-token"),
+            ("v1.secret.runo.rocks/generate-2", "code"),
+            ("v1.secret.runo.rocks/profile-2", "pin"),
+            ("v1.secret.runo.rocks/pattern-2", "[a-z]"),
+        ]);
+        assert_eq!(Generator::of(&secret, "0", &settings), Generator::Charset);
+        assert_eq!(Generator::of(&secret, "1", &settings), Generator::Pattern);
+        assert_eq!(Generator::of(&secret, "2", &settings), Generator::Pattern);
+    }
+
+    #[rstest]
+    #[case("namespaceScope:\n  namespaces: [apps, monitoring]\n")]
+    #[case("namespaceScope:\n  selector: team=platform\n")]
+    #[case("maxLength: 64\n")]
+    fn reload_keeps_watched_namespaces(#[case] content: &str) {
+        let command_line = NamespaceScope {
+            namespaces: vec!["apps".to_string()],
+            ..NamespaceScope::default()
+        };
+        let settings =
+            keep_watched_namespaces(&Settings::default(), parse(content).unwrap(), &command_line);
+        let scope = settings.namespace_scope.unwrap_or(command_line.clone());
+        assert_eq!(scope.namespaces, command_line.namespaces);
+        assert_eq!(scope.selector, None);
+    }
+
+    #[rstest]
+    fn reload_applies_denied_namespaces() {
+        let previous = parse("namespaceScope:\n  namespaces: [apps]\n").unwrap();
+        let settings = keep_watched_namespaces(
+            &previous,
+            parse("maxLength: 64\nnamespaceScope:\n  namespaces: [apps, monitoring]\n  denied: [kube-system]\n")
+                .unwrap(),
+            &NamespaceScope::default(),
+        );
+        assert_eq!(settings.max_length, Some(64));
+        assert_eq!(
+            settings.namespace_scope,
+            Some(NamespaceScope {
+                namespaces: vec!["apps".to_string()],
+                selector: None,
+                denied: vec!["kube-system".to_string()],
+            })
+        );
+    }
+}
@@ -0,0 +1,289 @@
+use crate::annotations::{
+    charset, checksum, clone_from, generate, generated_at, generated_with_checksum,
+    generated_with_legacy_checksum, id_iter, is_paused, length, pattern, profile, uses_charset,
+    V1Annotation,
+};
+use crate::k8s::K8s;
+use crate::labels;
+use crate::reconciler;
+use crate::resource::GeneratedResource;
+use crate::schedule;
+use crate::settings::Settings;
+use chrono::{DateTime, Utc};
+use k8s_openapi::api::core::v1::Secret;
+use kube::{Api, ResourceExt};
+use std::sync::Arc;
+
+const HEADER: [&str; 8] = [
+    "SECRET",
+    "ID",
+    "FIELD",
+    "GENERATOR",
+    "GENERATED-AT",
+    "NEXT-RENEWAL",
+    "PAUSED",
+    "CONFIG-CHANGED",
+];
+
+/// State of a single field. Values are never part of it.
+#[derive(Debug, PartialEq)]
+pub struct FieldStatus {
+    pub(crate) secret: String,
+    pub(crate) id: String,
+    pub(crate) field: String,
+    pub(crate) generator: String,
+    pub(crate) generated_at: Option<DateTime<Utc>>,
+    pub(crate) next_renewal: Option<DateTime<Utc>>,
+    pub(crate) paused: bool,
+    pub(crate) config_changed: bool,
+}
+
+/// The controller stores the checksum of the current config with every reconciliation, so a
+/// changed config differs from the one the value was generated with
+fn config_changed(obj: &Arc<impl GeneratedResource>, id: &str, settings: &Settings) -> bool {
+    let checksum = checksum(obj, id);
+    let generated_with_checksum = generated_with_checksum(obj, id);
+    checksum.exists()
+        && generated_with_checksum.exists()
+        && checksum.get_value() != generated_with_checksum.get_value()
+        && !generated_with_legacy_checksum(obj, id, settings)
+}
+
+fn generator(obj: &Arc<impl GeneratedResource>, id: &str, settings: &Settings) -> String {
+    let clone_from = clone_from(obj, id);
+    if clone_from.exists() {
+        return format!("clone-from {}", clone_from.get_value());
+    }
+    let profile = profile(obj, id);
+    let profile = match (profile.exists(), profile.get_value()) {
+        (true, name) if settings.profiles.contains_key(&name) => format!("profile {}, ", name),
+        (true, name) => format!("unknown profile {}, ", name),
+        (false, _) => String::new(),
+    };
+    let length_v1 = V1Annotation::Length.value(id);
+    let length = match obj.annotations().get(&length_v1).map(|l| l.parse::<i32>()) {
+        Some(Err(_)) => "invalid length".to_string(),
+        _ => format!("length {}", length(obj, id, settings).get_value()),
+    };
+    match uses_charset(obj, id, settings) {
+        true => format!(
+            "{}{}, charset of {} characters",
+            profile,
+            length,
+            charset(obj, id, settings).get_value().chars().count()
+        ),
+        false => format!(
+            "{}{}, pattern {}",
+            profile,
+            length,
+            pattern(obj, id, settings).get_value()
+        ),
+    }
+}
+
+fn next_renewal(
+    obj: &Arc<impl GeneratedResource>,
+    id: &str,
+    now: &DateTime<Utc>,
+    settings: &Settings,
+) -> Option<DateTime<Utc>> {
+    let offset = schedule::jitter_offset(obj, id, settings).unwrap_or_default();
+    schedule::next_renewal(obj, id, now, settings)
+        .and_then(|next| next.checked_add_signed(offset))
+        .into_iter()
+        .chain(schedule::max_age_expiry(obj, id, settings))
+        .min()
+}
+
+/// Collect the state of all fields of a managed resource
+pub fn field_statuses(
+    obj: &Arc<impl GeneratedResource>,
+    now: &DateTime<Utc>,
+    settings: &Settings,
+) -> Vec<FieldStatus> {
+    let mut ids = id_iter(obj);
+    ids.sort();
+    ids.iter()
+        .map(|id| FieldStatus {
+            secret: format!("{}/{}", obj.namespace().unwrap_or_default(), obj.name_any()),
+            id: id.clone(),
+            field: generate(obj, id).get_value(),
+            generator: generator(obj, id, settings),
+            generated_at: generated_at(obj, id)
+                .get_value()
+                .parse::<i64>()
+                .ok()
+                .and_then(|t| DateTime::from_timestamp(t, 0)),
+            next_renewal: match is_paused(obj, id) {
+                true => None,
+                false => next_renewal(obj, id, now, settings),
+            },
+            paused: is_paused(obj, id),
+            config_changed: config_changed(obj, id, settings),
+        })
+        .collect()
+}
+
+fn format_time(time: &Option<DateTime<Utc>>) -> String {
+    match time {
+        Some(time) => time.to_rfc3339(),
+        None => "-".to_string(),
+    }
+}
+
+/// Print the states as a table with aligned columns
+pub fn format_table(statuses: &[FieldStatus]) -> String {
+    let rows = std::iter::once(HEADER.map(String::from).to_vec())
+        .chain(statuses.iter().map(|s| {
+            vec![
+                s.secret.clone(),
+                s.id.clone(),
+                s.field.clone(),
+                s.generator.clone(),
+                format_time(&s.generated_at),
+                format_time(&s.next_renewal),
+                s.paused.to_string(),
+                s.config_changed.to_string(),
+            ]
+        }))
+        .collect::<Vec<_>>();
+    let widths = (0..HEADER.len())
+        .map(|i| rows.iter().map(|r| r[i].chars().count()).max().unwrap_or(0))
+        .collect::<Vec<_>>();
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// List the fields of all managed Secrets in the namespace or in all namespaces
+pub async fn status(
+    namespace: Option<&str>,
+    settings: &Settings,
+) -> Result<Vec<FieldStatus>, kube::Error> {
+    let client = K8s::get_client().await;
+    let secrets: Api<Secret> = match namespace {
+        Some(namespace) => Api::namespaced(client, namespace),
+        None => Api::all(client),
+    };
+    let now = Utc::now();
+    Ok(reconciler::list_managed(&secrets)
+        .await?
+        .into_iter()
+        .map(Arc::new)
+        .filter(labels::managed_by_us)
+        .flat_map(|s| field_statuses(&s, &now, settings))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{field_statuses, format_table};
+    use crate::settings::Settings;
+    use crate::test_utils::build_secret_with_data;
+    use chrono::{TimeZone, Utc};
+    use rstest::rstest;
+
+    #[rstest]
+    fn field_statuses_of_secret() {
+        let secret = build_secret_with_data(
+            vec![
+                ("v1.secret.runo.rocks/generate-0", "password"),
+                ("v1.secret.runo.rocks/length-0", "16"),
+                ("v1.secret.runo.rocks/generated-at-0", "1767225600"),
+                ("v1.secret.runo.rocks/renewal-cron-0", "0 0 * * *"),
+                ("v1.secret.runo.rocks/config-checksum-0", "current"),
+                ("v1.secret.runo.rocks/generated-with-checksum-0", "outdated"),
+                ("v1.secret.runo.rocks/generate-1", "token"),
+                ("v1.secret.runo.rocks/charset-1", "abc"),
+                ("v1.secret.runo.rocks/pause-1", "true"),
+            ],
+            vec![("password", "very-secret")],
+        );
+        let now = Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap();
+        let statuses = field_statuses(&secret, &now, &Settings::default());
+        assert_eq!(statuses.len(), 2);
+        assert_eq!(statuses[0].secret, "apps/db");
+        assert_eq!(statuses[0].field, "password");
+        assert!(statuses[0].generator.starts_with("length 16, pattern"));
+        assert_eq!(
+            statuses[0].generated_at,
+            Some(Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            statuses[0].next_renewal,
+            Some(Utc.with_ymd_and_hms(2026, 1, 2, 0, 0, 0).unwrap())
+        );
+        assert!(!statuses[0].paused);
+        assert!(statuses[0].config_changed);
+        assert_eq!(statuses[1].generator, "length 32, charset of 3 characters");
+        assert_eq!(statuses[1].generated_at, None);
+        assert_eq!(statuses[1].next_renewal, None);
+        assert!(statuses[1].paused);
+        assert!(!statuses[1].config_changed);
+    }
+
+    #[rstest]
+    fn field_statuses_with_profile() {
+        let settings = crate::settings::test_settings();
+        let secret = build_secret_with_data(
+            vec![
+                ("v1.secret.runo.rocks/generate-0", "pin"),
+                ("v1.secret.runo.rocks/profile-0", "pin"),
+            ],
+            vec![("password", "very-secret")],
+        );
+        assert_eq!(
+            field_statuses(&secret, &Utc::now(), &settings)[0].generator,
+            "profile pin, length 6, charset of 10 characters"
+        );
+        assert_eq!(
+            field_statuses(&secret, &Utc::now(), &Settings::default())[0].generator,
+            "unknown profile pin, length 32, pattern [a-zA-Z0-9\\-\\_\\(\\)\\%\\$\\@]"
+        );
+    }
+
+    #[rstest]
+    #[case(Some("a"), Some("a"), false)]
+    #[case(Some("b"), Some("a"), true)]
+    #[case(None, Some("a"), false)]
+    #[case(Some("a"), None, false)]
+    fn field_statuses_compare_stored_checksums(
+        #[case] config_checksum: Option<&str>,
+        #[case] generated_with_checksum: Option<&str>,
+        #[case] expected: bool,
+    ) {
+        let mut annotations = vec![("v1.secret.runo.rocks/generate-0", "password")];
+        if let Some(checksum) = config_checksum {
+            annotations.push(("v1.secret.runo.rocks/config-checksum-0", checksum));
+        }
+        if let Some(checksum) = generated_with_checksum {
+            annotations.push(("v1.secret.runo.rocks/generated-with-checksum-0", checksum));
+        }
+        let secret = build_secret_with_data(annotations, vec![("password", "very-secret")]);
+        assert_eq!(
+            field_statuses(&secret, &Utc::now(), &Settings::default())[0].config_changed,
+            expected
+        );
+    }
+
+    #[rstest]
+    fn table_never_contains_values() {
+        let secret = build_secret_with_data(
+            vec![("v1.secret.runo.rocks/generate-0", "password")],
+            vec![("password", "very-secret")],
+        );
+        let table = format_table(&field_statuses(&secret, &Utc::now(), &Settings::default()));
+        assert!(table.starts_with("SECRET   ID  FIELD"));
+        assert!(table.contains("apps/db  0   password"));
+        assert!(!table.contains("very-secret"));
+    }
+}
@@ -0,0 +1,48 @@
+use k8s_openapi::api::core::v1::Secret;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::ByteString;
+use std::sync::Arc;
+
+/// Secret without name and namespace with the given annotations
+pub(crate) fn build_secret_with_annotations(annotations: Vec<(String, String)>) -> Secret {
+    Secret {
+        metadata: ObjectMeta {
+            annotations: Some(annotations.into_iter().collect()),
+            ..ObjectMeta::default()
+        },
+        ..Secret::default()
+    }
+}
+
+/// Secret `apps/db` with the given annotations and without data
+pub(crate) fn build_secret(annotations: Vec<(&str, &str)>) -> Arc<Secret> {
+    Arc::new(Secret {
+        metadata: ObjectMeta {
+            name: Some("db".to_string()),
+            namespace: Some("apps".to_string()),
+            resource_version: Some("42".to_string()),
+            annotations: Some(
+                annotations
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            ),
+            ..ObjectMeta::default()
+        },
+        ..Secret::default()
+    })
+}
+
+/// Secret `apps/db` with the given annotations and data
+pub(crate) fn build_secret_with_data(
+    annotations: Vec<(&str, &str)>,
+    data: Vec<(&str, &str)>,
+) -> Arc<Secret> {
+    let mut secret = build_secret(annotations).as_ref().clone();
+    secret.data = Some(
+        data.into_iter()
+            .map(|(k, v)| (k.to_string(), ByteString(v.as_bytes().to_vec())))
+            .collect(),
+    );
+    Arc::new(secret)
+}
@@ -0,0 +1,257 @@
+use crate::errors::{FollowUpFailed, NoNamespaceForSecret};
+use crate::k8s::K8s;
+use chrono::Utc;
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, StatefulSet};
+use k8s_openapi::api::core::v1::{Container, PodSpec, Secret};
+use k8s_openapi::NamespaceResourceScope;
+use kube::api::{ListParams, Patch};
+use kube::{Api, Resource, ResourceExt};
+use serde::de::DeserializeOwned;
+use std::fmt::Debug;
+use std::sync::Arc;
+use tracing::{debug, error, info};
+
+pub fn get_restarted_at_annotation() -> String {
+    "v1.secret.runo.rocks/restarted-at".to_string()
+}
+
+fn container_references_secret(container: &Container, secret_name: &str) -> bool {
+    let in_env = container.env.iter().flatten().any(|e| {
+        e.value_from
+            .as_ref()
+            .and_then(|v| v.secret_key_ref.as_ref())
+            .is_some_and(|r| r.name == secret_name)
+    });
+    let in_env_from = container
+        .env_from
+        .iter()
+        .flatten()
+        .any(|e| e.secret_ref.as_ref().is_some_and(|r| r.name == secret_name));
+    in_env || in_env_from
+}
+
+/// Check if a pod spec consumes the Secret via env, envFrom or volumes
+pub fn references_secret(pod_spec: &PodSpec, secret_name: &str) -> bool {
+    let in_containers = pod_spec
+        .containers
+        .iter()
+        .chain(pod_spec.init_containers.iter().flatten())
+        .any(|c| container_references_secret(c, secret_name));
+    let in_volumes = pod_spec.volumes.iter().flatten().any(|v| {
+        let in_secret = v
+            .secret
+            .as_ref()
+            .and_then(|s| s.secret_name.as_ref())
+            .is_some_and(|n| n == secret_name);
+        let in_projected = v
+            .projected
+            .as_ref()
+            .and_then(|p| p.sources.as_ref())
+            .is_some_and(|sources| {
+                sources
+                    .iter()
+                    .filter_map(|s| s.secret.as_ref())
+                    .any(|s| s.name == secret_name)
+            });
+        in_secret || in_projected
+    });
+    in_containers || in_volumes
+}
+
+fn build_restart_patch() -> serde_json::Value {
+    serde_json::json!({
+        "spec": {
+            "template": {
+                "metadata": {
+                    "annotations": {
+                        get_restarted_at_annotation(): Utc::now().to_rfc3339()
+                    }
+                }
+            }
+        }
+    })
+}
+
+async fn restart_workloads<K>(
+    namespace: &str,
+    secret_name: &str,
+    k8s: &K8s,
+    pod_spec: fn(&K) -> Option<&PodSpec>,
+) -> bool
+where
+    K: Resource<Scope = NamespaceResourceScope, DynamicType = ()>
+        + Clone
+        + DeserializeOwned
+        + Debug,
+{
+    let workloads: Api<K> = Api::namespaced(K8s::get_client().await, namespace);
+    let list = match workloads.list(&ListParams::default()).await {
+        Ok(list) => list,
+        Err(e) => {
+            error!("Can't list {} in {:?}: {:?}", K::kind(&()), namespace, e);
+            return false;
+        }
+    };
+    let mut restarted = true;
+    for workload in list
+        .into_iter()
+        .filter(|w| pod_spec(w).is_some_and(|s| references_secret(s, secret_name)))
+    {
+        let r = workloads
+            .patch(
+                &workload.name_any(),
+                &k8s.get_merge_patch_params(),
+                &Patch::Merge(build_restart_patch()),
+            )
+            .await;
+        match r {
+            Ok(w) => info!(
+                "{} {:?} restarted because {:?} changed",
+                K::kind(&()),
+                w.name_any(),
+                secret_name
+            ),
+            Err(e) => {
+                error!("{:?}", e);
+                restarted = false
+            }
+        }
+    }
+    restarted
+}
+
+/// Trigger a rolling restart of all Deployments, StatefulSets and DaemonSets in the namespace
+/// of the Secret which consume it
+pub async fn restart(obj: &Arc<Secret>, k8s: &K8s) -> Result<(), FollowUpFailed> {
+    let failed = FollowUpFailed {
+        step: "restart".to_string(),
+    };
+    match obj.namespace() {
+        Some(namespace) => {
+            let secret_name = obj.name_any();
+            debug!("Restarting workloads consuming {:?}", secret_name);
+            let deployments = restart_workloads::<Deployment>(&namespace, &secret_name, k8s, |d| {
+                d.spec.as_ref().and_then(|s| s.template.spec.as_ref())
+            })
+            .await;
+            let stateful_sets =
+                restart_workloads::<StatefulSet>(&namespace, &secret_name, k8s, |s| {
+                    s.spec.as_ref().and_then(|s| s.template.spec.as_ref())
+                })
+                .await;
+            let daemon_sets = restart_workloads::<DaemonSet>(&namespace, &secret_name, k8s, |d| {
+                d.spec.as_ref().and_then(|s| s.template.spec.as_ref())
+            })
+            .await;
+            match deployments && stateful_sets && daemon_sets {
+                true => Ok(()),
+                false => Err(failed),
+            }
+        }
+        None => {
+            error!("{:?}", NoNamespaceForSecret);
+            Err(failed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::references_secret;
+    use k8s_openapi::api::core::v1::{
+        Container, EnvFromSource, EnvVar, EnvVarSource, PodSpec, ProjectedVolumeSource,
+        SecretEnvSource, SecretKeySelector, SecretProjection, SecretVolumeSource, Volume,
+        VolumeProjection,
+    };
+    use rstest::rstest;
+
+    fn build_pod_spec(container: Container, volumes: Vec<Volume>) -> PodSpec {
+        PodSpec {
+            containers: vec![container],
+            volumes: Some(volumes),
+            ..PodSpec::default()
+        }
+    }
+
+    fn env_container(secret_name: &str) -> Container {
+        Container {
+            env: Some(vec![EnvVar {
+                name: "PASSWORD".to_string(),
+                value_from: Some(EnvVarSource {
+                    secret_key_ref: Some(SecretKeySelector {
+                        name: secret_name.to_string(),
+                        key: "password".to_string(),
+                        ..SecretKeySelector::default()
+                    }),
+                    ..EnvVarSource::default()
+                }),
+                ..EnvVar::default()
+            }]),
+            ..Container::default()
+        }
+    }
+
+    fn env_from_container(secret_name: &str) -> Container {
+        Container {
+            env_from: Some(vec![EnvFromSource {
+                secret_ref: Some(SecretEnvSource {
+                    name: secret_name.to_string(),
+                    ..SecretEnvSource::default()
+                }),
+                ..EnvFromSource::default()
+            }]),
+            ..Container::default()
+        }
+    }
+
+    fn secret_volume(secret_name: &str) -> Volume {
+        Volume {
+            name: "secret".to_string(),
+            secret: Some(SecretVolumeSource {
+                secret_name: Some(secret_name.to_string()),
+                ..SecretVolumeSource::default()
+            }),
+            ..Volume::default()
+        }
+    }
+
+    fn projected_volume(secret_name: &str) -> Volume {
+        Volume {
+            name: "projected".to_string(),
+            projected: Some(ProjectedVolumeSource {
+                sources: Some(vec![VolumeProjection {
+                    secret: Some(SecretProjection {
+                        name: secret_name.to_string(),
+                        ..SecretProjection::default()
+                    }),
+                    ..VolumeProjection::default()
+                }]),
+                ..ProjectedVolumeSource::default()
+            }),
+            ..Volume::default()
+        }
+    }
+
+    #[rstest]
+    #[case(build_pod_spec(env_container("db"), vec![]))]
+    #[case(build_pod_spec(env_from_container("db"), vec![]))]
+    #[case(build_pod_spec(Container::default(), vec![secret_volume("db")]))]
+    #[case(build_pod_spec(Container::default(), vec![projected_volume("db")]))]
+    #[case(PodSpec {
+        init_containers: Some(vec![env_container("db")]),
+        ..PodSpec::default()
+    })]
+    fn pod_spec_references_secret(#[case] pod_spec: PodSpec) {
+        assert!(references_secret(&pod_spec, "db"));
+    }
+
+    #[rstest]
+    #[case(build_pod_spec(env_container("other"), vec![]))]
+    #[case(build_pod_spec(env_from_container("other"), vec![]))]
+    #[case(build_pod_spec(Container::default(), vec![secret_volume("other")]))]
+    #[case(build_pod_spec(Container::default(), vec![projected_volume("other")]))]
+    #[case(PodSpec::default())]
+    fn pod_spec_doesnt_reference_secret(#[case] pod_spec: PodSpec) {
+        assert!(!references_secret(&pod_spec, "db"));
+    }
+}
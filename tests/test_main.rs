@@ -3,7 +3,8 @@ use assert_cmd::Command;
 #[test]
 fn runs() {
     let mut cmd = Command::cargo_bin("runo").unwrap();
-    cmd.timeout(std::time::Duration::from_secs(1))
+    cmd.arg("run")
+        .timeout(std::time::Duration::from_secs(1))
         .assert()
         .interrupted();
 }
@@ -21,7 +22,15 @@ fn version() {
 }
 
 #[test]
-fn http_port() {
+fn version_subcommand() {
+    let mut cmd = Command::cargo_bin("runo").unwrap();
+    let assert = cmd.arg("version").assert().success();
+    let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+    assert!(stdout.starts_with("runo "));
+}
+
+#[test]
+fn runs_without_subcommand() {
     let mut cmd = Command::cargo_bin("runo").unwrap();
     cmd.arg("--http-port")
         .arg("0")
@@ -30,10 +39,53 @@ fn http_port() {
         .interrupted();
 }
 
+#[test]
+fn deprecated_mode() {
+    let mut cmd = Command::cargo_bin("runo").unwrap();
+    let assert = cmd
+        .arg("--mode")
+        .arg("reconciliation")
+        .arg("--http-port")
+        .arg("0")
+        .timeout(std::time::Duration::from_secs(1))
+        .assert()
+        .interrupted();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone()).unwrap();
+    assert!(stderr.contains("--mode is deprecated"));
+}
+
+#[test]
+fn invalid_mode() {
+    let mut cmd = Command::cargo_bin("runo").unwrap();
+    cmd.arg("--mode").arg("invalid").assert().failure();
+}
+
+#[test]
+fn http_port_from_env() {
+    let mut cmd = Command::cargo_bin("runo").unwrap();
+    cmd.arg("run")
+        .env("RUNO_HTTP_PORT", "0")
+        .timeout(std::time::Duration::from_secs(1))
+        .assert()
+        .interrupted();
+}
+
+#[test]
+fn http_port() {
+    let mut cmd = Command::cargo_bin("runo").unwrap();
+    cmd.arg("run")
+        .arg("--http-port")
+        .arg("0")
+        .timeout(std::time::Duration::from_secs(1))
+        .assert()
+        .interrupted();
+}
+
 #[test]
 fn dry_run() {
     let mut cmd = Command::cargo_bin("runo").unwrap();
-    cmd.arg("--dry-run")
+    cmd.arg("run")
+        .arg("--dry-run")
         .arg("--http-port")
         .arg("0")
         .timeout(std::time::Duration::from_secs(1))
@@ -44,7 +96,8 @@ fn dry_run() {
 #[test]
 fn requeue_duration() {
     let mut cmd = Command::cargo_bin("runo").unwrap();
-    cmd.arg("--requeue-duration")
+    cmd.arg("run")
+        .arg("--requeue-duration")
         .arg("10")
         .arg("--http-port")
         .arg("0")
@@ -52,3 +105,115 @@ fn requeue_duration() {
         .assert()
         .interrupted();
 }
+
+#[test]
+fn renewal_mode() {
+    let mut cmd = Command::cargo_bin("runo").unwrap();
+    cmd.arg("run")
+        .arg("--renewal-mode")
+        .arg("cronjob")
+        .arg("--http-port")
+        .arg("0")
+        .timeout(std::time::Duration::from_secs(1))
+        .assert()
+        .interrupted();
+}
+
+#[test]
+fn invalid_renewal_mode() {
+    let mut cmd = Command::cargo_bin("runo").unwrap();
+    cmd.arg("run")
+        .arg("--renewal-mode")
+        .arg("invalid")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn lint_valid_manifest() {
+    let mut cmd = Command::cargo_bin("runo").unwrap();
+    cmd.arg("lint")
+        .arg("-")
+        .write_stdin(
+            "kind: Secret\nmetadata:\n  name: example\n  labels:\n    v1.secret.runo.rocks/managed: \"true\"\n  annotations:\n    v1.secret.runo.rocks/generate-0: password\n",
+        )
+        .assert()
+        .success();
+}
+
+#[test]
+fn lint_invalid_manifest() {
+    let mut cmd = Command::cargo_bin("runo").unwrap();
+    let assert = cmd
+        .arg("lint")
+        .arg("-")
+        .write_stdin(
+            "kind: Secret\nmetadata:\n  name: example\n  labels:\n    v1.secret.runo.rocks/managed: \"true\"\n  annotations:\n    v1.secret.runo.rocks/generate-0: password\n    v1.secret.runo.rocks/length-0: \"0\"\n",
+        )
+        .assert()
+        .failure();
+    let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+    assert!(stdout.starts_with("<stdin>:8:"));
+}
+
+#[test]
+fn generate_manifest() {
+    let mut cmd = Command::cargo_bin("runo").unwrap();
+    let assert = cmd
+        .arg("generate")
+        .arg("-f")
+        .arg("-")
+        .write_stdin(
+            "kind: Secret\nmetadata:\n  name: example\n  labels:\n    v1.secret.runo.rocks/managed: \"true\"\n  annotations:\n    v1.secret.runo.rocks/generate-0: password\n",
+        )
+        .assert()
+        .success();
+    let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+    assert!(stdout.contains("password:"));
+    assert!(stdout.contains("v1.secret.runo.rocks/generated-at-0"));
+}
+
+#[test]
+fn plugin_help() {
+    let mut cmd = Command::cargo_bin("kubectl-runo").unwrap();
+    let assert = cmd.arg("--help").assert().success();
+    let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+    assert!(stdout.contains("add-field"));
+}
+
+#[test]
+fn plugin_rejects_invalid_length() {
+    let mut cmd = Command::cargo_bin("kubectl-runo").unwrap();
+    cmd.args(["add-field", "db", "password", "--length", "0"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn invalid_config_file() {
+    let mut cmd = Command::cargo_bin("runo").unwrap();
+    cmd.args(["once", "--config-file", "/nonexistent/runo.yaml"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn lint_with_config_file() {
+    let config_file = std::env::temp_dir().join("runo-lint-profiles.yaml");
+    std::fs::write(&config_file, "profiles:\n  pin:\n    length: 6\n").unwrap();
+    let manifest = "kind: Secret\nmetadata:\n  name: example\n  labels:\n    v1.secret.runo.rocks/managed: \"true\"\n  annotations:\n    v1.secret.runo.rocks/generate-0: pin\n    v1.secret.runo.rocks/profile-0: pin\n";
+    Command::cargo_bin("runo")
+        .unwrap()
+        .args(["lint", "-"])
+        .write_stdin(manifest)
+        .assert()
+        .failure();
+    Command::cargo_bin("runo")
+        .unwrap()
+        .args(["lint", "--config-file"])
+        .arg(&config_file)
+        .arg("-")
+        .write_stdin(manifest)
+        .assert()
+        .success();
+}